@@ -0,0 +1,154 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Access control for the daemon socket.
+//!
+//! Anyone who can connect to the socket may read status. Requests that
+//! change fan control are only accepted from root and the users and groups
+//! listed in [`DaemonConfig::control_users`] and
+//! [`DaemonConfig::control_groups`]. Clients are identified by the
+//! credentials the kernel attaches to the connection (`SO_PEERCRED`), so
+//! they can't be spoofed by the client.
+
+use crate::config::DaemonConfig;
+use nix::unistd::{Gid, Group, Uid, User};
+use std::collections::HashSet;
+use std::ffi::CString;
+
+/// Credentials of a connected client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub uid: u32,
+    pub gid: u32,
+    /// Supplementary groups from the user database
+    pub groups: Vec<u32>,
+}
+
+impl Peer {
+    /// Build from the connection's peer credentials, looking up the
+    /// user's supplementary groups.
+    pub fn from_cred(uid: u32, gid: u32) -> Self {
+        let groups = User::from_uid(Uid::from_raw(uid))
+            .ok()
+            .flatten()
+            .and_then(|user| CString::new(user.name).ok())
+            .and_then(|name| nix::unistd::getgrouplist(&name, Gid::from_raw(gid)).ok())
+            .map(|gids| gids.into_iter().map(Gid::as_raw).collect())
+            .unwrap_or_default();
+        Self { uid, gid, groups }
+    }
+
+    /// User name if known, otherwise the uid, e.g. "alice" or "uid 1000".
+    pub fn describe(&self) -> String {
+        match User::from_uid(Uid::from_raw(self.uid)) {
+            Ok(Some(user)) => user.name,
+            _ => format!("uid {}", self.uid),
+        }
+    }
+}
+
+/// Who may send mutating requests, resolved to numeric ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessPolicy {
+    uids: HashSet<u32>,
+    gids: HashSet<u32>,
+}
+
+impl AccessPolicy {
+    /// Resolve the allow-lists in `config`. Names that don't exist are
+    /// logged and skipped.
+    pub fn from_config(config: &DaemonConfig) -> Self {
+        let mut policy = Self::default();
+        for user in &config.control_users {
+            match lookup_user(user) {
+                Some(uid) => {
+                    policy.uids.insert(uid);
+                }
+                None => log::warn!("Ignoring unknown user '{user}' in control_users"),
+            }
+        }
+        for group in &config.control_groups {
+            match lookup_group(group) {
+                Some(gid) => {
+                    policy.gids.insert(gid);
+                }
+                None => log::warn!("Ignoring unknown group '{group}' in control_groups"),
+            }
+        }
+        policy
+    }
+
+    /// Whether `peer` may change fan control. Root always may.
+    pub fn may_control(&self, peer: &Peer) -> bool {
+        peer.uid == 0
+            || self.uids.contains(&peer.uid)
+            || self.gids.contains(&peer.gid)
+            || peer.groups.iter().any(|g| self.gids.contains(g))
+    }
+}
+
+/// Resolve a user name or numeric uid.
+pub fn lookup_user(user: &str) -> Option<u32> {
+    if let Ok(uid) = user.parse() {
+        return Some(uid);
+    }
+    User::from_name(user).ok().flatten().map(|u| u.uid.as_raw())
+}
+
+/// Resolve a group name or numeric gid.
+pub fn lookup_group(group: &str) -> Option<u32> {
+    if let Ok(gid) = group.parse() {
+        return Some(gid);
+    }
+    Group::from_name(group)
+        .ok()
+        .flatten()
+        .map(|g| g.gid.as_raw())
+}
+
+/// Parse octal permission bits such as "0660" or "660".
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    let digits = mode.trim().trim_start_matches("0o");
+    match u32::from_str_radix(digits, 8) {
+        Ok(bits) if bits <= 0o777 => Ok(bits),
+        _ => Err(format!(
+            "Invalid socket mode '{mode}', expected octal like \"0660\""
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_by_uid_and_group() {
+        let config = DaemonConfig {
+            control_users: vec!["1000".to_string(), "no-such-user-here".to_string()],
+            control_groups: vec!["2000".to_string()],
+            ..DaemonConfig::default()
+        };
+        let policy = AccessPolicy::from_config(&config);
+        let peer = |uid, gid, groups: &[u32]| Peer {
+            uid,
+            gid,
+            groups: groups.to_vec(),
+        };
+
+        assert!(policy.may_control(&peer(0, 0, &[])));
+        assert!(policy.may_control(&peer(1000, 1000, &[])));
+        assert!(policy.may_control(&peer(1001, 2000, &[])));
+        assert!(policy.may_control(&peer(1002, 1002, &[10, 2000])));
+        assert!(!policy.may_control(&peer(1003, 1003, &[10])));
+        assert!(!AccessPolicy::default().may_control(&peer(1000, 1000, &[])));
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("0660"), Ok(0o660));
+        assert_eq!(parse_mode("644"), Ok(0o644));
+        assert_eq!(parse_mode("0o600"), Ok(0o600));
+        assert!(parse_mode("0999").is_err());
+        assert!(parse_mode("rw-rw----").is_err());
+    }
+}
@@ -0,0 +1,772 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! fanctl: non-interactive client for scripts and one-off commands.
+//!
+//! Sends a single request to the daemon (or streams pushes for `watch`)
+//! and exits with a status that says how it went, so shell scripts can
+//! branch on it.
+
+use chrono::{DateTime, Local};
+use clap::{Args, Parser, Subcommand};
+use linux_fan_utility::config::{self, FanAssignment};
+use linux_fan_utility::curve::{CurvePoint, FanCurve, InputUnit};
+use linux_fan_utility::history::{self, Sample};
+use linux_fan_utility::hwmon::{FanStatus, TempStatus};
+use linux_fan_utility::protocol::{self, Envelope, FanAssignmentInfo, Push, Request, Response};
+use linux_fan_utility::schedule::ScheduleStatus;
+use linux_fan_utility::trigger::TriggerStatus;
+use nix::sys::signal::{self, SigHandler, Signal};
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// The daemon answered with an error.
+const EXIT_DAEMON_ERROR: u8 = 1;
+/// Could not reach the daemon, or it speaks another protocol version.
+const EXIT_UNAVAILABLE: u8 = 3;
+/// The daemon refused the request for this user.
+const EXIT_DENIED: u8 = 4;
+
+// ---------------------------------------------------------------------------
+// CLI
+// ---------------------------------------------------------------------------
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "fanctl",
+    about = "Linux fan control command-line client",
+    after_help = "Exit status: 0 success, 1 daemon error, 2 usage error, \
+                  3 daemon unavailable or incompatible, 4 permission denied"
+)]
+struct Cli {
+    /// Path to the daemon socket.
+    #[arg(short, long, default_value = config::DEFAULT_SOCKET_PATH)]
+    socket: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show fans, temperatures and assignments.
+    Status {
+        /// Print the daemon's status as JSON.
+        #[arg(long)]
+        json: bool,
+
+        /// Include fans and sensors hidden by ignore rules.
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Change how a fan is controlled.
+    #[command(subcommand)]
+    Set(SetCommand),
+
+    /// Manage fan curves.
+    #[command(subcommand)]
+    Curve(CurveCommand),
+
+    /// Switch between named sets of fan assignments.
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+
+    /// Show recorded readings for sensors and fans.
+    History {
+        /// Sensor or fan ids; all of them if none are given.
+        ids: Vec<String>,
+
+        /// How far back to go, in seconds.
+        #[arg(long, default_value_t = 600)]
+        since: u64,
+
+        /// Average readings over this many seconds per line.
+        #[arg(long)]
+        resolution: Option<u64>,
+
+        /// Print the samples as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Give a fan or sensor a friendly name, or clear it.
+    Label {
+        /// Fan or sensor id.
+        id: String,
+        /// New name; leave out to go back to the kernel's label.
+        label: Option<String>,
+    },
+
+    /// Save the daemon's configuration to disk.
+    Save,
+
+    /// Reload the daemon's configuration from disk.
+    Reload,
+
+    /// Stream events (and optionally status) until interrupted.
+    Watch {
+        /// Print every push as a JSON line.
+        #[arg(long)]
+        json: bool,
+
+        /// Also print a line per fan after every control-loop tick.
+        #[arg(long)]
+        status: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SetCommand {
+    /// Hold a fan at a fixed PWM.
+    Manual {
+        fan_id: String,
+        /// PWM duty 0-255
+        pwm: u8,
+    },
+
+    /// Hand a fan back to BIOS/firmware control.
+    Auto { fan_id: String },
+
+    /// Drive a fan from a curve tracking a sensor.
+    Curve {
+        fan_id: String,
+        curve_name: String,
+        temp_sensor_id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CurveCommand {
+    /// List curve names.
+    List {
+        /// Print the curves as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show one curve's points and settings.
+    Show {
+        name: String,
+        /// Print the curve as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Create or replace a curve.
+    Add(CurveArgs),
+
+    /// Delete a curve.
+    Rm { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// List profiles, marking the active one.
+    List {
+        /// Print the profiles as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Switch every fan to a profile's assignments.
+    Set { name: String },
+
+    /// Save the current assignments as a profile.
+    Save { name: String },
+
+    /// Delete a profile.
+    Rm { name: String },
+}
+
+#[derive(Args, Debug)]
+struct CurveArgs {
+    name: String,
+
+    /// Points as INPUT:PWM, e.g. 30:60 50:128 80:255
+    #[arg(required = true, value_parser = parse_point)]
+    points: Vec<CurvePoint>,
+
+    /// What the x-axis measures: celsius, percent, load, rpm or other.
+    #[arg(long, default_value_t = InputUnit::Celsius)]
+    unit: InputUnit,
+
+    /// Hysteresis band, in the curve's unit.
+    #[arg(long, default_value_t = 0.0)]
+    hysteresis: f64,
+
+    /// Minimum time between PWM changes, in milliseconds.
+    #[arg(long, default_value_t = 0)]
+    hold_ms: u64,
+
+    /// Max PWM increase per second (0 = unlimited).
+    #[arg(long, default_value_t = 0.0)]
+    ramp_up: f64,
+
+    /// Max PWM decrease per second (0 = unlimited).
+    #[arg(long, default_value_t = 0.0)]
+    ramp_down: f64,
+
+    /// Scale PWM into each fan's calibrated range.
+    #[arg(long)]
+    relative: bool,
+}
+
+fn parse_point(s: &str) -> Result<CurvePoint, String> {
+    let (input, pwm) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected INPUT:PWM, got '{s}'"))?;
+    Ok(CurvePoint {
+        input: input
+            .trim()
+            .parse()
+            .map_err(|e| format!("bad input '{input}': {e}"))?,
+        pwm: pwm
+            .trim()
+            .parse()
+            .map_err(|e| format!("bad PWM '{pwm}': {e}"))?,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Connection
+// ---------------------------------------------------------------------------
+
+/// A failed command, with the exit status it maps to.
+struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: u8, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn unavailable(e: io::Error) -> Self {
+        Self::new(EXIT_UNAVAILABLE, format!("Connection error: {e}"))
+    }
+}
+
+struct Connection {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    last_id: u64,
+}
+
+impl Connection {
+    /// Connect and check the daemon speaks our protocol version.
+    fn open(path: &str) -> Result<Self, Failure> {
+        let stream = UnixStream::connect(path).map_err(|e| {
+            Failure::new(
+                EXIT_UNAVAILABLE,
+                format!("Could not connect to {path}: {e}. Is fanctl-daemon running?"),
+            )
+        })?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .map_err(Failure::unavailable)?;
+        let reader = BufReader::new(stream.try_clone().map_err(Failure::unavailable)?);
+        let mut conn = Self {
+            stream,
+            reader,
+            last_id: 0,
+        };
+
+        let hello = Request::Hello {
+            protocol_version: protocol::PROTOCOL_VERSION,
+            client: Some(format!("fanctl {}", env!("CARGO_PKG_VERSION"))),
+        };
+        match conn.request(&hello)? {
+            Response::Hello {
+                protocol_version, ..
+            } if protocol::is_compatible(protocol_version) => Ok(conn),
+            Response::Hello {
+                protocol_version,
+                daemon_version,
+                ..
+            } => Err(Failure::new(
+                EXIT_UNAVAILABLE,
+                format!(
+                    "Incompatible daemon: fanctl-daemon {daemon_version} speaks protocol \
+                     {protocol_version}, fanctl speaks {}",
+                    protocol::PROTOCOL_VERSION
+                ),
+            )),
+            _ => Err(Failure::new(
+                EXIT_UNAVAILABLE,
+                "Incompatible daemon: fanctl-daemon is too old to report its protocol version",
+            )),
+        }
+    }
+
+    /// Send a request and wait for its reply.
+    fn request(&mut self, req: &Request) -> Result<Response, Failure> {
+        self.last_id += 1;
+        let id = self.last_id;
+        let encoded = protocol::encode(&Envelope::new(Some(id), req))
+            .map_err(|e| Failure::new(EXIT_UNAVAILABLE, format!("Encode error: {e}")))?;
+        self.stream
+            .write_all(encoded.as_bytes())
+            .map_err(Failure::unavailable)?;
+        loop {
+            let reply = self.read_message()?;
+            if !reply.body.is_push() && reply.id.is_none_or(|r| r == id) {
+                return Ok(reply.body);
+            }
+        }
+    }
+
+    /// Send a request that is answered with `Ok`, mapping failures to
+    /// exit statuses.
+    fn command(&mut self, req: &Request) -> Result<String, Failure> {
+        match self.request(req)? {
+            Response::Ok { message } => Ok(message),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn read_message(&mut self) -> Result<Envelope<Response>, Failure> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(Failure::new(
+                EXIT_UNAVAILABLE,
+                "Daemon closed the connection",
+            )),
+            Ok(_) => protocol::decode(&line)
+                .map_err(|e| Failure::new(EXIT_UNAVAILABLE, format!("Decode error: {e}"))),
+            Err(e) => Err(Failure::unavailable(e)),
+        }
+    }
+}
+
+/// Turn a reply the command didn't ask for into a failure.
+fn unexpected(response: Response) -> Failure {
+    match response {
+        Response::Error { message } => Failure::new(EXIT_DAEMON_ERROR, message),
+        Response::Denied { message } => {
+            Failure::new(EXIT_DENIED, format!("Permission denied: {message}"))
+        }
+        other => Failure::new(
+            EXIT_DAEMON_ERROR,
+            format!("Unexpected reply from daemon: {other:?}"),
+        ),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Main
+// ---------------------------------------------------------------------------
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    // Die quietly like other tools when piped into `head` and the like,
+    // rather than panicking on the next print
+    // SAFETY: nothing else is running yet to observe the change
+    unsafe {
+        let _ = signal::signal(Signal::SIGPIPE, SigHandler::SigDfl);
+    }
+
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            eprintln!("fanctl: {}", failure.message);
+            ExitCode::from(failure.code)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Failure> {
+    let mut conn = Connection::open(&cli.socket)?;
+
+    match cli.command {
+        Command::Status { json, all } => match conn.request(&Request::GetStatus)? {
+            Response::Status {
+                fans,
+                temps,
+                assignments,
+                active_profile,
+                schedule,
+                trigger,
+            } => {
+                if json {
+                    print_json(&StatusJson {
+                        fans: &fans,
+                        temps: &temps,
+                        assignments: &assignments,
+                        active_profile: active_profile.as_deref(),
+                        schedule: schedule.as_ref(),
+                        trigger: trigger.as_ref(),
+                    });
+                } else {
+                    if let Some(name) = &active_profile {
+                        println!("Profile: {name}");
+                    }
+                    if let Some(schedule) = &schedule {
+                        println!("Schedule: {}", schedule.describe());
+                    }
+                    if let Some(trigger) = &trigger {
+                        println!("Trigger: {}", trigger.describe());
+                    }
+                    if active_profile.is_some() || schedule.is_some() || trigger.is_some() {
+                        println!();
+                    }
+                    print_status(&fans, &temps, &assignments, all);
+                }
+            }
+            other => return Err(unexpected(other)),
+        },
+
+        Command::Set(set) => {
+            let req = match set {
+                SetCommand::Manual { fan_id, pwm } => Request::SetManual { fan_id, pwm },
+                SetCommand::Auto { fan_id } => Request::SetAuto { fan_id },
+                SetCommand::Curve {
+                    fan_id,
+                    curve_name,
+                    temp_sensor_id,
+                } => Request::SetCurve {
+                    fan_id,
+                    curve_name,
+                    temp_sensor_id,
+                    smoothing: None,
+                },
+            };
+            println!("{}", conn.command(&req)?);
+        }
+
+        Command::Curve(CurveCommand::List { json }) => {
+            let curves = list_curves(&mut conn)?;
+            if json {
+                print_json(&curves);
+            } else {
+                for curve in &curves {
+                    println!("{}", curve.name);
+                }
+            }
+        }
+
+        Command::Curve(CurveCommand::Show { name, json }) => {
+            let curve = list_curves(&mut conn)?
+                .into_iter()
+                .find(|c| c.name == name)
+                .ok_or_else(|| {
+                    Failure::new(EXIT_DAEMON_ERROR, format!("Curve '{name}' not found"))
+                })?;
+            if json {
+                print_json(&curve);
+            } else {
+                print_curve(&curve);
+            }
+        }
+
+        Command::Curve(CurveCommand::Add(args)) => {
+            let req = Request::UpsertCurve {
+                name: args.name,
+                points: args.points,
+                hysteresis: args.hysteresis,
+                hold_ms: args.hold_ms,
+                ramp_up_rate: args.ramp_up,
+                ramp_down_rate: args.ramp_down,
+                relative: args.relative,
+                unit: args.unit,
+            };
+            println!("{}", conn.command(&req)?);
+        }
+
+        Command::Curve(CurveCommand::Rm { name }) => {
+            println!("{}", conn.command(&Request::DeleteCurve { name })?);
+        }
+
+        Command::Profile(ProfileCommand::List { json }) => {
+            let profiles = match conn.request(&Request::ListProfiles)? {
+                Response::Profiles { profiles } => profiles,
+                other => return Err(unexpected(other)),
+            };
+            if json {
+                print_json(&profiles);
+            } else {
+                for p in &profiles {
+                    let marker = match (p.active, p.modified) {
+                        (true, true) => " (active, modified)",
+                        (true, false) => " (active)",
+                        _ => "",
+                    };
+                    println!("{}{marker}", p.name);
+                }
+            }
+        }
+
+        Command::Profile(ProfileCommand::Set { name }) => {
+            println!("{}", conn.command(&Request::SetProfile { name })?);
+        }
+
+        Command::Profile(ProfileCommand::Save { name }) => {
+            println!("{}", conn.command(&Request::SaveProfile { name })?);
+        }
+
+        Command::Profile(ProfileCommand::Rm { name }) => {
+            println!("{}", conn.command(&Request::DeleteProfile { name })?);
+        }
+
+        Command::History {
+            ids,
+            since,
+            resolution,
+            json,
+        } => {
+            let req = Request::GetHistory {
+                ids: ids.clone(),
+                since: Some(history::now_ms().saturating_sub(since.saturating_mul(1000))),
+                resolution: resolution.map(|r| r.saturating_mul(1000)),
+            };
+            let samples = match conn.request(&req)? {
+                Response::History { samples } => samples,
+                other => return Err(unexpected(other)),
+            };
+            if json {
+                print_json(&samples);
+            } else {
+                print_history(&ids, &samples);
+            }
+        }
+
+        Command::Label { id, label } => {
+            println!(
+                "{}",
+                conn.command(&Request::SetLabel { target: id, label })?
+            );
+        }
+
+        Command::Save => println!("{}", conn.command(&Request::SaveConfig)?),
+
+        Command::Reload => println!("{}", conn.command(&Request::ReloadConfig)?),
+
+        Command::Watch { json, status } => watch(conn, json, status)?,
+    }
+
+    Ok(())
+}
+
+fn list_curves(conn: &mut Connection) -> Result<Vec<FanCurve>, Failure> {
+    match conn.request(&Request::ListCurves)? {
+        Response::Curves { curves } => Ok(curves),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Subscribe and print pushes until the daemon goes away.
+fn watch(mut conn: Connection, json: bool, status: bool) -> Result<(), Failure> {
+    conn.command(&Request::Subscribe)?;
+    // Events can be far apart
+    conn.stream
+        .set_read_timeout(None)
+        .map_err(Failure::unavailable)?;
+
+    loop {
+        let Response::Push { push } = conn.read_message()?.body else {
+            continue;
+        };
+        match push {
+            Push::Status { .. } if json && !status => {}
+            push if json => print_json(&push),
+            Push::Status { fans, .. } => {
+                if status {
+                    for fan in &fans {
+                        println!("{}", describe_fan(fan));
+                    }
+                }
+            }
+            Push::Event { event } => println!("{}", event.describe()),
+        }
+        // Let `fanctl watch | grep ...` see lines as they arrive
+        let _ = io::stdout().flush();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Output
+// ---------------------------------------------------------------------------
+
+/// `fanctl status --json` output.
+#[derive(Serialize)]
+struct StatusJson<'a> {
+    fans: &'a [FanStatus],
+    temps: &'a [TempStatus],
+    assignments: &'a [FanAssignmentInfo],
+    active_profile: Option<&'a str>,
+    schedule: Option<&'a ScheduleStatus>,
+    trigger: Option<&'a TriggerStatus>,
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(s) => println!("{s}"),
+        Err(e) => eprintln!("fanctl: could not encode JSON: {e}"),
+    }
+}
+
+/// Fans and sensors hidden by ignore rules are left out unless `all`.
+fn print_status(
+    fans: &[FanStatus],
+    temps: &[TempStatus],
+    assignments: &[FanAssignmentInfo],
+    all: bool,
+) {
+    println!(
+        "{:<24} {:<16} {:>7} {:>5}  ASSIGNMENT",
+        "FAN", "LABEL", "RPM", "PWM"
+    );
+    for fan in fans.iter().filter(|f| all || !f.hidden) {
+        let assignment = assignments.iter().find(|a| a.fan_id == fan.id).map_or_else(
+            || "unassigned".to_string(),
+            |a| describe_assignment(&a.assignment),
+        );
+        let mut notes = String::new();
+        if fan.stalled {
+            notes.push_str(" [STALLED]");
+        }
+        if let Some(forced) = &fan.forced {
+            notes.push_str(&format!(" [{forced}]"));
+        }
+        if let Some(calibrating) = &fan.calibrating {
+            notes.push_str(&format!(" [calibrating: {calibrating}]"));
+        }
+        if fan.hidden {
+            notes.push_str(" [hidden]");
+        }
+        println!(
+            "{:<24} {:<16} {:>7} {:>5}  {assignment}{notes}",
+            fan.id,
+            fan.label.as_deref().unwrap_or("-"),
+            fan.rpm.map_or_else(|| "-".to_string(), |r| r.to_string()),
+            fan.pwm.map_or_else(|| "-".to_string(), |p| p.to_string()),
+        );
+    }
+
+    println!();
+    println!("{:<24} {:<16} {:>8}", "SENSOR", "LABEL", "READING");
+    for temp in temps.iter().filter(|t| all || !t.hidden) {
+        println!(
+            "{:<24} {:<16} {:>8}{}",
+            temp.id,
+            temp.label.as_deref().unwrap_or("-"),
+            temp.temp_c
+                .map_or_else(|| "-".to_string(), |t| temp.unit.format(t)),
+            if temp.hidden { "  [hidden]" } else { "" },
+        );
+    }
+
+    let hidden =
+        fans.iter().filter(|f| f.hidden).count() + temps.iter().filter(|t| t.hidden).count();
+    if !all && hidden > 0 {
+        println!();
+        println!("{hidden} hidden by ignore rules; --all shows them");
+    }
+}
+
+/// One line per sample: the time, then a column per id. Fans show
+/// PWM/RPM.
+fn print_history(ids: &[String], samples: &[Sample]) {
+    let mut columns: Vec<&String> = ids.iter().collect();
+    if columns.is_empty() {
+        let mut seen = std::collections::BTreeSet::new();
+        for sample in samples {
+            seen.extend(sample.sensors.keys());
+            seen.extend(sample.fans.keys());
+        }
+        columns = seen.into_iter().collect();
+    }
+    let width = |id: &str| id.len().max(9);
+
+    print!("{:<8}", "TIME");
+    for id in &columns {
+        print!("  {id:>w$}", w = width(id));
+    }
+    println!();
+    for sample in samples {
+        let time = DateTime::from_timestamp_millis(sample.time_ms as i64)
+            .map(|t| t.with_timezone(&Local).format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        print!("{time:<8}");
+        for id in &columns {
+            let cell = if let Some(v) = sample.sensors.get(*id) {
+                format!("{v:.1}")
+            } else if let Some(fan) = sample.fans.get(*id) {
+                format!(
+                    "{}/{}",
+                    fan.pwm.map_or_else(|| "-".to_string(), |p| p.to_string()),
+                    fan.rpm.map_or_else(|| "-".to_string(), |r| r.to_string()),
+                )
+            } else {
+                "-".to_string()
+            };
+            print!("  {cell:>w$}", w = width(id));
+        }
+        println!();
+    }
+}
+
+fn print_curve(curve: &FanCurve) {
+    println!("Curve: {}", curve.name);
+    if !curve.unit.is_celsius() {
+        println!("Input: {}", curve.unit.quantity());
+    }
+    for p in &curve.points {
+        println!("  {:>8} -> PWM {}", curve.unit.format(p.input), p.pwm);
+    }
+    if curve.hysteresis > 0.0 || curve.hold_ms > 0 {
+        println!(
+            "Hysteresis: {}, hold {} ms",
+            curve.unit.format(curve.hysteresis),
+            curve.hold_ms
+        );
+    }
+    if curve.ramp_up_rate > 0.0 || curve.ramp_down_rate > 0.0 {
+        println!(
+            "Ramp: up {}/s, down {}/s",
+            curve.ramp_up_rate, curve.ramp_down_rate
+        );
+    }
+    if curve.relative {
+        println!("Relative: PWM scaled into each fan's calibrated range");
+    }
+}
+
+fn describe_assignment(assignment: &FanAssignment) -> String {
+    match assignment {
+        FanAssignment::Auto => "auto".to_string(),
+        FanAssignment::Manual { pwm } => format!("manual PWM {pwm}"),
+        FanAssignment::Curve {
+            curve_name,
+            temp_sensor_id,
+            ..
+        } => format!("curve {curve_name} on {temp_sensor_id}"),
+        FanAssignment::MultiCurve { inputs, combiner } => {
+            format!("{combiner:?} of {} curves", inputs.len()).to_lowercase()
+        }
+        FanAssignment::Pid {
+            temp_sensor_id,
+            setpoint_c,
+            ..
+        } => format!("PID {temp_sensor_id} at {setpoint_c:.1}°C"),
+    }
+}
+
+fn describe_fan(fan: &FanStatus) -> String {
+    format!(
+        "{} rpm={} pwm={}{}",
+        fan.id,
+        fan.rpm.map_or_else(|| "-".to_string(), |r| r.to_string()),
+        fan.pwm.map_or_else(|| "-".to_string(), |p| p.to_string()),
+        if fan.stalled { " stalled" } else { "" }
+    )
+}
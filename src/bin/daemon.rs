@@ -4,16 +4,36 @@
 //! fanctl-daemon: system service that owns hwmon writes, runs fan curves,
 //! and accepts commands from TUI clients over a Unix domain socket.
 
+use chrono::{Local, NaiveDateTime};
 use clap::Parser;
-use linux_fan_utility::config::{self, Config, FanAssignment};
-use linux_fan_utility::curve::FanCurve;
-use linux_fan_utility::hwmon::{self, Fan, TempSensor};
-use linux_fan_utility::protocol::{self, FanAssignmentInfo, Request, Response};
+use linux_fan_utility::access::{self, AccessPolicy, Peer};
+use linux_fan_utility::calibration::{self, CalibrationProgress, Calibrator};
+use linux_fan_utility::config::{self, Combiner, Config, CurveInputRef, FanAssignment, Profile};
+use linux_fan_utility::curve::{CurveEvaluator, FanCurve, InputUnit, SlewLimiter};
+use linux_fan_utility::failsafe::{self, Failsafe, FailsafeAction, FailsafeChange, FailsafeScope};
+use linux_fan_utility::history::{self, FanSample, History, Sample};
+use linux_fan_utility::hwmon::{self, Fan, FanStatus, HwmonRoot, TempSensor, TempStatus};
+use linux_fan_utility::ignore::IgnoreRules;
+use linux_fan_utility::labels::SensorsConf;
+use linux_fan_utility::metrics::{self, MetricsEndpoint, MetricsSnapshot};
+use linux_fan_utility::pid::{PidController, PidParams, PidTerms};
+use linux_fan_utility::protocol::{
+    self, DaemonEvent, Envelope, FanAssignmentInfo, ProfileInfo, Push, Request, Response,
+};
+use linux_fan_utility::schedule::{self, ScheduleRule, ScheduleStatus, Scheduler};
+use linux_fan_utility::smoothing::TempFilter;
+use linux_fan_utility::stall::{StallChange, StallDetector};
+use linux_fan_utility::sysload::{LoadSensor, LoadSensors, ProcRoot};
+use linux_fan_utility::trigger::{SystemSample, TriggerChange, TriggerStatus, Triggers};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::{UnixListener, UnixStream};
-use tokio::sync::{Mutex, Notify};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, UnixListener, UnixStream};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::{Mutex, Semaphore, broadcast, watch};
 use tokio::time::{self, Duration};
 
 // ---------------------------------------------------------------------------
@@ -30,6 +50,14 @@ struct Cli {
     /// Override the socket path.
     #[arg(short, long)]
     socket: Option<String>,
+
+    /// Override the sysfs root hwmon devices are discovered under.
+    #[arg(long)]
+    sysfs_root: Option<String>,
+
+    /// Override the procfs root load and processes are read from.
+    #[arg(long)]
+    procfs_root: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -41,10 +69,279 @@ struct DaemonState {
     fans: Vec<Fan>,
     sensors: Vec<TempSensor>,
     config_path: PathBuf,
+    /// Per-fan control-loop state, keyed by fan id.
+    runtime: HashMap<String, FanRuntime>,
+    /// Per-fan tachometer stall tracking, keyed by fan id.
+    stall: HashMap<String, StallDetector>,
+    /// Over-temperature / lost-sensor trips.
+    failsafe: Failsafe,
+    /// Sensors fans depend on that had no reading on the last tick.
+    unread: HashSet<String>,
+    /// Fans whose assignment is currently overridden, keyed by fan id.
+    forced: HashMap<String, Forced>,
+    /// Calibrations in progress, keyed by fan id.
+    calibrating: HashMap<String, Calibrator>,
+    /// Events pushed to subscribed clients.
+    events: broadcast::Sender<DaemonEvent>,
+    /// Encoded status push from the last tick, for subscribed clients.
+    status: watch::Sender<Option<Arc<str>>>,
+    /// Rendered metrics from the last tick, for scrapes.
+    metrics: watch::Sender<Option<Arc<str>>>,
+    /// Who may send mutating requests, resolved from the config.
+    access: AccessPolicy,
+    /// Time-of-day profile switching.
+    scheduler: Scheduler,
+    /// CPU and load readings from procfs, taken once per tick.
+    load: LoadSensors,
+    /// Profile switching on system activity.
+    triggers: Triggers,
+    /// What was active before a trigger fired, put back when it clears.
+    pre_trigger: Option<SavedAssignments>,
+    /// Recent readings for charts.
+    history: History,
+    /// When the history was last written to disk.
+    history_saved: Instant,
+    /// Held while the history file is written, so saves don't overlap.
+    history_writer: Arc<std::sync::Mutex<()>>,
+    /// Set once a shutdown signal has handed the fans back; no tick may
+    /// touch them after that.
+    shutting_down: bool,
+    /// How long the last control-loop tick took.
+    tick: Option<Duration>,
+    /// Compiled `[[ignore]]` rules from the config.
+    ignore: IgnoreRules,
+}
+
+/// Assignments set aside while a trigger holds a profile.
+struct SavedAssignments {
+    profile: Option<String>,
+    fans: HashMap<String, FanAssignment>,
+}
+
+impl DaemonState {
+    /// State for freshly discovered hardware, with no control loop history.
+    fn new(
+        config: Config,
+        fans: Vec<Fan>,
+        sensors: Vec<TempSensor>,
+        config_path: PathBuf,
+        load: LoadSensors,
+        history: History,
+        ignore: IgnoreRules,
+    ) -> Self {
+        let access = AccessPolicy::from_config(&config.daemon);
+        Self {
+            config,
+            fans,
+            sensors,
+            config_path,
+            runtime: HashMap::new(),
+            stall: HashMap::new(),
+            failsafe: Failsafe::new(),
+            unread: HashSet::new(),
+            forced: HashMap::new(),
+            calibrating: HashMap::new(),
+            events: broadcast::channel(EVENT_BACKLOG).0,
+            status: watch::channel(None).0,
+            metrics: watch::channel(None).0,
+            access,
+            scheduler: Scheduler::new(),
+            load,
+            triggers: Triggers::new(),
+            pre_trigger: None,
+            history,
+            history_saved: Instant::now(),
+            history_writer: Arc::default(),
+            shutting_down: false,
+            tick: None,
+            ignore,
+        }
+    }
+
+    /// Tell subscribed clients about an event. Never blocks; clients that
+    /// fall too far behind miss events instead.
+    fn notify(&self, event: DaemonEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Record a new assignment for a fan and tell subscribers.
+    fn assign(&mut self, fan_id: String, assignment: FanAssignment) {
+        self.notify(DaemonEvent::AssignmentChanged {
+            fan_id: fan_id.clone(),
+            assignment: assignment.clone(),
+        });
+        self.config.fans.insert(fan_id, assignment);
+        self.note_manual_change();
+    }
+
+    /// Keep a hand-made change until the schedule or a trigger next
+    /// switches on its own.
+    fn note_manual_change(&mut self) {
+        self.scheduler.override_manual();
+        self.triggers.override_manual();
+    }
+
+    /// Switch every fan to a profile's assignments in one go. Fans the
+    /// profile leaves out go back to BIOS control.
+    fn activate_profile(&mut self, name: &str) -> Result<(), String> {
+        let Some(profile) = self.config.profiles.get(name) else {
+            return Err(format!("Unknown profile: {name}"));
+        };
+        // Refuse the whole switch rather than apply half of it
+        for assignment in profile.fans.values() {
+            self.config.check_assignment(assignment)?;
+            self.check_units(assignment)?;
+        }
+        let fans = profile.fans.clone();
+        self.replace_assignments(
+            fans,
+            Some(name.to_string()),
+            &format!("switch to profile '{name}'"),
+        );
+
+        log::info!("Switched to profile '{name}'");
+        self.notify(DaemonEvent::ProfileChanged {
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Run a hwmon write for a fan unless an override holds it. The caller
+    /// records the new assignment either way; it takes effect when the
+    /// override clears.
+    fn write_unless_forced(
+        &self,
+        fan: &Fan,
+        write: impl FnOnce(&Fan) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        if let Some(forced) = self.forced.get(&fan.id) {
+            log::info!(
+                "Fan {} is overridden ({}); its new assignment applies once that clears",
+                fan.id,
+                forced.describe()
+            );
+            return Ok(());
+        }
+        write(fan)
+    }
+
+    /// The config as it should be written to disk. A trigger's profile is
+    /// only borrowed, so what it replaced is saved instead, unless a manual
+    /// change has made it stay.
+    fn saved_config(&self) -> Cow<'_, Config> {
+        let saved = self.pre_trigger.as_ref();
+        let Some(saved) = saved.filter(|_| !self.triggers.is_overridden()) else {
+            return Cow::Borrowed(&self.config);
+        };
+        let mut config = self.config.clone();
+        config.fans = saved.fans.clone();
+        config.active_profile = saved.profile.clone();
+        Cow::Owned(config)
+    }
+
+    /// Check that an assignment's curves suit the units of its sensors.
+    fn check_units(&self, assignment: &FanAssignment) -> Result<(), String> {
+        self.config
+            .check_units(assignment, &|id| base_unit(&self.sensors, &self.fans, id))
+    }
+
+    /// Swap in a whole new set of assignments. Fans left out go back to
+    /// BIOS control, and overridden fans pick theirs up when the override
+    /// clears. Calibrations in progress are cut short and their fans handed
+    /// back; `reason` explains why.
+    fn replace_assignments(
+        &mut self,
+        fans: HashMap<String, FanAssignment>,
+        profile: Option<String>,
+        reason: &str,
+    ) {
+        let dropped: Vec<String> = self
+            .config
+            .fans
+            .keys()
+            .filter(|id| !fans.contains_key(*id))
+            .cloned()
+            .collect();
+
+        let calibrating: Vec<String> = std::mem::take(&mut self.calibrating).into_keys().collect();
+        for fan_id in &calibrating {
+            self.notify(DaemonEvent::CalibrationFailed {
+                fan_id: fan_id.clone(),
+                message: format!("interrupted by {reason}"),
+            });
+        }
+        self.config.fans = fans;
+        self.config.active_profile = profile;
+        self.runtime.clear();
+        apply_assignments(&self.fans, &self.config, &self.forced);
+        // A calibration may have left its fan stopped, assigned or not
+        let released: HashSet<&String> = dropped.iter().chain(&calibrating).collect();
+        for fan_id in released {
+            if self.forced.contains_key(fan_id) {
+                continue;
+            }
+            if let Some(fan) = hwmon::find_fan(&self.fans, fan_id) {
+                restore_assignment(fan, &self.config);
+            }
+        }
+    }
+}
+
+/// Control-loop state for a fan under curve control.
+#[derive(Default)]
+struct FanRuntime {
+    slew: SlewLimiter,
+    /// One entry per curve input of the assignment
+    inputs: Vec<InputRuntime>,
+    /// PWM the curve asked for on the last tick
+    target_pwm: Option<u8>,
+    /// PWM written on the last tick after ramp limiting
+    applied_pwm: Option<u8>,
+    /// The input whose demand won on the last tick
+    active_input: Option<String>,
+    /// Controller state for PID assignments
+    pid: PidController,
+    /// P/I/D contributions on the last tick
+    pid_terms: Option<PidTerms>,
+}
+
+/// Why and how a fan's assignment is being overridden.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Forced {
+    /// A tripped failsafe sensor affects this fan
+    Failsafe(FailsafeAction),
+    /// Another fan has stalled and `full_speed_on_stall` is set
+    StallFallback,
+}
+
+impl Forced {
+    fn full_speed(self) -> bool {
+        !matches!(self, Forced::Failsafe(FailsafeAction::Auto))
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Forced::Failsafe(action) => format!("failsafe: {}", action.describe()),
+            Forced::StallFallback => "stall fallback: full speed".to_string(),
+        }
+    }
+}
+
+/// Control-loop state for one curve input of a fan.
+#[derive(Default)]
+struct InputRuntime {
+    evaluator: CurveEvaluator,
+    /// Smoothing state, if the input is filtered
+    filter: Option<TempFilter>,
+    /// Sensor id and filtered reading from the last tick
+    filtered: Option<(String, f64)>,
 }
 
 type SharedState = Arc<Mutex<DaemonState>>;
 
+/// How many undelivered events a slow subscriber may fall behind by.
+const EVENT_BACKLOG: usize = 64;
+
 // ---------------------------------------------------------------------------
 // Main
 // ---------------------------------------------------------------------------
@@ -55,7 +352,7 @@ async fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
     let config_path = config::resolve_config_path(Some(&cli.config));
-    let cfg = config::load_config(&config_path).unwrap_or_else(|e| {
+    let mut cfg = config::load_config(&config_path).unwrap_or_else(|e| {
         log::warn!("Could not load config: {e}, using defaults");
         Config::default()
     });
@@ -65,76 +362,158 @@ async fn main() -> anyhow::Result<()> {
         .clone()
         .unwrap_or_else(|| cfg.daemon.socket_path.clone());
 
+    let hwmon_root = HwmonRoot::new(
+        cli.sysfs_root
+            .clone()
+            .unwrap_or_else(|| cfg.daemon.sysfs_root.clone()),
+    );
+    let load = LoadSensors::new(ProcRoot::new(
+        cli.procfs_root
+            .clone()
+            .unwrap_or_else(|| cfg.daemon.procfs_root.clone()),
+    ));
+
     // Discover hardware
-    let fans = hwmon::discover_fans().unwrap_or_else(|e| {
+    log::info!("Scanning {}", hwmon_root.hwmon_dir().display());
+    let mut fans = hwmon::discover_fans(&hwmon_root).unwrap_or_else(|e| {
         log::error!("Failed to discover fans: {e}");
         Vec::new()
     });
-    let sensors = hwmon::discover_temp_sensors().unwrap_or_else(|e| {
+    let mut sensors = hwmon::discover_temp_sensors(&hwmon_root).unwrap_or_else(|e| {
         log::error!("Failed to discover temp sensors: {e}");
         Vec::new()
     });
+    if cfg.lm_sensors.import {
+        SensorsConf::load(&cfg.lm_sensors.paths).apply(&mut fans, &mut sensors);
+    }
 
     log::info!(
-        "Discovered {} fan(s) and {} temp sensor(s)",
+        "Discovered {} fan(s), {} temp sensor(s) and {} load sensor(s)",
         fans.len(),
-        sensors.len()
+        sensors.len(),
+        load.available().len()
     );
 
+    cfg.migrate_legacy_ids(&fans, &sensors);
+    cfg.validate(&|id| base_unit(&sensors, &fans, id))
+        .map_err(anyhow::Error::msg)?;
+    check_config(&cfg, &sensors, &fans);
+    let ignore = IgnoreRules::new(&cfg.ignore).map_err(anyhow::Error::msg)?;
+    log_hidden(&ignore, &fans, &sensors);
+
     // Apply initial config
-    apply_assignments(&fans, &sensors, &cfg);
+    apply_assignments(&fans, &cfg, &HashMap::new());
+
+    let history = load_history(&cfg);
+    let metrics_endpoint = cfg
+        .daemon
+        .metrics
+        .as_ref()
+        .map(|m| m.endpoint())
+        .transpose()
+        .map_err(anyhow::Error::msg)?;
 
     let restore_on_exit = cfg.daemon.restore_on_exit;
     let poll_interval = cfg.daemon.poll_interval_ms;
-    let state: SharedState = Arc::new(Mutex::new(DaemonState {
-        config: cfg,
+    let socket_mode = access::parse_mode(&cfg.daemon.socket_mode).map_err(anyhow::Error::msg)?;
+    let socket_gid = match &cfg.daemon.socket_group {
+        Some(group) => Some(
+            access::lookup_group(group)
+                .ok_or_else(|| anyhow::anyhow!("Unknown socket group '{group}'"))?,
+        ),
+        None => None,
+    };
+    let state: SharedState = Arc::new(Mutex::new(DaemonState::new(
+        cfg,
         fans,
         sensors,
         config_path,
-    }));
+        load,
+        history,
+        ignore,
+    )));
 
     // Clean up old socket file
     let _ = std::fs::remove_file(&socket_path);
     let listener = UnixListener::bind(&socket_path)?;
 
-    // Make socket accessible to non-root users
+    // Who may connect at all; who may change things is checked per request
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(0o666);
+        if socket_gid.is_some() {
+            std::os::unix::fs::chown(&socket_path, None, socket_gid)?;
+        }
+        let perms = std::fs::Permissions::from_mode(socket_mode);
         std::fs::set_permissions(&socket_path, perms)?;
     }
 
     log::info!("Listening on {socket_path}");
 
-    let shutdown = Arc::new(Notify::new());
-    let shutdown_signal = shutdown.clone();
+    let metrics_socket = match metrics_endpoint {
+        Some(endpoint) => {
+            let metrics = state.lock().await.metrics.subscribe();
+            spawn_metrics(endpoint, metrics).await?
+        }
+        None => None,
+    };
+
+    // A watch rather than a notification, so a signal that comes while
+    // nobody is waiting still counts
+    let (shutdown_signal, mut shutdown) = watch::channel(false);
 
-    // Signal handler
+    // Signal handler: Ctrl-C, or SIGTERM from systemd and kill
+    let mut terminate = signal(SignalKind::terminate())?;
     let state_for_signal = state.clone();
     tokio::spawn(async move {
-        tokio::signal::ctrl_c().await.ok();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
         log::info!("Received shutdown signal");
-        if restore_on_exit {
-            let st = state_for_signal.lock().await;
-            hwmon::restore_all_automatic(&st.fans);
-            log::info!("Restored all fans to automatic control");
+        let save = {
+            let mut st = state_for_signal.lock().await;
+            // Ticks still waiting for the lock see this and leave the fans
+            // alone, so they stay where we put them here
+            st.shutting_down = true;
+            if restore_on_exit {
+                hwmon::restore_all_automatic(&st.fans);
+                log::info!("Restored all fans to automatic control");
+            }
+            history_save(&st)
+        };
+        if let Some(save) = save {
+            let _ = tokio::task::spawn_blocking(move || save.write()).await;
         }
-        shutdown_signal.notify_waiters();
+        let _ = shutdown_signal.send(true);
     });
 
     // Curve engine loop
     let state_for_curve = state.clone();
-    let shutdown_for_curve = shutdown.clone();
+    let mut shutdown_for_curve = shutdown.clone();
     tokio::spawn(async move {
         let mut interval = time::interval(Duration::from_millis(poll_interval));
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    let st = state_for_curve.lock().await;
-                    run_curve_engine(&st);
+                    let save = {
+                        let mut st = state_for_curve.lock().await;
+                        if st.shutting_down {
+                            break;
+                        }
+                        let started = Instant::now();
+                        run_curve_engine(&mut st);
+                        st.tick = Some(started.elapsed());
+                        publish_status(&st);
+                        periodic_history_save(&mut st)
+                    };
+                    // Serialising and writing take a while; do it without
+                    // holding up clients
+                    if let Some(save) = save {
+                        let _ = tokio::task::spawn_blocking(move || save.write()).await;
+                    }
                 }
-                _ = shutdown_for_curve.notified() => {
+                _ = shutdown_for_curve.changed() => {
                     break;
                 }
             }
@@ -155,40 +534,177 @@ async fn main() -> anyhow::Result<()> {
                     }
                 }
             }
-            _ = shutdown.notified() => {
+            _ = shutdown.changed() => {
                 log::info!("Daemon shutting down");
                 break;
             }
         }
     }
 
-    // Cleanup socket
+    // Cleanup sockets
     let _ = std::fs::remove_file(&socket_path);
+    if let Some(path) = metrics_socket {
+        let _ = std::fs::remove_file(path);
+    }
     Ok(())
 }
 
+// ---------------------------------------------------------------------------
+// Metrics exporter
+// ---------------------------------------------------------------------------
+
+/// Longest request head a scraper may send.
+const METRICS_MAX_REQUEST: usize = 8192;
+
+/// How long a scraper has to send its request.
+const METRICS_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most scrapes served at once; connections beyond that are closed.
+const METRICS_MAX_SCRAPES: usize = 8;
+
+/// Rendered metrics from the last tick; see [`publish_status`].
+type MetricsText = watch::Receiver<Option<Arc<str>>>;
+
+/// Bind the metrics endpoint and serve scrapes in the background. Returns
+/// the socket file to remove on exit, for a Unix socket.
+async fn spawn_metrics(
+    endpoint: MetricsEndpoint,
+    metrics: MetricsText,
+) -> anyhow::Result<Option<PathBuf>> {
+    let scrapes = Arc::new(Semaphore::new(METRICS_MAX_SCRAPES));
+    match endpoint {
+        MetricsEndpoint::Tcp(addr) => {
+            let listener = TcpListener::bind(addr).await?;
+            log::info!("Serving metrics on http://{addr}{}", metrics::METRICS_PATH);
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _addr)) => spawn_scrape(stream, &metrics, &scrapes),
+                        Err(e) => log::error!("Failed to accept metrics connection: {e}"),
+                    }
+                }
+            });
+            Ok(None)
+        }
+        MetricsEndpoint::Unix(path) => {
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path)?;
+            log::info!("Serving metrics on {}", path.display());
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _addr)) => spawn_scrape(stream, &metrics, &scrapes),
+                        Err(e) => log::error!("Failed to accept metrics connection: {e}"),
+                    }
+                }
+            });
+            Ok(Some(path))
+        }
+    }
+}
+
+/// Serve a scrape in its own task, or close the connection if too many
+/// are already in progress.
+fn spawn_scrape<S>(stream: S, metrics: &MetricsText, scrapes: &Arc<Semaphore>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let Ok(permit) = scrapes.clone().try_acquire_owned() else {
+        log::debug!("Too many metrics scrapes in progress, dropping one");
+        return;
+    };
+    let metrics = metrics.clone();
+    tokio::spawn(async move {
+        handle_scrape(stream, metrics).await;
+        drop(permit);
+    });
+}
+
+/// Answer one HTTP request on a metrics connection, then close it.
+async fn handle_scrape<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, metrics: MetricsText) {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    let read = time::timeout(METRICS_READ_TIMEOUT, async {
+        while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+            if head.len() > METRICS_MAX_REQUEST {
+                return false;
+            }
+            match stream.read(&mut buf).await {
+                Ok(0) | Err(_) => return false,
+                Ok(n) => head.extend_from_slice(&buf[..n]),
+            }
+        }
+        true
+    })
+    .await;
+    if read != Ok(true) {
+        return;
+    }
+
+    // Empty until the first tick
+    let text = metrics.borrow().clone();
+    let response = metrics::respond(&String::from_utf8_lossy(&head), || {
+        text.as_deref().unwrap_or_default().to_string()
+    });
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
 // ---------------------------------------------------------------------------
 // Client connection handler
 // ---------------------------------------------------------------------------
 
 async fn handle_client(stream: UnixStream, state: SharedState) {
+    // Without credentials the client is limited to read-only requests
+    let peer = match stream.peer_cred() {
+        Ok(cred) => Some(Peer::from_cred(cred.uid(), cred.gid())),
+        Err(e) => {
+            log::warn!("Could not read client credentials: {e}");
+            None
+        }
+    };
     let (reader, mut writer) = stream.into_split();
     let mut lines = BufReader::new(reader).lines();
+    // Present while this connection is subscribed
+    let mut subscription: Option<Subscription> = None;
 
-    while let Ok(Some(line)) = lines.next_line().await {
-        let response = match protocol::decode::<Request>(&line) {
-            Ok(req) => process_request(req, &state).await,
-            Err(e) => Response::Error {
-                message: format!("Invalid request: {e}"),
-            },
-        };
-
-        let encoded = match protocol::encode(&response) {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("Failed to encode response: {e}");
-                continue;
+    loop {
+        let encoded: Arc<str> = tokio::select! {
+            line = lines.next_line() => {
+                let Ok(Some(line)) = line else {
+                    break; // Client disconnected
+                };
+                let (id, response) = match protocol::decode::<Envelope<Request>>(&line) {
+                    Ok(Envelope { id, body: Request::Subscribe }) => {
+                        subscription = Some(Subscription::new(&*state.lock().await));
+                        let message = "Subscribed".to_string();
+                        (id, Response::Ok { message })
+                    }
+                    Ok(Envelope { id, body: Request::Unsubscribe }) => {
+                        subscription = None;
+                        let message = "Unsubscribed".to_string();
+                        (id, Response::Ok { message })
+                    }
+                    Ok(Envelope { id, body }) => {
+                        (id, process_request(body, peer.as_ref(), &state).await)
+                    }
+                    Err(e) => {
+                        let message = format!("Invalid request: {e}");
+                        (protocol::salvage_id(&line), Response::Error { message })
+                    }
+                };
+                match protocol::encode(&Envelope::new(id, response)) {
+                    Ok(s) => s.into(),
+                    Err(e) => {
+                        log::error!("Failed to encode response: {e}");
+                        continue;
+                    }
+                }
             }
+            push = next_push(&mut subscription) => match push {
+                Some(line) => line,
+                None => continue,
+            },
         };
 
         if writer.write_all(encoded.as_bytes()).await.is_err() {
@@ -197,37 +713,118 @@ async fn handle_client(stream: UnixStream, state: SharedState) {
     }
 }
 
-async fn process_request(req: Request, state: &SharedState) -> Response {
+/// Receivers for the pushes a subscribed connection gets.
+struct Subscription {
+    events: broadcast::Receiver<DaemonEvent>,
+    status: watch::Receiver<Option<Arc<str>>>,
+}
+
+impl Subscription {
+    fn new(st: &DaemonState) -> Self {
+        Self {
+            events: st.events.subscribe(),
+            status: st.status.subscribe(),
+        }
+    }
+}
+
+/// Wait for the next push on a subscription, already encoded. Never
+/// resolves while the connection isn't subscribed.
+async fn next_push(subscription: &mut Option<Subscription>) -> Option<Arc<str>> {
+    let Some(sub) = subscription else {
+        return std::future::pending().await;
+    };
+    let push = tokio::select! {
+        // Events first so a busy status stream can't starve them
+        biased;
+        event = sub.events.recv() => match event {
+            Ok(event) => {
+                let push = Response::Push {
+                    push: Push::Event { event },
+                };
+                Ok(protocol::encode(&push).ok().map(Arc::from))
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                log::warn!("Subscriber fell behind, dropped {n} event(s)");
+                Ok(None)
+            }
+            Err(broadcast::error::RecvError::Closed) => Err(()),
+        },
+        changed = sub.status.changed() => match changed {
+            Ok(()) => Ok(sub.status.borrow_and_update().clone()),
+            Err(_) => Err(()),
+        },
+    };
+    push.unwrap_or_else(|()| {
+        // The daemon is going away
+        *subscription = None;
+        None
+    })
+}
+
+async fn process_request(req: Request, peer: Option<&Peer>, state: &SharedState) -> Response {
     let mut st = state.lock().await;
 
+    if req.is_mutating() && !peer.is_some_and(|p| st.access.may_control(p)) {
+        let who = peer.map_or_else(|| "unknown client".to_string(), Peer::describe);
+        log::warn!("Denied {req:?} from {who}");
+        return Response::Denied {
+            message: format!(
+                "{who} is not allowed to change fan control; add the user to \
+                 control_users or control_groups in the daemon config"
+            ),
+        };
+    }
+
     match req {
-        Request::GetStatus => {
-            let fans = hwmon::read_all_fan_statuses(&st.fans);
-            let temps = hwmon::read_all_temp_statuses(&st.sensors);
-            let assignments = st
-                .config
-                .fans
-                .iter()
-                .map(|(fan_id, a)| FanAssignmentInfo {
-                    fan_id: fan_id.clone(),
-                    assignment: a.clone(),
-                })
-                .collect();
+        Request::Hello {
+            protocol_version,
+            client,
+        } => {
+            let client = client.as_deref().unwrap_or("unnamed client");
+            if protocol::is_compatible(protocol_version) {
+                log::debug!("Hello from {client} (protocol {protocol_version})");
+            } else {
+                log::warn!(
+                    "Hello from {client} speaking protocol {protocol_version}, \
+                     daemon speaks {}",
+                    protocol::PROTOCOL_VERSION
+                );
+            }
+            Response::Hello {
+                protocol_version: protocol::PROTOCOL_VERSION,
+                daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+                features: protocol::FEATURES.iter().map(|f| f.to_string()).collect(),
+            }
+        }
+
+        Request::GetHistory {
+            ids,
+            since,
+            resolution,
+        } => Response::History {
+            samples: st.history.query(&ids, since, resolution),
+        },
 
+        Request::GetStatus => {
+            let snapshot = status_snapshot(&st);
             Response::Status {
-                fans,
-                temps,
-                assignments,
+                fans: snapshot.fans,
+                temps: snapshot.temps,
+                assignments: snapshot.assignments,
+                active_profile: snapshot.active_profile,
+                schedule: snapshot.schedule,
+                trigger: snapshot.trigger,
             }
         }
 
         Request::SetManual { fan_id, pwm } => {
-            if let Some(fan) = st.fans.iter().find(|f| f.id == fan_id) {
-                match hwmon::set_manual_pwm(fan, pwm) {
+            if let Some(fan) = hwmon::find_fan(&st.fans, &fan_id).cloned() {
+                match st.write_unless_forced(&fan, |f| hwmon::set_manual_pwm(f, pwm)) {
                     Ok(()) => {
-                        st.config
-                            .fans
-                            .insert(fan_id.clone(), FanAssignment::Manual { pwm });
+                        st.runtime.remove(&fan.id);
+                        st.calibrating.remove(&fan.id);
+                        st.assign(fan.id.clone(), FanAssignment::Manual { pwm });
                         Response::Ok {
                             message: format!("Set {fan_id} to manual PWM {pwm}"),
                         }
@@ -247,6 +844,7 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
             fan_id,
             curve_name,
             temp_sensor_id,
+            smoothing,
         } => {
             // Validate curve exists
             if !st.config.curves.iter().any(|c| c.name == curve_name) {
@@ -254,42 +852,224 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
                     message: format!("Unknown curve: {curve_name}"),
                 };
             }
-            // Validate sensor exists
-            if !st.sensors.iter().any(|s| s.id == temp_sensor_id) {
+            // Validate sensor exists (physical or virtual)
+            let Some(temp_sensor_id) = resolve_sensor_id(&st, &temp_sensor_id) else {
                 return Response::Error {
                     message: format!("Unknown temp sensor: {temp_sensor_id}"),
                 };
+            };
+            let Some(fan) = hwmon::find_fan(&st.fans, &fan_id).cloned() else {
+                return Response::Error {
+                    message: format!("Unknown fan: {fan_id}"),
+                };
+            };
+            let assignment = FanAssignment::Curve {
+                curve_name: curve_name.clone(),
+                temp_sensor_id,
+                smoothing,
+            };
+            if let Err(message) = st.check_units(&assignment) {
+                return Response::Error { message };
             }
             // Put fan in manual mode (curves write PWM via manual mode)
-            if let Some(fan) = st.fans.iter().find(|f| f.id == fan_id) {
-                if let Err(e) = hwmon::set_pwm_enable(fan, 1) {
+            if let Err(e) = st.write_unless_forced(&fan, |f| hwmon::set_pwm_enable(f, 1)) {
+                return Response::Error {
+                    message: format!("Failed to enable manual mode: {e}"),
+                };
+            }
+
+            st.runtime.remove(&fan.id);
+            st.calibrating.remove(&fan.id);
+            st.assign(fan.id.clone(), assignment);
+            Response::Ok {
+                message: format!("Assigned curve '{curve_name}' to {fan_id}"),
+            }
+        }
+
+        Request::SetMultiCurve {
+            fan_id,
+            mut inputs,
+            combiner,
+        } => {
+            if inputs.is_empty() {
+                return Response::Error {
+                    message: "At least one curve input is required".to_string(),
+                };
+            }
+            for input in &mut inputs {
+                if !st.config.curves.iter().any(|c| c.name == input.curve_name) {
                     return Response::Error {
-                        message: format!("Failed to enable manual mode: {e}"),
+                        message: format!("Unknown curve: {}", input.curve_name),
                     };
                 }
-            } else {
+                let Some(sensor_id) = resolve_sensor_id(&st, &input.temp_sensor_id) else {
+                    return Response::Error {
+                        message: format!("Unknown temp sensor: {}", input.temp_sensor_id),
+                    };
+                };
+                input.temp_sensor_id = sensor_id;
+            }
+            let Some(fan) = hwmon::find_fan(&st.fans, &fan_id).cloned() else {
+                return Response::Error {
+                    message: format!("Unknown fan: {fan_id}"),
+                };
+            };
+            let count = inputs.len();
+            let assignment = FanAssignment::MultiCurve { inputs, combiner };
+            if let Err(message) = st.check_units(&assignment) {
+                return Response::Error { message };
+            }
+            if let Err(e) = st.write_unless_forced(&fan, |f| hwmon::set_pwm_enable(f, 1)) {
+                return Response::Error {
+                    message: format!("Failed to enable manual mode: {e}"),
+                };
+            }
+
+            st.runtime.remove(&fan.id);
+            st.calibrating.remove(&fan.id);
+            st.assign(fan.id.clone(), assignment);
+            Response::Ok {
+                message: format!("Assigned {count} curve inputs to {}", fan.id),
+            }
+        }
+
+        Request::SetPid {
+            fan_id,
+            temp_sensor_id,
+            setpoint_c,
+            kp,
+            ki,
+            kd,
+            min_pwm,
+            max_pwm,
+        } => {
+            let params = PidParams {
+                setpoint_c,
+                kp,
+                ki,
+                kd,
+                min_pwm,
+                max_pwm,
+            };
+            if let Err(e) = params.validate() {
+                return Response::Error { message: e };
+            }
+            let Some(temp_sensor_id) = resolve_sensor_id(&st, &temp_sensor_id) else {
+                return Response::Error {
+                    message: format!("Unknown temp sensor: {temp_sensor_id}"),
+                };
+            };
+            let Some(fan) = hwmon::find_fan(&st.fans, &fan_id).cloned() else {
                 return Response::Error {
                     message: format!("Unknown fan: {fan_id}"),
                 };
+            };
+            let assignment = FanAssignment::pid(temp_sensor_id, params);
+            if let Err(message) = st.check_units(&assignment) {
+                return Response::Error { message };
+            }
+            if let Err(e) = st.write_unless_forced(&fan, |f| hwmon::set_pwm_enable(f, 1)) {
+                return Response::Error {
+                    message: format!("Failed to enable manual mode: {e}"),
+                };
+            }
+
+            st.runtime.remove(&fan.id);
+            st.calibrating.remove(&fan.id);
+            st.assign(fan.id.clone(), assignment);
+            Response::Ok {
+                message: format!("Holding {} at {setpoint_c:.1}°C with PID", fan.id),
+            }
+        }
+
+        Request::TunePid {
+            fan_id,
+            setpoint_c,
+            kp,
+            ki,
+            kd,
+            min_pwm,
+            max_pwm,
+        } => {
+            let Some(fan_id) = hwmon::find_fan(&st.fans, &fan_id).map(|f| f.id.clone()) else {
+                return Response::Error {
+                    message: format!("Unknown fan: {fan_id}"),
+                };
+            };
+            let Some((temp_sensor_id, mut params)) = st
+                .config
+                .fans
+                .get(&fan_id)
+                .and_then(|a| a.pid_params())
+                .map(|(id, p)| (id.to_string(), p))
+            else {
+                return Response::Error {
+                    message: format!("{fan_id} is not under PID control"),
+                };
+            };
+
+            params.setpoint_c = setpoint_c.unwrap_or(params.setpoint_c);
+            params.kp = kp.unwrap_or(params.kp);
+            params.ki = ki.unwrap_or(params.ki);
+            params.kd = kd.unwrap_or(params.kd);
+            params.min_pwm = min_pwm.unwrap_or(params.min_pwm);
+            params.max_pwm = max_pwm.unwrap_or(params.max_pwm);
+            if let Err(e) = params.validate() {
+                return Response::Error { message: e };
+            }
+
+            // Controller state is kept. Its integral has ki applied already,
+            // so a new ki only weighs error from here on and doesn't bump
+            st.assign(fan_id.clone(), FanAssignment::pid(temp_sensor_id, params));
+            Response::Ok {
+                message: format!(
+                    "Tuned {fan_id}: setpoint {:.1}°C, kp {} ki {} kd {}",
+                    params.setpoint_c, params.kp, params.ki, params.kd
+                ),
             }
+        }
 
-            st.config.fans.insert(
-                fan_id.clone(),
-                FanAssignment::Curve {
-                    curve_name: curve_name.clone(),
-                    temp_sensor_id,
-                },
+        Request::Calibrate { fan_id, settle_ms } => {
+            let Some(fan) = hwmon::find_fan(&st.fans, &fan_id).cloned() else {
+                return Response::Error {
+                    message: format!("Unknown fan: {fan_id}"),
+                };
+            };
+            if fan.rpm_path.is_none() {
+                return Response::Error {
+                    message: format!("{} has no tachometer to calibrate against", fan.id),
+                };
+            }
+            if let Some(forced) = st.forced.get(&fan.id) {
+                return Response::Error {
+                    message: format!("{} is overridden ({})", fan.id, forced.describe()),
+                };
+            }
+            let calibrator = Calibrator::new(
+                settle_ms.unwrap_or(calibration::DEFAULT_SETTLE_MS),
+                Instant::now(),
             );
+            if let Err(e) = hwmon::set_manual_pwm(&fan, calibrator.pwm()) {
+                return Response::Error {
+                    message: format!("Failed to set PWM: {e}"),
+                };
+            }
+
+            log::info!("Calibrating {}", fan.id);
+            st.runtime.remove(&fan.id);
+            st.calibrating.insert(fan.id.clone(), calibrator);
             Response::Ok {
-                message: format!("Assigned curve '{curve_name}' to {fan_id}"),
+                message: format!("Calibrating {}; this takes a few minutes", fan.id),
             }
         }
 
         Request::SetAuto { fan_id } => {
-            if let Some(fan) = st.fans.iter().find(|f| f.id == fan_id) {
-                match hwmon::restore_automatic(fan) {
+            if let Some(fan) = hwmon::find_fan(&st.fans, &fan_id).cloned() {
+                match st.write_unless_forced(&fan, hwmon::restore_automatic) {
                     Ok(()) => {
-                        st.config.fans.insert(fan_id.clone(), FanAssignment::Auto);
+                        st.runtime.remove(&fan.id);
+                        st.calibrating.remove(&fan.id);
+                        st.assign(fan.id.clone(), FanAssignment::Auto);
                         Response::Ok {
                             message: format!("Restored {fan_id} to automatic control"),
                         }
@@ -305,15 +1085,75 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
             }
         }
 
+        Request::SetLabel { target, label } => {
+            let resolved = hwmon::find_fan(&st.fans, &target)
+                .map(|f| f.id.clone())
+                .or_else(|| resolve_sensor_id(&st, &target));
+            let Some(resolved) = resolved else {
+                return Response::Error {
+                    message: format!("Unknown fan or sensor: {target}"),
+                };
+            };
+            let label = label
+                .as_deref()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string);
+            match &label {
+                Some(label) => st.config.labels.insert(resolved.clone(), label.clone()),
+                None => st.config.labels.remove(&resolved),
+            };
+            let event = DaemonEvent::LabelChanged {
+                id: resolved,
+                label,
+            };
+            let message = event.describe();
+            st.notify(event);
+            // Clients show names from the status push; don't leave them
+            // on the old one until the next tick
+            publish_status(&st);
+            Response::Ok { message }
+        }
+
         Request::ListCurves => Response::Curves {
             curves: st.config.curves.clone(),
         },
 
-        Request::UpsertCurve { name, points } => {
-            let curve = FanCurve::new(name.clone(), points);
+        Request::UpsertCurve {
+            name,
+            points,
+            hysteresis,
+            hold_ms,
+            ramp_up_rate,
+            ramp_down_rate,
+            relative,
+            unit,
+        } => {
+            let curve = FanCurve::new(name.clone(), points)
+                .with_hysteresis(hysteresis, hold_ms)
+                .with_ramp_rates(ramp_up_rate, ramp_down_rate)
+                .with_relative(relative)
+                .with_unit(unit);
             if let Err(e) = curve.validate() {
                 return Response::Error { message: e };
             }
+            // A changed unit mustn't strand fans already using the curve
+            let in_use = st
+                .config
+                .fans
+                .values()
+                .filter_map(|a| a.curve_inputs())
+                .flat_map(|(inputs, _)| inputs)
+                .filter(|i| i.curve_name == name);
+            for input in in_use {
+                let base = |id: &str| base_unit(&st.sensors, &st.fans, id);
+                if let Err(message) =
+                    st.config
+                        .check_curve_unit(&curve, input.temp_sensor_id, &base)
+                {
+                    return Response::Error { message };
+                }
+            }
 
             // Replace existing or push new
             if let Some(existing) = st.config.curves.iter_mut().find(|c| c.name == name) {
@@ -321,6 +1161,7 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
             } else {
                 st.config.curves.push(curve);
             }
+            st.notify(DaemonEvent::CurvesChanged);
 
             Response::Ok {
                 message: format!("Curve '{name}' saved"),
@@ -331,6 +1172,7 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
             let before = st.config.curves.len();
             st.config.curves.retain(|c| c.name != name);
             if st.config.curves.len() < before {
+                st.notify(DaemonEvent::CurvesChanged);
                 Response::Ok {
                     message: format!("Deleted curve '{name}'"),
                 }
@@ -341,19 +1183,115 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
             }
         }
 
-        Request::SaveConfig => match config::save_config(&st.config_path, &st.config) {
-            Ok(()) => Response::Ok {
-                message: format!("Config saved to {}", st.config_path.display()),
-            },
-            Err(e) => Response::Error {
-                message: format!("Failed to save config: {e}"),
-            },
-        },
-
-        Request::ReloadConfig => match config::load_config(&st.config_path) {
-            Ok(cfg) => {
-                apply_assignments(&st.fans, &st.sensors, &cfg);
-                st.config = cfg;
+        Request::ListProfiles => {
+            let mut names: Vec<&String> = st.config.profiles.keys().collect();
+            names.sort();
+            let profiles = names
+                .into_iter()
+                .map(|name| {
+                    let active = st.config.active_profile.as_ref() == Some(name);
+                    ProfileInfo {
+                        name: name.clone(),
+                        assignments: st.config.profiles[name]
+                            .fans
+                            .iter()
+                            .map(|(fan_id, a)| FanAssignmentInfo {
+                                fan_id: fan_id.clone(),
+                                assignment: a.clone(),
+                            })
+                            .collect(),
+                        active,
+                        modified: active && st.config.profile_modified(),
+                    }
+                })
+                .collect();
+            Response::Profiles { profiles }
+        }
+
+        Request::SetProfile { name } => match st.activate_profile(&name) {
+            Ok(()) => {
+                st.note_manual_change();
+                Response::Ok {
+                    message: format!("Switched to profile '{name}'"),
+                }
+            }
+            Err(message) => Response::Error { message },
+        },
+
+        Request::SaveProfile { name } => {
+            if name.trim().is_empty() {
+                return Response::Error {
+                    message: "Profile name must not be empty".to_string(),
+                };
+            }
+            let profile = Profile {
+                fans: st.config.fans.clone(),
+            };
+            st.config.profiles.insert(name.clone(), profile);
+            st.config.active_profile = Some(name.clone());
+            // Choosing a name for what's running makes it stay
+            st.note_manual_change();
+            st.notify(DaemonEvent::ProfilesChanged);
+            Response::Ok {
+                message: format!("Saved current assignments as profile '{name}'"),
+            }
+        }
+
+        Request::DeleteProfile { name } => {
+            if st.config.profiles.remove(&name).is_none() {
+                return Response::Error {
+                    message: format!("Profile '{name}' not found"),
+                };
+            }
+            if st.config.active_profile.as_ref() == Some(&name) {
+                st.config.active_profile = None;
+            }
+            st.notify(DaemonEvent::ProfilesChanged);
+            Response::Ok {
+                message: format!("Deleted profile '{name}'"),
+            }
+        }
+
+        Request::SaveConfig => match config::save_config(&st.config_path, &st.saved_config()) {
+            Ok(()) => Response::Ok {
+                message: format!("Config saved to {}", st.config_path.display()),
+            },
+            Err(e) => Response::Error {
+                message: format!("Failed to save config: {e}"),
+            },
+        },
+
+        Request::ReloadConfig => match config::load_config(&st.config_path) {
+            Ok(mut cfg) => {
+                let ignore = match IgnoreRules::new(&cfg.ignore) {
+                    Ok(ignore) => ignore,
+                    Err(e) => {
+                        return Response::Error {
+                            message: format!("Failed to reload config: {e}"),
+                        };
+                    }
+                };
+                cfg.migrate_legacy_ids(&st.fans, &st.sensors);
+                if let Err(e) = cfg.validate(&|id| base_unit(&st.sensors, &st.fans, id)) {
+                    return Response::Error {
+                        message: format!("Failed to reload config: {e}"),
+                    };
+                }
+                check_config(&cfg, &st.sensors, &st.fans);
+                log_hidden(&ignore, &st.fans, &st.sensors);
+                st.ignore = ignore;
+                st.access = AccessPolicy::from_config(&cfg.daemon);
+                st.scheduler.reset();
+                st.triggers.reset();
+                st.pre_trigger = None;
+                st.history.set_capacity(cfg.history.capacity());
+                // Swap the assignments in last, against the old ones, so
+                // fans that lose theirs are handed back
+                let fans = std::mem::replace(&mut cfg.fans, std::mem::take(&mut st.config.fans));
+                let profile = cfg.active_profile.clone();
+                st.config = cfg;
+                st.replace_assignments(fans, profile, "config reload");
+                st.notify(DaemonEvent::ConfigReloaded);
                 Response::Ok {
                     message: "Config reloaded".to_string(),
                 }
@@ -364,90 +1302,1314 @@ async fn process_request(req: Request, state: &SharedState) -> Response {
         },
 
         Request::Subscribe | Request::Unsubscribe => {
-            // Subscription is handled at the connection level in a full
-            // implementation. For now, status polling via GetStatus works.
-            Response::Ok {
-                message: "Acknowledged".to_string(),
+            // Handled per connection in handle_client
+            Response::Error {
+                message: "Subscriptions are per connection".to_string(),
             }
         }
     }
 }
 
+/// Everything a status reply or push carries.
+struct StatusSnapshot {
+    fans: Vec<FanStatus>,
+    temps: Vec<TempStatus>,
+    assignments: Vec<FanAssignmentInfo>,
+    active_profile: Option<String>,
+    schedule: Option<ScheduleStatus>,
+    trigger: Option<TriggerStatus>,
+}
+
+/// Live readings merged with the control loop's view of each fan.
+fn status_snapshot(st: &DaemonState) -> StatusSnapshot {
+    let mut fans = hwmon::read_all_fan_statuses(&st.fans);
+    for fan in &mut fans {
+        if let Some(rt) = st.runtime.get(&fan.id) {
+            fan.target_pwm = rt.target_pwm;
+            fan.applied_pwm = rt.applied_pwm;
+            fan.active_input = rt.active_input.clone();
+            fan.pid = rt.pid_terms;
+        }
+        fan.stalled = st.stall.get(&fan.id).is_some_and(|d| d.is_stalled());
+        fan.forced = st.forced.get(&fan.id).map(|f| f.describe());
+        fan.calibrating = st.calibrating.get(&fan.id).map(|c| c.describe());
+        fan.calibration = st.config.calibrations.get(&fan.id).cloned();
+        if let Some(label) = st.config.labels.get(&fan.id) {
+            fan.label = Some(label.clone());
+        }
+    }
+    let mut temps = hwmon::read_all_temp_statuses(&st.sensors);
+    // Virtual sensors are computed from the readings just taken
+    let mut readings = extra_readings(st);
+    readings.extend(temps.iter().filter_map(|t| Some((t.id.clone(), t.temp_c?))));
+    st.config.evaluate_virtual_sensors(&mut readings);
+    temps.extend(st.load.statuses());
+    temps.extend(hwmon::tach_statuses(&st.fans, &readings));
+    temps.extend(
+        st.config
+            .virtual_sensor_statuses(&readings, &|id| base_unit(&st.sensors, &st.fans, id)),
+    );
+    for temp in &mut temps {
+        temp.filtered_c = st
+            .runtime
+            .values()
+            .flat_map(|rt| rt.inputs.iter())
+            .filter_map(|irt| irt.filtered.as_ref())
+            .find(|(id, _)| *id == temp.id)
+            .map(|&(_, c)| c);
+        if let Some(label) = st.config.labels.get(&temp.id) {
+            temp.label = Some(label.clone());
+        }
+    }
+    st.ignore.mark(&mut fans, &mut temps);
+    let assignments = st
+        .config
+        .fans
+        .iter()
+        .map(|(fan_id, a)| FanAssignmentInfo {
+            fan_id: fan_id.clone(),
+            assignment: a.clone(),
+        })
+        .collect();
+
+    StatusSnapshot {
+        fans,
+        temps,
+        assignments,
+        active_profile: st.config.active_profile.clone(),
+        schedule: st.config.schedule.is_active().then(|| {
+            st.scheduler
+                .status(&st.config.schedule, Local::now().naive_local())
+        }),
+        trigger: st.triggers.status(&st.config.triggers),
+    }
+}
+
+/// Encode a status push for subscribers and render the metrics for
+/// scrapes, if there are any. Slow clients only ever see the latest
+/// snapshot, so they can't hold up the engine.
+fn publish_status(st: &DaemonState) {
+    let clients = st.status.receiver_count() > 0;
+    let scrapes = st.metrics.receiver_count() > 0;
+    if !clients && !scrapes {
+        return;
+    }
+    let snapshot = status_snapshot(st);
+    if scrapes {
+        let text = metrics::render(&MetricsSnapshot {
+            fans: &snapshot.fans,
+            temps: &snapshot.temps,
+            assignments: &snapshot.assignments,
+            failsafe: &st.failsafe,
+            tick: st.tick,
+        });
+        st.metrics.send_replace(Some(text.into()));
+    }
+    if !clients {
+        return;
+    }
+    let push = Response::Push {
+        push: Push::Status {
+            fans: snapshot.fans,
+            temps: snapshot.temps,
+            assignments: snapshot.assignments,
+            active_profile: snapshot.active_profile,
+            schedule: snapshot.schedule,
+            trigger: snapshot.trigger,
+        },
+    };
+    match protocol::encode(&push) {
+        Ok(line) => {
+            st.status.send_replace(Some(line.into()));
+        }
+        Err(e) => log::error!("Failed to encode status push: {e}"),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Curve engine
 // ---------------------------------------------------------------------------
 
-fn run_curve_engine(st: &DaemonState) {
-    let temp_map = hwmon::read_temp_map(&st.sensors);
+fn run_curve_engine(st: &mut DaemonState) {
+    let now = Instant::now();
+    st.load.update();
+    check_triggers(st, now);
+    check_schedule(st);
+    let mut temp_map = hwmon::read_temp_map(&st.sensors, &extra_readings(st));
+    st.config.evaluate_virtual_sensors(&mut temp_map);
+    check_stalls(st, now);
+    check_readings(st, &temp_map);
+    check_failsafe(st, &temp_map, now);
+    apply_overrides(st);
+    run_calibrations(st, now);
 
     for (fan_id, assignment) in &st.config.fans {
-        if let FanAssignment::Curve {
-            curve_name,
-            temp_sensor_id,
-        } = assignment
-        {
-            let Some(curve) = st.config.curves.iter().find(|c| &c.name == curve_name) else {
-                log::warn!("Fan {fan_id}: curve '{curve_name}' not found, skipping");
+        if st.forced.contains_key(fan_id) || st.calibrating.contains_key(fan_id) {
+            continue;
+        }
+        if let Some((temp_sensor_id, params)) = assignment.pid_params() {
+            let Some(fan) = hwmon::find_fan(&st.fans, fan_id) else {
                 continue;
             };
             let Some(&temp) = temp_map.get(temp_sensor_id) else {
-                log::warn!("Fan {fan_id}: sensor '{temp_sensor_id}' has no reading, skipping");
+                // Logged once in check_readings
+                log::debug!("Fan {fan_id}: sensor '{temp_sensor_id}' has no reading, skipping");
+                continue;
+            };
+            let rt = st.runtime.entry(fan_id.clone()).or_default();
+            let terms = rt.pid.update(&params, temp, now);
+            rt.pid_terms = Some(terms);
+            rt.target_pwm = Some(terms.output);
+            rt.applied_pwm = Some(terms.output);
+            if let Err(e) = hwmon::set_pwm(fan, terms.output) {
+                log::error!("Failed to write PWM for {fan_id}: {e}");
+            }
+            continue;
+        }
+
+        let Some((inputs, combiner)) = assignment.curve_inputs() else {
+            continue;
+        };
+        let Some(fan) = hwmon::find_fan(&st.fans, fan_id) else {
+            continue;
+        };
+
+        let rt = st.runtime.entry(fan_id.clone()).or_default();
+        if !rt.slew.is_seeded() {
+            // Ramp from whatever the fan is running at right now
+            if let Some(current) = hwmon::read_fan_status(fan).pwm {
+                rt.slew.seed(current, now);
+            }
+        }
+        rt.inputs.resize_with(inputs.len(), InputRuntime::default);
+
+        // Evaluate every input independently
+        let mut demands: Vec<(u8, &FanCurve, &CurveInputRef)> = Vec::new();
+        for (input, irt) in inputs.iter().zip(rt.inputs.iter_mut()) {
+            let curve_name = input.curve_name;
+            let temp_sensor_id = input.temp_sensor_id;
+            let Some(curve) = st.config.curves.iter().find(|c| c.name == curve_name) else {
+                log::warn!("Fan {fan_id}: curve '{curve_name}' not found, skipping");
                 continue;
             };
-            let pwm = curve.interpolate(temp);
+            let Some(&raw) = temp_map.get(temp_sensor_id) else {
+                // Logged once in check_readings
+                log::debug!("Fan {fan_id}: sensor '{temp_sensor_id}' has no reading, skipping");
+                continue;
+            };
+            let temp = match &input.smoothing {
+                Some(spec) => {
+                    let filter = irt.filter.get_or_insert_with(|| TempFilter::new(*spec));
+                    let filtered = filter.update(raw, now);
+                    irt.filtered = Some((temp_sensor_id.to_string(), filtered));
+                    filtered
+                }
+                None => raw,
+            };
+            let pwm = irt.evaluator.evaluate(curve, temp, now);
+            demands.push((
+                scale_curve_pwm(&st.config, fan_id, curve, pwm),
+                curve,
+                input,
+            ));
+        }
+
+        let pwms: Vec<u8> = demands.iter().map(|d| d.0).collect();
+        let Some(target) = combiner.combine(&pwms) else {
+            continue;
+        };
+        // The highest demand "wins" and decides the ramp rates
+        let Some(&(_, curve, winner)) = demands.iter().max_by_key(|d| d.0) else {
+            continue;
+        };
+        let pwm = rt
+            .slew
+            .step(target, curve.ramp_up_rate, curve.ramp_down_rate, now);
+        rt.target_pwm = Some(target);
+        rt.applied_pwm = Some(pwm);
+        rt.active_input = Some(match combiner {
+            Combiner::Max => format!("{} @ {}", winner.curve_name, winner.temp_sensor_id),
+            // No single input decides an average
+            Combiner::Average => format!("average of {} inputs", demands.len()),
+        });
+
+        if let Err(e) = hwmon::set_pwm(fan, pwm) {
+            log::error!("Failed to write PWM for {fan_id}: {e}");
+        }
+    }
+    record_history(st, &temp_map);
+}
+
+/// Switch profiles when the schedule crosses a rule boundary.
+fn check_schedule(st: &mut DaemonState) {
+    if !st.config.schedule.is_active() {
+        return;
+    }
+    let now = Local::now().naive_local();
+    let Some(name) = st.scheduler.update(&st.config.schedule, now) else {
+        return;
+    };
+    if st.triggers.is_engaged() {
+        log::info!("Schedule wants profile '{name}', but a trigger is holding another");
+        return;
+    }
+    if st.config.active_profile.as_deref() == Some(name.as_str()) && !st.config.profile_modified() {
+        return; // Already there, e.g. after a restart
+    }
+    let rule = schedule::active_rule(&st.config.schedule, now)
+        .map_or_else(|| "default".to_string(), ScheduleRule::describe);
+    log::info!("Schedule ({rule}): switching to profile '{name}'");
+    if let Err(e) = st.activate_profile(&name) {
+        log::error!("Schedule could not switch to profile '{name}': {e}");
+    }
+}
+
+/// Switch profiles when a trigger fires, and put back what was there
+/// before when it clears.
+fn check_triggers(st: &mut DaemonState, now: Instant) {
+    if !st.config.triggers.is_active() && !st.triggers.is_engaged() {
+        return;
+    }
+    let sample = sample_system(st);
+    let Some(change) = st.triggers.update(&st.config.triggers, &sample, now) else {
+        return;
+    };
+    match change {
+        TriggerChange::Engage { rule, profile } => {
+            if st.pre_trigger.is_none() {
+                st.pre_trigger = Some(SavedAssignments {
+                    profile: st.config.active_profile.clone(),
+                    fans: st.config.fans.clone(),
+                });
+            }
+            log::info!("Trigger ({rule}): switching to profile '{profile}'");
+            st.notify(DaemonEvent::TriggerEngaged {
+                rule,
+                profile: profile.clone(),
+            });
+            if let Err(e) = st.activate_profile(&profile) {
+                log::error!("Trigger could not switch to profile '{profile}': {e}");
+            }
+        }
+        TriggerChange::Release { rule, overridden } => {
+            release_trigger(st, rule, overridden, Local::now().naive_local());
+        }
+    }
+}
+
+/// Hand control back after a trigger clears: to the schedule if it has a
+/// profile for `at`, otherwise to what was active before the trigger.
+fn release_trigger(st: &mut DaemonState, rule: String, overridden: bool, at: NaiveDateTime) {
+    let saved = st.pre_trigger.take();
+    let scheduled = st.config.schedule.is_active()
+        && !st.scheduler.is_overridden()
+        && schedule::target_profile(&st.config.schedule, at).is_some();
+    if overridden {
+        log::info!("Trigger ({rule}) cleared, keeping manual changes");
+    } else if scheduled {
+        log::info!("Trigger ({rule}) cleared, returning to the schedule");
+        // The schedule check right after switches to its profile
+        st.scheduler.reset();
+    } else if let Some(saved) = saved {
+        log::info!("Trigger ({rule}) cleared, restoring previous assignments");
+        st.replace_assignments(saved.fans, saved.profile, "trigger release");
+    }
+    st.notify(DaemonEvent::TriggerReleased { rule });
+}
+
+/// What the trigger rules look at. Readings that fail are left out, so
+/// their conditions count as clear.
+fn sample_system(st: &DaemonState) -> SystemSample {
+    let readings = st.load.readings();
+    let mut sample = SystemSample {
+        processes: Default::default(),
+        load: readings.get(&LoadSensor::LoadAvg.id()).copied(),
+        cpu_percent: readings.get(&LoadSensor::Cpu.id()).copied(),
+    };
+    // Only walk the process table if some rule needs it
+    if st.config.triggers.needs_processes() {
+        match st.load.proc().process_names() {
+            Ok(names) => sample.processes = names,
+            Err(e) => log::debug!("Failed to list processes: {e}"),
+        }
+    }
+    sample
+}
+
+/// Compare the PWM of every fan the daemon drives with its tachometer and
+/// report stalls and recoveries.
+fn check_stalls(st: &mut DaemonState, now: Instant) {
+    if !st.config.stall.enabled {
+        st.stall.clear();
+        return;
+    }
+    for fan in &st.fans {
+        if st.calibrating.contains_key(&fan.id) {
+            // Calibration stops the fan on purpose
+            st.stall.remove(&fan.id);
+            continue;
+        }
+        let mut status = hwmon::read_fan_status(fan);
+        if let Some(label) = st.config.labels.get(&fan.id) {
+            status.label = Some(label.clone());
+        }
+        // Headers the daemon doesn't drive may be empty or under BIOS
+        // control, where 0 RPM means nothing; hidden ones are known junk
+        let driven = status.pwm_enable == Some(1)
+            && st
+                .config
+                .fans
+                .get(&fan.id)
+                .is_some_and(|a| !matches!(a, FanAssignment::Auto));
+        if !driven || st.ignore.hides_fan(&status) {
+            st.stall.remove(&fan.id);
+            continue;
+        }
+        let detector = st.stall.entry(fan.id.clone()).or_default();
+        match detector.update(status.pwm, status.rpm, &st.config.stall, now) {
+            Some(StallChange::Stalled) => {
+                let pwm = status.pwm.unwrap_or_default();
+                log::error!("Fan {} stalled: 0 RPM at PWM {pwm}", fan.id);
+                st.notify(DaemonEvent::FanStalled {
+                    fan_id: fan.id.clone(),
+                    pwm,
+                });
+                if let Some(command) = &st.config.stall.command {
+                    run_stall_command(command, fan);
+                }
+            }
+            Some(StallChange::Recovered) => {
+                let rpm = status.rpm.unwrap_or_default();
+                if rpm > 0 {
+                    log::info!("Fan {} recovered: {rpm} RPM", fan.id);
+                } else {
+                    log::info!("Fan {} turned down below the stall check", fan.id);
+                }
+                st.notify(DaemonEvent::FanRecovered {
+                    fan_id: fan.id.clone(),
+                    rpm,
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+/// Log when a sensor some fan depends on loses its reading and when it
+/// comes back, rather than on every tick.
+fn check_readings(st: &mut DaemonState, temp_map: &HashMap<String, f64>) {
+    let missing: HashSet<String> = st
+        .config
+        .fans
+        .values()
+        .flat_map(|a| a.sensor_ids())
+        .filter(|id| !temp_map.contains_key(*id))
+        // Utilisation reads nothing until its first clock tick
+        .filter(|id| {
+            !LoadSensor::ALL.into_iter().any(|load| {
+                st.load.is_warming_up(&load.id()) && st.config.sensor_depends_on(id, &load.id())
+            })
+        })
+        .map(str::to_string)
+        .collect();
+    for id in missing.difference(&st.unread) {
+        log::warn!("Sensor {id} has no reading; fans using it hold their PWM");
+    }
+    for id in st.unread.difference(&missing) {
+        log::info!("Sensor {id} is reading again");
+    }
+    st.unread = missing;
+}
+
+/// Check sensors against their limits and report trips and recoveries.
+fn check_failsafe(st: &mut DaemonState, temp_map: &HashMap<String, f64>, now: Instant) {
+    if !st.config.failsafe.enabled {
+        st.failsafe.reset();
+        return;
+    }
+    // Only sensors that a present fan actually depends on
+    let watched: Vec<&str> = st
+        .config
+        .fans
+        .iter()
+        .filter(|(fan_id, _)| hwmon::find_fan(&st.fans, fan_id).is_some())
+        .flat_map(|(_, a)| a.sensor_ids())
+        .collect();
+    // Utilisation reads nothing until a clock tick has passed since the
+    // first sample; that's not a lost sensor
+    let warming_up: Vec<String> = LoadSensor::ALL
+        .into_iter()
+        .map(LoadSensor::id)
+        .filter(|id| st.load.is_warming_up(id))
+        .collect();
+    let expected: Vec<&str> = watched
+        .iter()
+        .copied()
+        .filter(|id| {
+            !warming_up
+                .iter()
+                .any(|load| st.config.sensor_depends_on(id, load))
+        })
+        .collect();
+    // Hidden sensors are junk unless something reads them
+    let sensors: Vec<TempSensor> = st
+        .sensors
+        .iter()
+        .filter(|s| {
+            watched.contains(&s.id.as_str())
+                || !st.ignore.hides_sensor(
+                    &s.id,
+                    &s.legacy_id,
+                    &s.hwmon_name,
+                    st.config
+                        .labels
+                        .get(&s.id)
+                        .or(s.label.as_ref())
+                        .map(String::as_str),
+                    temp_map.get(&s.id).copied(),
+                    InputUnit::Celsius,
+                )
+        })
+        .cloned()
+        .collect();
+    let conditions = failsafe::check_sensors(&sensors, temp_map, &expected, &st.config.failsafe);
+
+    for change in st.failsafe.update(conditions, &st.config.failsafe, now) {
+        match change {
+            FailsafeChange::Triggered { sensor_id, reason } => {
+                log::error!(
+                    "Failsafe triggered by {sensor_id} ({}): fans to {}",
+                    reason.describe(),
+                    st.config.failsafe.action.describe()
+                );
+                st.notify(DaemonEvent::FailsafeTriggered {
+                    sensor_id,
+                    reason,
+                    action: st.config.failsafe.action,
+                });
+            }
+            FailsafeChange::Cleared { sensor_id } => {
+                log::info!("Failsafe cleared for {sensor_id}");
+                st.notify(DaemonEvent::FailsafeCleared { sensor_id });
+            }
+        }
+    }
+}
+
+/// Work out which fans the failsafe and stall fallback override this
+/// tick, drive them, and hand released fans back to their assignment.
+fn apply_overrides(st: &mut DaemonState) {
+    let mut forced: HashMap<String, Forced> = HashMap::new();
+
+    if st.failsafe.is_active() {
+        let cfg = &st.config;
+        for fan in &st.fans {
+            let affected = match cfg.failsafe.scope {
+                FailsafeScope::All => true,
+                FailsafeScope::Affected => cfg.fans.get(&fan.id).is_some_and(|a| {
+                    a.sensor_ids().iter().any(|id| {
+                        st.failsafe
+                            .tripped()
+                            .any(|(tripped, _)| cfg.sensor_depends_on(id, tripped))
+                    })
+                }),
+            };
+            if affected {
+                forced.insert(fan.id.clone(), Forced::Failsafe(cfg.failsafe.action));
+            }
+        }
+    }
+
+    // The stall checker only tracks fans the daemon drives and shows, and
+    // only those are worth speeding up
+    let stalled = st.stall.values().any(StallDetector::is_stalled);
+    if st.config.stall.full_speed_on_stall && stalled {
+        for (fan_id, _) in st.stall.iter().filter(|(_, d)| !d.is_stalled()) {
+            let entry = forced
+                .entry(fan_id.clone())
+                .or_insert(Forced::StallFallback);
+            if !entry.full_speed() {
+                *entry = Forced::StallFallback;
+            }
+        }
+    }
+
+    for (fan_id, &mode) in &forced {
+        let Some(fan) = hwmon::find_fan(&st.fans, fan_id) else {
+            continue;
+        };
+        let changed = st.forced.get(fan_id) != Some(&mode);
+        if changed {
+            log::warn!("Fan {fan_id}: overriding assignment ({})", mode.describe());
+        }
+        let result = if mode.full_speed() {
+            hwmon::set_manual_pwm(fan, 255)
+        } else if changed {
+            hwmon::restore_automatic(fan)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            log::error!("Failed to override {fan_id}: {e}");
+        }
+    }
+
+    for fan_id in st.forced.keys().filter(|id| !forced.contains_key(*id)) {
+        let Some(fan) = hwmon::find_fan(&st.fans, fan_id) else {
+            continue;
+        };
+        log::info!("Fan {fan_id}: resuming normal control");
+        st.runtime.remove(fan_id);
+        restore_assignment(fan, &st.config);
+    }
+
+    st.forced = forced;
+}
 
-            if let Some(fan) = st.fans.iter().find(|f| &f.id == fan_id) {
+/// Step every running calibration and store finished results.
+fn run_calibrations(st: &mut DaemonState, now: Instant) {
+    let fan_ids: Vec<String> = st.calibrating.keys().cloned().collect();
+    for fan_id in fan_ids {
+        let Some(fan) = hwmon::find_fan(&st.fans, &fan_id) else {
+            st.calibrating.remove(&fan_id);
+            continue;
+        };
+        let progress = if st.forced.contains_key(&fan_id) {
+            CalibrationProgress::Failed("aborted, fan was overridden".to_string())
+        } else {
+            let rpm = hwmon::read_fan_status(fan).rpm;
+            match st.calibrating.get_mut(&fan_id) {
+                Some(calibrator) => calibrator.update(rpm, now),
+                None => continue,
+            }
+        };
+
+        match progress {
+            CalibrationProgress::Running => {
+                let pwm = st.calibrating.get(&fan_id).map_or(255, |c| c.pwm());
                 if let Err(e) = hwmon::set_pwm(fan, pwm) {
                     log::error!("Failed to write PWM for {fan_id}: {e}");
                 }
+                continue;
+            }
+            CalibrationProgress::Done(calibration) => {
+                log::info!("Calibrated {fan_id}: {}", calibration.describe());
+                st.config
+                    .calibrations
+                    .insert(fan_id.clone(), calibration.clone());
+                st.notify(DaemonEvent::CalibrationFinished {
+                    fan_id: fan_id.clone(),
+                    calibration,
+                });
             }
+            CalibrationProgress::Failed(message) => {
+                log::warn!("Calibration of {fan_id} failed: {message}");
+                st.notify(DaemonEvent::CalibrationFailed {
+                    fan_id: fan_id.clone(),
+                    message,
+                });
+            }
+        }
+
+        st.calibrating.remove(&fan_id);
+        if !st.forced.contains_key(&fan_id) {
+            restore_assignment(fan, &st.config);
         }
     }
 }
 
+/// Run the configured stall command in the background.
+fn run_stall_command(command: &str, fan: &Fan) {
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FANCTL_FAN_ID", &fan.id)
+        .env("FANCTL_FAN_LABEL", fan.label.as_deref().unwrap_or(""))
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("Failed to run stall command: {e}");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => log::warn!("Stall command exited with {status}"),
+            Ok(_) => {}
+            Err(e) => log::error!("Stall command failed: {e}"),
+        }
+    });
+}
+
 // ---------------------------------------------------------------------------
-// Apply assignments from config on startup/reload
+// History
 // ---------------------------------------------------------------------------
 
-fn apply_assignments(fans: &[Fan], sensors: &[TempSensor], config: &Config) {
-    let temp_map = hwmon::read_temp_map(sensors);
+/// Load the saved history, if the config keeps one on disk.
+fn load_history(config: &Config) -> History {
+    let cfg = &config.history;
+    let Some(path) = &cfg.persist_path else {
+        return History::new(cfg.capacity());
+    };
+    match History::load(
+        path.as_ref(),
+        cfg.capacity(),
+        cfg.retention_secs,
+        history::now_ms(),
+    ) {
+        Ok(history) => {
+            log::info!("Loaded {} history sample(s) from {path}", history.len());
+            history
+        }
+        Err(e) => {
+            log::warn!("Failed to load history from {path}: {e}");
+            History::new(cfg.capacity())
+        }
+    }
+}
+
+/// A copy of the history taken under the state lock, to be written to
+/// disk after releasing it.
+struct HistorySave {
+    history: History,
+    path: PathBuf,
+    writer: Arc<std::sync::Mutex<()>>,
+}
 
+impl HistorySave {
+    /// Write the copy out. Blocks, and waits for any save in progress.
+    fn write(self) {
+        let _writing = self
+            .writer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let path = self.path.display();
+        match self.history.save(&self.path) {
+            Ok(()) => log::debug!("Saved {} history sample(s) to {path}", self.history.len()),
+            Err(e) => log::error!("Failed to save history to {path}: {e}"),
+        }
+    }
+}
+
+/// Copy the history for saving, if the config asks for that.
+fn history_save(st: &DaemonState) -> Option<HistorySave> {
+    let cfg = &st.config.history;
+    let path = cfg.persist_path.as_ref().filter(|_| cfg.enabled)?;
+    Some(HistorySave {
+        history: st.history.clone(),
+        path: PathBuf::from(path),
+        writer: st.history_writer.clone(),
+    })
+}
+
+/// Copy the history for saving once `persist_secs` have passed since the
+/// last save.
+fn periodic_history_save(st: &mut DaemonState) -> Option<HistorySave> {
+    let persist = Duration::from_secs(st.config.history.persist_secs);
+    if st.history_saved.elapsed() < persist {
+        return None;
+    }
+    st.history_saved = Instant::now();
+    history_save(st)
+}
+
+/// Add this tick's readings to the history once a sample is due.
+fn record_history(st: &mut DaemonState, temp_map: &HashMap<String, f64>) {
+    if !st.config.history.enabled {
+        return;
+    }
+    let now = history::now_ms();
+    // Ticks jitter, so take a sample up to half a poll interval early
+    let slack = st.config.daemon.poll_interval_ms / 2;
+    let due = st
+        .history
+        .last_time_ms()
+        .is_none_or(|last| now < last || now - last + slack >= st.config.history.interval_ms);
+    if !due {
+        return;
+    }
+
+    let fans = st
+        .fans
+        .iter()
+        .map(|fan| {
+            let status = hwmon::read_fan_status(fan);
+            let driven = !st.forced.contains_key(&fan.id) && !st.calibrating.contains_key(&fan.id);
+            let applied = st
+                .runtime
+                .get(&fan.id)
+                .filter(|_| driven)
+                .and_then(|rt| rt.applied_pwm);
+            let sample = FanSample {
+                pwm: applied.or(status.pwm),
+                rpm: status.rpm,
+                source: fan_source(st, &fan.id),
+            };
+            (fan.id.clone(), sample)
+        })
+        .collect();
+    st.history.push(Sample {
+        time_ms: now,
+        sensors: temp_map.iter().map(|(id, &v)| (id.clone(), v)).collect(),
+        fans,
+    });
+}
+
+/// What is setting a fan's PWM, for the history.
+fn fan_source(st: &DaemonState, fan_id: &str) -> Option<String> {
+    if let Some(forced) = st.forced.get(fan_id) {
+        return Some(forced.describe());
+    }
+    if st.calibrating.contains_key(fan_id) {
+        return Some("calibration".to_string());
+    }
+    match st.config.fans.get(fan_id)? {
+        FanAssignment::Auto => None,
+        FanAssignment::Manual { .. } => Some("manual".to_string()),
+        FanAssignment::Pid { temp_sensor_id, .. } => Some(format!("pid @ {temp_sensor_id}")),
+        FanAssignment::Curve { .. } | FanAssignment::MultiCurve { .. } => st
+            .runtime
+            .get(fan_id)
+            .and_then(|rt| rt.active_input.clone()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Apply assignments from config on startup/reload
+// ---------------------------------------------------------------------------
+
+/// Apply every configured assignment, except to fans an override holds;
+/// those get theirs when the override clears.
+fn apply_assignments(fans: &[Fan], config: &Config, forced: &HashMap<String, Forced>) {
     for (fan_id, assignment) in &config.fans {
-        let Some(fan) = fans.iter().find(|f| &f.id == fan_id) else {
+        let Some(fan) = hwmon::find_fan(fans, fan_id) else {
             log::warn!("Config references unknown fan: {fan_id}");
             continue;
         };
+        if !forced.contains_key(&fan.id) {
+            apply_assignment(fan, assignment);
+        }
+    }
+}
 
-        match assignment {
-            FanAssignment::Auto => {
-                if let Err(e) = hwmon::restore_automatic(fan) {
-                    log::error!("Failed to set {fan_id} to auto: {e}");
-                }
+/// Hand a fan back to its configured assignment, or to BIOS control if it
+/// has none.
+fn restore_assignment(fan: &Fan, config: &Config) {
+    match config.fans.get(&fan.id) {
+        Some(assignment) => apply_assignment(fan, assignment),
+        None => {
+            if let Err(e) = hwmon::restore_automatic(fan) {
+                log::error!("Failed to restore {} to auto: {e}", fan.id);
             }
-            FanAssignment::Manual { pwm } => {
-                if let Err(e) = hwmon::set_manual_pwm(fan, *pwm) {
-                    log::error!("Failed to set {fan_id} to manual PWM {pwm}: {e}");
-                }
+        }
+    }
+}
+
+/// Put a single fan into the state its assignment asks for.
+fn apply_assignment(fan: &Fan, assignment: &FanAssignment) {
+    let fan_id = &fan.id;
+    match assignment {
+        FanAssignment::Auto => {
+            if let Err(e) = hwmon::restore_automatic(fan) {
+                log::error!("Failed to set {fan_id} to auto: {e}");
             }
-            FanAssignment::Curve {
-                curve_name,
-                temp_sensor_id,
-            } => {
-                // Enable manual mode so the curve engine can write PWM values
-                if let Err(e) = hwmon::set_pwm_enable(fan, 1) {
-                    log::error!("Failed to enable manual mode for {fan_id}: {e}");
-                    continue;
-                }
-                // Apply initial value from curve
-                if let Some(curve) = config.curves.iter().find(|c| &c.name == curve_name) {
-                    if let Some(&temp) = temp_map.get(temp_sensor_id) {
-                        let pwm = curve.interpolate(temp);
-                        if let Err(e) = hwmon::set_pwm(fan, pwm) {
-                            log::error!("Failed to write initial curve PWM for {fan_id}: {e}");
-                        }
-                    }
-                }
+        }
+        FanAssignment::Manual { pwm } => {
+            if let Err(e) = hwmon::set_manual_pwm(fan, *pwm) {
+                log::error!("Failed to set {fan_id} to manual PWM {pwm}: {e}");
             }
         }
+        FanAssignment::Pid { .. }
+        | FanAssignment::Curve { .. }
+        | FanAssignment::MultiCurve { .. } => {
+            // Enable manual mode and leave the PWM where it is; the curve
+            // engine ramps from there to the target on its next tick
+            if let Err(e) = hwmon::set_pwm_enable(fan, 1) {
+                log::error!("Failed to enable manual mode for {fan_id}: {e}");
+            }
+        }
+    }
+}
+
+/// Map a curve's output into the fan's calibrated range if the curve is
+/// relative and the fan has been calibrated.
+fn scale_curve_pwm(config: &Config, fan_id: &str, curve: &FanCurve, pwm: u8) -> u8 {
+    match config.calibrations.get(fan_id) {
+        Some(calibration) if curve.relative => calibration.scale(pwm),
+        _ => pwm,
+    }
+}
+
+/// Resolve a physical (stable or legacy), load, tachometer or virtual
+/// sensor id to the id the curve engine looks up.
+fn resolve_sensor_id(st: &DaemonState, id: &str) -> Option<String> {
+    if let Some(sensor) = hwmon::find_temp_sensor(&st.sensors, id) {
+        return Some(sensor.id.clone());
+    }
+    let load = st.load.available().iter().map(|s| s.id());
+    let tachs = st.fans.iter().filter_map(Fan::tach_id);
+    let virtuals = st.config.virtual_sensors.iter().map(|v| v.id());
+    load.chain(tachs).chain(virtuals).find(|v| v == id)
+}
+
+/// Log the discovered fans and sensors the ignore rules hide.
+fn log_hidden(ignore: &IgnoreRules, fans: &[Fan], sensors: &[TempSensor]) {
+    if ignore.is_empty() {
+        return;
+    }
+    let mut fans = hwmon::read_all_fan_statuses(fans);
+    let mut temps = hwmon::read_all_temp_statuses(sensors);
+    ignore.mark(&mut fans, &mut temps);
+    let hidden: Vec<&str> = fans
+        .iter()
+        .filter(|f| f.hidden)
+        .map(|f| f.id.as_str())
+        .chain(temps.iter().filter(|t| t.hidden).map(|t| t.id.as_str()))
+        .collect();
+    if !hidden.is_empty() {
+        log::info!("Hiding {} per ignore rules", hidden.join(", "));
+    }
+}
+
+/// Unit of a non-virtual sensor, if it is one we know.
+fn base_unit(sensors: &[TempSensor], fans: &[Fan], id: &str) -> Option<InputUnit> {
+    if hwmon::find_temp_sensor(sensors, id).is_some() {
+        Some(InputUnit::Celsius)
+    } else if let Some(sensor) = LoadSensor::from_id(id) {
+        Some(sensor.unit())
+    } else if fans.iter().any(|f| f.tach_id().as_deref() == Some(id)) {
+        Some(InputUnit::Rpm)
+    } else {
+        None
+    }
+}
+
+/// Readings that don't come from hwmon temperature inputs: load sensors
+/// and fan tachometers.
+fn extra_readings(st: &DaemonState) -> HashMap<String, f64> {
+    let mut readings = st.load.readings().clone();
+    readings.extend(hwmon::read_tach_map(&st.fans));
+    readings
+}
+
+/// Log problems in a freshly loaded config that would otherwise only show
+/// up as fans silently not being controlled.
+fn check_config(config: &Config, sensors: &[TempSensor], fans: &[Fan]) {
+    let base = |id: &str| base_unit(sensors, fans, id);
+    for curve in &config.curves {
+        if let Err(e) = curve.validate() {
+            log::warn!("Curve '{}': {e}", curve.name);
+        }
+    }
+    for (fan_id, assignment) in &config.fans {
+        if let Err(e) = config.check_units(assignment, &base) {
+            log::warn!("Fan {fan_id}: {e}");
+        }
+    }
+    for (name, profile) in &config.profiles {
+        for assignment in profile.fans.values() {
+            if let Err(e) = config
+                .check_assignment(assignment)
+                .and_then(|()| config.check_units(assignment, &base))
+            {
+                log::warn!("Profile '{name}': {e}");
+            }
+        }
+    }
+    let scheduled = config.schedule.rules.iter().map(|r| &r.profile);
+    for name in scheduled.chain(&config.schedule.default_profile) {
+        if !config.profiles.contains_key(name) {
+            log::warn!("Schedule references unknown profile '{name}'");
+        }
+    }
+    for rule in &config.triggers.rules {
+        if let Err(e) = rule.validate() {
+            log::warn!("{e}");
+        }
+        if !config.profiles.contains_key(&rule.profile) {
+            log::warn!(
+                "Trigger '{}' references unknown profile '{}'",
+                rule.describe(),
+                rule.profile
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linux_fan_utility::fixture::{FakeProcfs, FakeSysfs};
+
+    /// Two profiles driving `nct6775/pwm1`, for the trigger tests.
+    const PROFILES: &str = r#"
+        [profiles.quiet.fans."nct6775/pwm1"]
+        mode = "manual"
+        pwm = 80
+
+        [profiles.render.fans."nct6775/pwm1"]
+        mode = "manual"
+        pwm = 200
+    "#;
+
+    /// Daemon state over a fake sysfs tree, as `main` would build it.
+    fn state(sysfs: &FakeSysfs, config: &str) -> DaemonState {
+        let config: Config = toml::from_str(config).unwrap();
+        let fans = hwmon::discover_fans(&sysfs.root()).unwrap();
+        let sensors = hwmon::discover_temp_sensors(&sysfs.root()).unwrap();
+        let load = LoadSensors::new(ProcRoot::new(sysfs.path().join("proc")));
+        let history = History::new(config.history.capacity());
+        let ignore = IgnoreRules::new(&config.ignore).unwrap();
+        let config_path = sysfs.path().join("config.toml");
+        apply_assignments(&fans, &config, &HashMap::new());
+        DaemonState::new(config, fans, sensors, config_path, load, history, ignore)
+    }
+
+    /// A client connected as root.
+    fn root() -> Peer {
+        Peer {
+            uid: 0,
+            gid: 0,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Send a request as root and expect it to succeed.
+    async fn request_ok(state: &SharedState, request: Request) {
+        let response = process_request(request, Some(&root()), state).await;
+        assert!(matches!(response, Response::Ok { .. }), "{response:?}");
+    }
+
+    #[test]
+    fn test_stalls_only_checked_on_driven_fans() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 200, Some(0)).unwrap();
+        chip.add_fan(2, 200, Some(0)).unwrap();
+        // Left in manual mode by the BIOS, but nothing is plugged in
+        chip.write_attr("pwm2_enable", 1).unwrap();
+        let mut st = state(
+            &sysfs,
+            r#"
+            [fans."nct6775/pwm1"]
+            mode = "manual"
+            pwm = 200
+            "#,
+        );
+
+        let start = Instant::now();
+        let grace = Duration::from_millis(st.config.stall.grace_ms);
+        check_stalls(&mut st, start);
+        check_stalls(&mut st, start + grace);
+
+        assert!(st.stall["nct6775/pwm1"].is_stalled());
+        assert!(!st.stall.contains_key("nct6775/pwm2"));
+    }
+
+    #[test]
+    fn test_stall_fallback_only_speeds_up_driven_fans() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 200, Some(0)).unwrap();
+        chip.add_fan(2, 100, Some(900)).unwrap();
+        chip.add_fan(3, 100, Some(900)).unwrap();
+        chip.add_fan(4, 100, Some(900)).unwrap();
+        let mut st = state(
+            &sysfs,
+            r#"
+            [stall]
+            grace_ms = 0
+            full_speed_on_stall = true
+
+            [fans."nct6775/pwm1"]
+            mode = "manual"
+            pwm = 200
+
+            [fans."nct6775/pwm2"]
+            mode = "manual"
+            pwm = 100
+
+            [fans."nct6775/pwm4"]
+            mode = "manual"
+            pwm = 100
+
+            [[ignore]]
+            id = "nct6775/pwm4"
+            "#,
+        );
+
+        run_curve_engine(&mut st);
+
+        assert_eq!(chip.read_attr("pwm2").unwrap(), "255");
+        // Left to the BIOS, and hidden, respectively
+        assert_eq!(chip.read_attr("pwm3").unwrap(), "100");
+        assert_eq!(chip.read_attr("pwm4").unwrap(), "100");
+    }
+
+    #[tokio::test]
+    async fn test_assignment_waits_for_override_to_clear() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 255, Some(1500)).unwrap();
+        chip.write_attr("pwm1_enable", 1).unwrap();
+        let mut st = state(&sysfs, "");
+        st.forced.insert(
+            "nct6775/pwm1".to_string(),
+            Forced::Failsafe(FailsafeAction::FullSpeed),
+        );
+        let state: SharedState = Arc::new(Mutex::new(st));
+
+        let request = Request::SetManual {
+            fan_id: "nct6775/pwm1".to_string(),
+            pwm: 80,
+        };
+        request_ok(&state, request).await;
+        assert_eq!(chip.read_attr("pwm1").unwrap(), "255");
+
+        let mut st = state.lock().await;
+        assert!(matches!(
+            st.config.fans.get("nct6775/pwm1"),
+            Some(FanAssignment::Manual { pwm: 80 })
+        ));
+        // Nothing is tripped any more, so the override lifts
+        apply_overrides(&mut st);
+        assert_eq!(chip.read_attr("pwm1").unwrap(), "80");
+    }
+
+    /// Start calibrating a fan the way a client would.
+    async fn start_calibration(state: &SharedState, fan_id: &str) {
+        let request = Request::Calibrate {
+            fan_id: fan_id.to_string(),
+            settle_ms: None,
+        };
+        request_ok(state, request).await;
+    }
+
+    #[tokio::test]
+    async fn test_reload_hands_back_calibrating_fans() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 100, Some(1500)).unwrap();
+        let state: SharedState = Arc::new(Mutex::new(state(&sysfs, "")));
+        start_calibration(&state, "nct6775/pwm1").await;
+        assert_eq!(chip.read_attr("pwm1_enable").unwrap(), "1");
+        let mut events = state.lock().await.events.subscribe();
+
+        request_ok(&state, Request::ReloadConfig).await;
+
+        assert!(state.lock().await.calibrating.is_empty());
+        assert_eq!(chip.read_attr("pwm1_enable").unwrap(), "2");
+        assert!(matches!(
+            events.try_recv(),
+            Ok(DaemonEvent::CalibrationFailed { message, .. }) if message == "interrupted by config reload"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_profile_switch_hands_back_calibrating_fans() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 100, Some(1500)).unwrap();
+        chip.add_fan(2, 100, Some(1500)).unwrap();
+        let st = state(
+            &sysfs,
+            r#"
+            [profiles.quiet.fans."nct6775/pwm1"]
+            mode = "manual"
+            pwm = 80
+            "#,
+        );
+        let state: SharedState = Arc::new(Mutex::new(st));
+        start_calibration(&state, "nct6775/pwm2").await;
+        assert_eq!(chip.read_attr("pwm2_enable").unwrap(), "1");
+
+        let mut st = state.lock().await;
+        let mut events = st.events.subscribe();
+        st.activate_profile("quiet").unwrap();
+
+        assert!(st.calibrating.is_empty());
+        assert_eq!(chip.read_attr("pwm1").unwrap(), "80");
+        // Not in the profile, so back to BIOS control rather than stopped
+        assert_eq!(chip.read_attr("pwm2_enable").unwrap(), "2");
+        assert!(matches!(
+            events.try_recv(),
+            Ok(DaemonEvent::CalibrationFailed { fan_id, .. }) if fan_id == "nct6775/pwm2"
+        ));
+    }
+
+    /// State as a trigger leaves it: `render` running in place of `quiet`.
+    fn render_triggered(sysfs: &FakeSysfs, extra: &str) -> DaemonState {
+        let config = format!(
+            r#"
+            active_profile = "render"
+
+            [fans."nct6775/pwm1"]
+            mode = "manual"
+            pwm = 200
+            {PROFILES}
+            {extra}
+            "#
+        );
+        let mut st = state(sysfs, &config);
+        st.pre_trigger = Some(SavedAssignments {
+            profile: Some("quiet".to_string()),
+            fans: st.config.profiles["quiet"].fans.clone(),
+        });
+        st
+    }
+
+    #[test]
+    fn test_release_restores_profile_outside_schedule_windows() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 0, Some(1500)).unwrap();
+        let mut st = render_triggered(
+            &sysfs,
+            r#"
+            [[schedule.rules]]
+            profile = "quiet"
+            start = "01:00"
+            end = "02:00"
+            "#,
+        );
+
+        // Midday: no rule applies and there is no default profile
+        let noon = chrono::NaiveDate::from_ymd_opt(2026, 10, 16)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        release_trigger(&mut st, "render".to_string(), false, noon);
+
+        assert_eq!(st.config.active_profile.as_deref(), Some("quiet"));
+        assert_eq!(chip.read_attr("pwm1").unwrap(), "80");
+    }
+
+    #[tokio::test]
+    async fn test_save_keeps_assignments_a_trigger_replaced() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 0, Some(1500)).unwrap();
+        let st = render_triggered(&sysfs, "");
+        let config_path = st.config_path.clone();
+        let state: SharedState = Arc::new(Mutex::new(st));
+
+        request_ok(&state, Request::SaveConfig).await;
+
+        let saved = config::load_config(&config_path).unwrap();
+        assert_eq!(saved.active_profile.as_deref(), Some("quiet"));
+        assert_eq!(
+            saved.fans.get("nct6775/pwm1"),
+            Some(&FanAssignment::Manual { pwm: 80 })
+        );
+        // The trigger still holds the running daemon
+        let st = state.lock().await;
+        assert_eq!(st.config.active_profile.as_deref(), Some("render"));
+    }
+
+    #[tokio::test]
+    async fn test_profile_saved_under_trigger_is_kept_on_release() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 0, Some(1500)).unwrap();
+        let procfs = FakeProcfs::new().unwrap();
+        procfs.add_process(100, "blender", &["blender"]).unwrap();
+        let config = format!(
+            r#"
+            active_profile = "quiet"
+
+            [fans."nct6775/pwm1"]
+            mode = "manual"
+            pwm = 80
+            {PROFILES}
+            [[triggers.rules]]
+            profile = "render"
+            processes = ["blender"]
+            clear_secs = 0
+            "#
+        );
+        let mut st = state(&sysfs, &config);
+        st.load = LoadSensors::new(procfs.root());
+        let start = Instant::now();
+        check_triggers(&mut st, start);
+        assert_eq!(chip.read_attr("pwm1").unwrap(), "200");
+        let state: SharedState = Arc::new(Mutex::new(st));
+
+        let request = Request::SaveProfile {
+            name: "render-tuned".to_string(),
+        };
+        request_ok(&state, request).await;
+
+        std::fs::remove_dir_all(procfs.path().join("100")).unwrap();
+        let mut st = state.lock().await;
+        check_triggers(&mut st, start + Duration::from_secs(1));
+        assert!(!st.triggers.is_engaged());
+        assert_eq!(st.config.active_profile.as_deref(), Some("render-tuned"));
+        assert_eq!(chip.read_attr("pwm1").unwrap(), "200");
+    }
+
+    #[test]
+    fn test_cpu_load_curve_does_not_trip_failsafe_on_first_tick() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 100, Some(1500)).unwrap();
+        let procfs = FakeProcfs::new().unwrap();
+        procfs.write("stat", "cpu  100 0 0 100 0\n").unwrap();
+        let mut st = state(
+            &sysfs,
+            r#"
+            [[curves]]
+            name = "busy"
+            unit = "percent"
+            points = [{ input = 0.0, pwm = 60 }, { input = 100.0, pwm = 255 }]
+
+            [fans."nct6775/pwm1"]
+            mode = "curve"
+            curve_name = "busy"
+            temp_sensor_id = "load/cpu"
+            "#,
+        );
+        st.load = LoadSensors::new(procfs.root());
+
+        // No clock tick has passed since startup, so there is no reading
+        run_curve_engine(&mut st);
+        assert!(!st.failsafe.is_active());
+        assert!(st.forced.is_empty());
+
+        procfs.write("stat", "cpu  200 0 0 200 0\n").unwrap();
+        run_curve_engine(&mut st);
+        assert!(!st.failsafe.is_active());
+        assert_eq!(st.runtime["nct6775/pwm1"].target_pwm, Some(158));
+    }
+
+    #[test]
+    fn test_hidden_fans_never_stall() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 200, Some(0)).unwrap();
+        chip.add_fan(2, 200, Some(0)).unwrap();
+        let mut st = state(
+            &sysfs,
+            r#"
+            [fans."nct6775/pwm1"]
+            mode = "manual"
+            pwm = 200
+
+            [fans."nct6775/pwm2"]
+            mode = "manual"
+            pwm = 200
+
+            [[ignore]]
+            id = "nct6775/pwm2"
+            "#,
+        );
+
+        let start = Instant::now();
+        let grace = Duration::from_millis(st.config.stall.grace_ms);
+        check_stalls(&mut st, start);
+        check_stalls(&mut st, start + grace);
+
+        assert!(st.stall["nct6775/pwm1"].is_stalled());
+        assert!(!st.stall.contains_key("nct6775/pwm2"));
     }
 }
@@ -11,22 +11,33 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use linux_fan_utility::config::{self, FanAssignment};
-use linux_fan_utility::curve::CurvePoint;
+use linux_fan_utility::curve::{CurvePoint, InputUnit};
+use linux_fan_utility::history::{self, Sample};
 use linux_fan_utility::hwmon::{FanStatus, TempStatus};
-use linux_fan_utility::protocol::{self, FanAssignmentInfo, Request, Response};
+use linux_fan_utility::pid::{PidParams, PidTerms};
+use linux_fan_utility::protocol::{
+    self, DaemonEvent, Envelope, FanAssignmentInfo, ProfileInfo, Push, Request, Response,
+};
+use linux_fan_utility::schedule::ScheduleStatus;
+use linux_fan_utility::smoothing::Smoothing;
+use linux_fan_utility::trigger::TriggerStatus;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Line, Span},
     widgets::{
-        Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Tabs,
+        Axis, Block, Borders, Cell, Chart, Clear, Dataset, GraphType, List, ListItem, ListState,
+        Paragraph, Row, Table, TableState, Tabs,
     },
 };
 use std::io::{self, BufRead, BufReader, Write};
 use std::os::unix::net::UnixStream;
-use std::time::Duration;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 // ---------------------------------------------------------------------------
 // CLI
@@ -79,11 +90,33 @@ struct App {
     running: bool,
     status_message: String,
     connection: Option<Connection>,
+    /// Set by a successful handshake
+    daemon: Option<DaemonInfo>,
+    /// The daemon speaks a protocol this client can't
+    incompatible: bool,
+    /// Pushes from the daemon; `None` falls back to polling
+    pushes: Option<mpsc::Receiver<Push>>,
 
     // Dashboard
+    /// Fans and sensors as the daemon reported them
+    all_fans: Vec<FanStatus>,
+    all_temps: Vec<TempStatus>,
+    /// The ones listed: all of them, or only those ignore rules don't hide
     fans: Vec<FanStatus>,
     temps: Vec<TempStatus>,
+    show_hidden: bool,
     assignments: Vec<FanAssignmentInfo>,
+    active_profile: Option<String>,
+    schedule: Option<ScheduleStatus>,
+    trigger: Option<TriggerStatus>,
+    /// Fan (first) or sensor row charted below the tables
+    dashboard_select: usize,
+    history: Vec<Sample>,
+    history_window: HistoryWindow,
+    /// When the history was last fetched; `None` fetches on the next frame
+    history_fetched: Option<Instant>,
+    /// Label being typed for a fan or sensor
+    renaming: Option<RenameState>,
 
     // Fan control
     fan_list_state: ListState,
@@ -91,6 +124,8 @@ struct App {
     fan_mode_select: FanModeSelect,
     temp_sensor_select: usize,
     curve_select: usize,
+    fan_smoothing: Option<Smoothing>,
+    pid_setpoint: f64,
 
     // Curve editor
     curves: Vec<CurveData>,
@@ -99,25 +134,118 @@ struct App {
 
     // Config tab
     config_path: String,
+    profiles: Vec<ProfileInfo>,
+    profile_list_state: ListState,
 }
 
+/// Time span of the dashboard history chart, zoomed with [+]/[-].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryWindow {
+    Minute,
+    TenMinutes,
+    Hour,
+}
+
+impl HistoryWindow {
+    const ALL: [HistoryWindow; 3] = [
+        HistoryWindow::Minute,
+        HistoryWindow::TenMinutes,
+        HistoryWindow::Hour,
+    ];
+
+    fn secs(self) -> u64 {
+        match self {
+            HistoryWindow::Minute => 60,
+            HistoryWindow::TenMinutes => 600,
+            HistoryWindow::Hour => 3600,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            HistoryWindow::Minute => "1m",
+            HistoryWindow::TenMinutes => "10m",
+            HistoryWindow::Hour => "1h",
+        }
+    }
+
+    fn zoom_in(self) -> Self {
+        let i = Self::ALL.iter().position(|&w| w == self).unwrap_or(0);
+        Self::ALL[i.saturating_sub(1)]
+    }
+
+    fn zoom_out(self) -> Self {
+        let i = Self::ALL.iter().position(|&w| w == self).unwrap_or(0);
+        Self::ALL[(i + 1).min(Self::ALL.len() - 1)]
+    }
+}
+
+/// Points per chart; the daemon averages history down to about this many.
+const HISTORY_POINTS: u64 = 120;
+
+/// How often the dashboard refetches history.
+const HISTORY_REFRESH: Duration = Duration::from_secs(1);
+
+/// Smoothing options cycled through with [s] in the fan control tab.
+const SMOOTHING_PRESETS: [Option<Smoothing>; 5] = [
+    None,
+    Some(Smoothing::Ema {
+        time_constant_ms: 2000,
+    }),
+    Some(Smoothing::Ema {
+        time_constant_ms: 5000,
+    }),
+    Some(Smoothing::Median { window: 3 }),
+    Some(Smoothing::Mean { window: 5 }),
+];
+
+/// Gains used when switching a fan to PID control from the TUI.
+const DEFAULT_PID: PidParams = PidParams {
+    setpoint_c: 40.0,
+    kp: 8.0,
+    ki: 0.5,
+    kd: 0.0,
+    min_pwm: 60,
+    max_pwm: 255,
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum FanModeSelect {
     Auto,
     Manual,
     Curve,
+    Pid,
+}
+
+/// A friendly name being entered for a fan or sensor.
+#[derive(Debug, Clone)]
+struct RenameState {
+    id: String,
+    label: String,
 }
 
 #[derive(Debug, Clone)]
 struct CurveData {
     name: String,
     points: Vec<CurvePoint>,
+    hysteresis: f64,
+    hold_ms: u64,
+    ramp_up_rate: f64,
+    ramp_down_rate: f64,
+    relative: bool,
+    unit: InputUnit,
 }
 
 #[derive(Debug, Clone)]
 struct CurveEditState {
     name: String,
     points: Vec<CurvePoint>,
+    hysteresis: f64,
+    hold_ms: u64,
+    ramp_up_rate: f64,
+    ramp_down_rate: f64,
+    relative: bool,
+    unit: InputUnit,
     selected_point: usize,
     editing_field: CurveField,
     is_new: bool,
@@ -126,13 +254,25 @@ struct CurveEditState {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CurveField {
     Name,
-    Temp,
+    Input,
     Pwm,
+    Hysteresis,
+    Hold,
+    RampUp,
+    RampDown,
+}
+
+/// What the daemon reported in its hello.
+struct DaemonInfo {
+    version: String,
+    features: Vec<String>,
 }
 
 struct Connection {
     stream: UnixStream,
     reader: BufReader<UnixStream>,
+    /// Correlation id of the last request sent
+    last_id: u64,
 }
 
 impl Connection {
@@ -140,27 +280,125 @@ impl Connection {
         let stream = UnixStream::connect(path)?;
         stream.set_read_timeout(Some(Duration::from_secs(2)))?;
         let reader = BufReader::new(stream.try_clone()?);
-        Ok(Self { stream, reader })
+        Ok(Self {
+            stream,
+            reader,
+            last_id: 0,
+        })
+    }
+
+    /// Exchange protocol versions. The error is a message for the user.
+    fn handshake(&mut self) -> Result<DaemonInfo, String> {
+        let hello = Request::Hello {
+            protocol_version: protocol::PROTOCOL_VERSION,
+            client: Some(format!("fanctl-tui {}", env!("CARGO_PKG_VERSION"))),
+        };
+        match self.send_request(&hello) {
+            Ok(Response::Hello {
+                protocol_version,
+                daemon_version,
+                features,
+            }) => {
+                if protocol::is_compatible(protocol_version) {
+                    Ok(DaemonInfo {
+                        version: daemon_version,
+                        features,
+                    })
+                } else {
+                    Err(format!(
+                        "Incompatible daemon: fanctl-daemon {daemon_version} speaks protocol \
+                         {protocol_version}, this client speaks {}. Upgrade the older one.",
+                        protocol::PROTOCOL_VERSION
+                    ))
+                }
+            }
+            // Daemons from before the handshake reject the request
+            Ok(Response::Error { .. }) => Err(
+                "Incompatible daemon: fanctl-daemon is too old to report its protocol \
+                 version. Upgrade it."
+                    .to_string(),
+            ),
+            Ok(other) => Err(format!("Unexpected reply to hello: {other:?}")),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => Err(format!(
+                "Incompatible daemon: could not understand its hello ({e}). Upgrade fanctl-tui."
+            )),
+            Err(e) => Err(format!("Connection error: {e}")),
+        }
     }
 
+    /// Send a request and wait for its reply, skipping pushes and replies
+    /// to earlier requests that timed out.
     fn send_request(&mut self, req: &Request) -> io::Result<Response> {
-        let encoded = protocol::encode(req).map_err(|e| {
+        self.last_id += 1;
+        let id = self.last_id;
+        let encoded = protocol::encode(&Envelope::new(Some(id), req)).map_err(|e| {
             io::Error::new(io::ErrorKind::InvalidData, format!("Encode error: {e}"))
         })?;
         self.stream.write_all(encoded.as_bytes())?;
         self.stream.flush()?;
+        loop {
+            let reply = self.read_message()?;
+            // Daemons from before correlation ids never set one
+            if !reply.body.is_push() && reply.id.is_none_or(|r| r == id) {
+                return Ok(reply.body);
+            }
+        }
+    }
 
+    fn read_message(&mut self) -> io::Result<Envelope<Response>> {
         let mut line = String::new();
-        self.reader.read_line(&mut line)?;
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Daemon closed the connection",
+            ));
+        }
         protocol::decode(&line).map_err(|e| {
             io::Error::new(io::ErrorKind::InvalidData, format!("Decode error: {e}"))
         })
     }
 }
 
+/// Subscribe on a second connection and forward its pushes from a
+/// background thread, so requests on the main connection never have to
+/// sort replies from pushes.
+fn subscribe(path: &str) -> io::Result<mpsc::Receiver<Push>> {
+    let mut conn = Connection::connect(path)?;
+    match conn.send_request(&Request::Subscribe)? {
+        Response::Ok { .. } => {}
+        Response::Error { message } => return Err(io::Error::other(message)),
+        other => {
+            return Err(io::Error::other(format!(
+                "Unexpected reply to subscribe: {other:?}"
+            )));
+        }
+    }
+    // Pushes can be far apart when nothing changes
+    conn.stream.set_read_timeout(None)?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        loop {
+            match conn.read_message().map(|m| m.body) {
+                Ok(Response::Push { push }) => {
+                    if tx.send(push).is_err() {
+                        break; // UI has gone away
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("Subscription ended: {e}");
+                    break;
+                }
+            }
+        }
+    });
+    Ok(rx)
+}
+
 impl App {
     fn new(socket_path: &str) -> Self {
-        let connection = match Connection::connect(socket_path) {
+        let mut connection = match Connection::connect(socket_path) {
             Ok(c) => {
                 log::info!("Connected to daemon at {socket_path}");
                 Some(c)
@@ -171,29 +409,73 @@ impl App {
             }
         };
 
+        let mut status_message = String::new();
+        let mut incompatible = false;
+        let daemon = match connection.as_mut().map(Connection::handshake) {
+            Some(Ok(info)) => Some(info),
+            Some(Err(message)) => {
+                log::error!("{message}");
+                status_message = message;
+                incompatible = true;
+                connection = None;
+                None
+            }
+            None => None,
+        };
+
+        let can_subscribe = daemon
+            .as_ref()
+            .is_some_and(|d| d.features.iter().any(|f| f == "subscribe"));
+        let pushes = if can_subscribe {
+            subscribe(socket_path)
+                .inspect_err(|e| log::warn!("Could not subscribe, polling instead: {e}"))
+                .ok()
+        } else {
+            None
+        };
+
         let mut app = Self {
             tab: Tab::Dashboard,
             running: true,
-            status_message: String::new(),
+            status_message,
             connection,
+            daemon,
+            incompatible,
+            pushes,
+            all_fans: Vec::new(),
+            all_temps: Vec::new(),
             fans: Vec::new(),
             temps: Vec::new(),
+            show_hidden: false,
             assignments: Vec::new(),
+            active_profile: None,
+            schedule: None,
+            trigger: None,
+            dashboard_select: 0,
+            history: Vec::new(),
+            history_window: HistoryWindow::TenMinutes,
+            history_fetched: None,
+            renaming: None,
             fan_list_state: ListState::default(),
             selected_fan_pwm: 128,
             fan_mode_select: FanModeSelect::Auto,
             temp_sensor_select: 0,
             curve_select: 0,
+            fan_smoothing: None,
+            pid_setpoint: DEFAULT_PID.setpoint_c,
             curves: Vec::new(),
             curve_list_state: ListState::default(),
             editing_curve: None,
             config_path: config::DEFAULT_CONFIG_PATH.to_string(),
+            profiles: Vec::new(),
+            profile_list_state: ListState::default(),
         };
 
         if app.connection.is_some() {
             app.refresh_status();
             app.refresh_curves();
-        } else {
+            app.refresh_profiles();
+        } else if !app.incompatible {
             app.status_message =
                 "Not connected to daemon. Is fanctl-daemon running?".to_string();
         }
@@ -208,10 +490,15 @@ impl App {
                     fans,
                     temps,
                     assignments,
+                    active_profile,
+                    schedule,
+                    trigger,
                 }) => {
-                    self.fans = fans;
-                    self.temps = temps;
+                    self.set_readings(fans, temps);
                     self.assignments = assignments;
+                    self.active_profile = active_profile;
+                    self.schedule = schedule;
+                    self.trigger = trigger;
                 }
                 Ok(Response::Error { message }) => {
                     self.status_message = format!("Error: {message}");
@@ -225,6 +512,155 @@ impl App {
         }
     }
 
+    /// Take the fans and sensors from a status snapshot.
+    fn set_readings(&mut self, fans: Vec<FanStatus>, temps: Vec<TempStatus>) {
+        self.all_fans = fans;
+        self.all_temps = temps;
+        self.filter_hidden();
+    }
+
+    /// Rebuild the listed fans and sensors from the full set.
+    fn filter_hidden(&mut self) {
+        let show = self.show_hidden;
+        self.fans = self
+            .all_fans
+            .iter()
+            .filter(|f| show || !f.hidden)
+            .cloned()
+            .collect();
+        self.temps = self
+            .all_temps
+            .iter()
+            .filter(|t| show || !t.hidden)
+            .cloned()
+            .collect();
+        let rows = self.fans.len() + self.temps.len();
+        self.dashboard_select = self.dashboard_select.min(rows.saturating_sub(1));
+        if self
+            .fan_list_state
+            .selected()
+            .is_some_and(|i| i >= self.fans.len())
+        {
+            self.fan_list_state.select(self.fans.len().checked_sub(1));
+        }
+    }
+
+    /// Number of fans and sensors ignore rules hide.
+    fn hidden_count(&self) -> usize {
+        self.all_fans.iter().filter(|f| f.hidden).count()
+            + self.all_temps.iter().filter(|t| t.hidden).count()
+    }
+
+    fn toggle_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        self.filter_hidden();
+        let count = self.hidden_count();
+        self.status_message = if self.show_hidden {
+            format!("Showing {count} hidden fan(s) and sensor(s)")
+        } else {
+            format!("Hiding {count} fan(s) and sensor(s) matched by ignore rules")
+        };
+    }
+
+    /// Apply everything the daemon has pushed since the last frame.
+    fn drain_pushes(&mut self) {
+        let Some(rx) = &self.pushes else {
+            return;
+        };
+        let mut pushes = Vec::new();
+        loop {
+            match rx.try_recv() {
+                Ok(push) => pushes.push(push),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.pushes = None;
+                    break;
+                }
+            }
+        }
+        for push in pushes {
+            match push {
+                Push::Status {
+                    fans,
+                    temps,
+                    assignments,
+                    active_profile,
+                    schedule,
+                    trigger,
+                } => {
+                    self.set_readings(fans, temps);
+                    self.assignments = assignments;
+                    self.active_profile = active_profile;
+                    self.schedule = schedule;
+                    self.trigger = trigger;
+                }
+                Push::Event { event } => {
+                    if matches!(
+                        event,
+                        DaemonEvent::CurvesChanged | DaemonEvent::ConfigReloaded
+                    ) {
+                        self.refresh_curves();
+                    }
+                    if matches!(
+                        event,
+                        DaemonEvent::ProfileChanged { .. }
+                            | DaemonEvent::ProfilesChanged
+                            | DaemonEvent::TriggerReleased { .. }
+                            | DaemonEvent::ConfigReloaded
+                    ) {
+                        self.refresh_profiles();
+                    }
+                    self.status_message = event.describe();
+                }
+            }
+        }
+    }
+
+    /// Fetch the charted window of history, at most once per
+    /// [`HISTORY_REFRESH`].
+    fn refresh_history(&mut self) {
+        let supported = self
+            .daemon
+            .as_ref()
+            .is_some_and(|d| d.features.iter().any(|f| f == "history"));
+        if !supported
+            || self
+                .history_fetched
+                .is_some_and(|t| t.elapsed() < HISTORY_REFRESH)
+        {
+            return;
+        }
+        self.history_fetched = Some(Instant::now());
+        let Some(conn) = &mut self.connection else {
+            return;
+        };
+        let window_ms = self.history_window.secs() * 1000;
+        let req = Request::GetHistory {
+            ids: Vec::new(),
+            since: Some(history::now_ms().saturating_sub(window_ms)),
+            resolution: Some(window_ms / HISTORY_POINTS),
+        };
+        match conn.send_request(&req) {
+            Ok(Response::History { samples }) => self.history = samples,
+            Ok(Response::Error { message }) => {
+                self.status_message = format!("Error: {message}");
+            }
+            Err(e) => {
+                self.status_message = format!("Connection error: {e}");
+                self.connection = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_history_window(&mut self, window: HistoryWindow) {
+        if window != self.history_window {
+            self.history_window = window;
+            self.history_fetched = None;
+            self.status_message = format!("History: last {}", window.label());
+        }
+    }
+
     fn refresh_curves(&mut self) {
         if let Some(conn) = &mut self.connection {
             match conn.send_request(&Request::ListCurves) {
@@ -234,6 +670,12 @@ impl App {
                         .map(|c| CurveData {
                             name: c.name,
                             points: c.points,
+                            hysteresis: c.hysteresis,
+                            hold_ms: c.hold_ms,
+                            ramp_up_rate: c.ramp_up_rate,
+                            ramp_down_rate: c.ramp_down_rate,
+                            relative: c.relative,
+                            unit: c.unit,
                         })
                         .collect();
                 }
@@ -246,6 +688,55 @@ impl App {
         }
     }
 
+    fn refresh_profiles(&mut self) {
+        if let Some(conn) = &mut self.connection {
+            match conn.send_request(&Request::ListProfiles) {
+                Ok(Response::Profiles { profiles }) => {
+                    self.profiles = profiles;
+                    if self.profile_list_state.selected().is_none() && !self.profiles.is_empty() {
+                        self.profile_list_state.select(Some(0));
+                    }
+                }
+                Err(e) => {
+                    self.status_message = format!("Connection error: {e}");
+                    self.connection = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn activate_selected_profile(&mut self) {
+        let Some(name) = self
+            .profile_list_state
+            .selected()
+            .and_then(|i| self.profiles.get(i))
+            .map(|p| p.name.clone())
+        else {
+            return;
+        };
+        if let Some(conn) = &mut self.connection {
+            match conn.send_request(&Request::SetProfile { name }) {
+                Ok(Response::Ok { message }) => {
+                    self.status_message = message;
+                    self.refresh_status();
+                    self.refresh_profiles();
+                }
+                Ok(Response::Error { message }) => {
+                    self.status_message = format!("Error: {message}");
+                }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                }
+                Err(e) => {
+                    self.status_message = format!("Connection error: {e}");
+                    self.connection = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn selected_fan(&self) -> Option<&FanStatus> {
         self.fan_list_state
             .selected()
@@ -294,6 +785,43 @@ impl App {
                     fan_id: fan.id.clone(),
                     curve_name,
                     temp_sensor_id,
+                    smoothing: self.fan_smoothing,
+                }
+            }
+            FanModeSelect::Pid => {
+                let Some(temp_sensor_id) = self
+                    .temps
+                    .get(self.temp_sensor_select)
+                    .map(|t| t.id.clone())
+                else {
+                    self.status_message = "Select a temp sensor first".to_string();
+                    return;
+                };
+                let current = self.selected_fan_assignment().and_then(|a| a.pid_params());
+                match current {
+                    // Same sensor: retune live so the controller keeps its state
+                    Some((id, _)) if id == temp_sensor_id => Request::TunePid {
+                        fan_id: fan.id.clone(),
+                        setpoint_c: Some(self.pid_setpoint),
+                        kp: None,
+                        ki: None,
+                        kd: None,
+                        min_pwm: None,
+                        max_pwm: None,
+                    },
+                    _ => {
+                        let params = current.map(|(_, p)| p).unwrap_or(DEFAULT_PID);
+                        Request::SetPid {
+                            fan_id: fan.id.clone(),
+                            temp_sensor_id,
+                            setpoint_c: self.pid_setpoint,
+                            kp: params.kp,
+                            ki: params.ki,
+                            kd: params.kd,
+                            min_pwm: params.min_pwm,
+                            max_pwm: params.max_pwm,
+                        }
+                    }
                 }
             }
         };
@@ -306,6 +834,9 @@ impl App {
                 Ok(Response::Error { message }) => {
                     self.status_message = format!("Error: {message}");
                 }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                }
                 Err(e) => {
                     self.status_message = format!("Connection error: {e}");
                     self.connection = None;
@@ -325,6 +856,9 @@ impl App {
                 Ok(Response::Error { message }) => {
                     self.status_message = format!("Error: {message}");
                 }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                }
                 Err(e) => {
                     self.status_message = format!("Connection error: {e}");
                     self.connection = None;
@@ -338,11 +872,19 @@ impl App {
         let Some(edit) = &self.editing_curve else {
             return;
         };
-        let name = edit.name.clone();
-        let points = edit.points.clone();
+        let req = Request::UpsertCurve {
+            name: edit.name.clone(),
+            points: edit.points.clone(),
+            hysteresis: edit.hysteresis,
+            hold_ms: edit.hold_ms,
+            ramp_up_rate: edit.ramp_up_rate,
+            ramp_down_rate: edit.ramp_down_rate,
+            relative: edit.relative,
+            unit: edit.unit,
+        };
 
         if let Some(conn) = &mut self.connection {
-            match conn.send_request(&Request::UpsertCurve { name, points }) {
+            match conn.send_request(&req) {
                 Ok(Response::Ok { message }) => {
                     self.status_message = message;
                     self.editing_curve = None;
@@ -351,6 +893,9 @@ impl App {
                 Ok(Response::Error { message }) => {
                     self.status_message = format!("Error: {message}");
                 }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                }
                 Err(e) => {
                     self.status_message = format!("Connection error: {e}");
                     self.connection = None;
@@ -360,6 +905,98 @@ impl App {
         }
     }
 
+    fn calibrate_selected_fan(&mut self) {
+        let Some(fan) = self.selected_fan().cloned() else {
+            return;
+        };
+        let req = Request::Calibrate {
+            fan_id: fan.id,
+            settle_ms: None,
+        };
+
+        if let Some(conn) = &mut self.connection {
+            match conn.send_request(&req) {
+                Ok(Response::Ok { message }) => {
+                    self.status_message = message;
+                    self.refresh_status();
+                }
+                Ok(Response::Error { message }) => {
+                    self.status_message = format!("Error: {message}");
+                }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                }
+                Err(e) => {
+                    self.status_message = format!("Connection error: {e}");
+                    self.connection = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Start entering a label for the fan or sensor selected on the
+    /// dashboard.
+    fn start_rename(&mut self) {
+        let supported = self
+            .daemon
+            .as_ref()
+            .is_some_and(|d| d.features.iter().any(|f| f == "labels"));
+        if !supported {
+            self.status_message = "The daemon doesn't support labels".to_string();
+            return;
+        }
+        let fan_count = self.fans.len();
+        let selected = if self.dashboard_select < fan_count {
+            self.fans
+                .get(self.dashboard_select)
+                .map(|f| (f.id.clone(), f.label.clone()))
+        } else {
+            self.temps
+                .get(self.dashboard_select - fan_count)
+                .map(|t| (t.id.clone(), t.label.clone()))
+        };
+        if let Some((id, label)) = selected {
+            self.renaming = Some(RenameState {
+                id,
+                label: label.unwrap_or_default(),
+            });
+        }
+    }
+
+    fn save_label(&mut self) {
+        let Some(rename) = &self.renaming else {
+            return;
+        };
+        let req = Request::SetLabel {
+            target: rename.id.clone(),
+            label: Some(rename.label.clone()),
+        };
+
+        if let Some(conn) = &mut self.connection {
+            match conn.send_request(&req) {
+                Ok(Response::Ok { message }) => {
+                    self.status_message = message;
+                    self.renaming = None;
+                    self.refresh_status();
+                }
+                Ok(Response::Error { message }) => {
+                    self.status_message = format!("Error: {message}");
+                }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                    self.renaming = None;
+                }
+                Err(e) => {
+                    self.status_message = format!("Connection error: {e}");
+                    self.connection = None;
+                    self.renaming = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn delete_selected_curve(&mut self) {
         let Some(idx) = self.curve_list_state.selected() else {
             return;
@@ -378,6 +1015,9 @@ impl App {
                 Ok(Response::Error { message }) => {
                     self.status_message = format!("Error: {message}");
                 }
+                Ok(Response::Denied { message }) => {
+                    self.status_message = format!("Permission denied: {message}");
+                }
                 Err(e) => {
                     self.status_message = format!("Connection error: {e}");
                     self.connection = None;
@@ -421,19 +1061,30 @@ fn run_app(
     app: &mut App,
 ) -> anyhow::Result<()> {
     let tick_rate = Duration::from_millis(500);
+    // Only as long as it takes to notice a push
+    let push_rate = Duration::from_millis(100);
 
     while app.running {
+        app.drain_pushes();
+        if app.tab == Tab::Dashboard {
+            app.refresh_history();
+        }
         terminal.draw(|f| ui(f, app))?;
 
-        if event::poll(tick_rate)? {
+        let timeout = if app.pushes.is_some() {
+            push_rate
+        } else {
+            tick_rate
+        };
+        if event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
                 if key.kind != KeyEventKind::Press {
                     continue;
                 }
                 handle_input(app, key.code, key.modifiers);
             }
-        } else {
-            // Periodic refresh
+        } else if app.pushes.is_none() {
+            // Periodic refresh when the daemon isn't pushing
             app.refresh_status();
         }
     }
@@ -452,13 +1103,18 @@ fn handle_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
             app.running = false;
             return;
         }
-        KeyCode::Char('q') if app.editing_curve.is_none() => {
+        KeyCode::Char('q') if app.editing_curve.is_none() && app.renaming.is_none() => {
             app.running = false;
             return;
         }
         _ => {}
     }
 
+    if app.renaming.is_some() {
+        handle_rename_input(app, key);
+        return;
+    }
+
     // If editing a curve, handle curve editor keys
     if app.editing_curve.is_some() {
         handle_curve_edit_input(app, key);
@@ -492,18 +1148,57 @@ fn handle_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
 }
 
 fn handle_dashboard_input(app: &mut App, key: KeyCode) {
+    let rows = app.fans.len() + app.temps.len();
     match key {
         KeyCode::Char('r') => {
             app.refresh_status();
+            app.history_fetched = None;
             app.status_message = "Refreshed".to_string();
         }
+        KeyCode::Down | KeyCode::Char('j') if app.dashboard_select + 1 < rows => {
+            app.dashboard_select += 1;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.dashboard_select = app.dashboard_select.saturating_sub(1);
+        }
+        KeyCode::Char('+') | KeyCode::Char('=') => {
+            app.set_history_window(app.history_window.zoom_in());
+        }
+        KeyCode::Char('-') => app.set_history_window(app.history_window.zoom_out()),
+        KeyCode::Char('n') => app.start_rename(),
+        KeyCode::Char('H') => app.toggle_hidden(),
+        KeyCode::Char('w') => {
+            let all = HistoryWindow::ALL;
+            let i = all
+                .iter()
+                .position(|&w| w == app.history_window)
+                .unwrap_or(0);
+            app.set_history_window(all[(i + 1) % all.len()]);
+        }
+        _ => {}
+    }
+}
+
+fn handle_rename_input(app: &mut App, key: KeyCode) {
+    let Some(rename) = &mut app.renaming else {
+        return;
+    };
+    match key {
+        KeyCode::Esc => app.renaming = None,
+        KeyCode::Enter => app.save_label(),
+        KeyCode::Backspace => {
+            rename.label.pop();
+        }
+        KeyCode::Char(ch) if !ch.is_control() => rename.label.push(ch),
         _ => {}
     }
 }
 
+#[allow(clippy::collapsible_match)]
 fn handle_fan_control_input(app: &mut App, key: KeyCode) {
     let fan_count = app.fans.len();
     match key {
+        KeyCode::Char('H') => app.toggle_hidden(),
         KeyCode::Up | KeyCode::Char('k') => {
             if fan_count > 0 {
                 let i = app.fan_list_state.selected().unwrap_or(0);
@@ -523,6 +1218,16 @@ fn handle_fan_control_input(app: &mut App, key: KeyCode) {
         KeyCode::Char('m') => app.fan_mode_select = FanModeSelect::Manual,
         KeyCode::Char('a') => app.fan_mode_select = FanModeSelect::Auto,
         KeyCode::Char('c') => app.fan_mode_select = FanModeSelect::Curve,
+        KeyCode::Char('p') => app.fan_mode_select = FanModeSelect::Pid,
+        KeyCode::Char('C') => app.calibrate_selected_fan(),
+        KeyCode::Char('s') if app.fan_mode_select == FanModeSelect::Curve => {
+            let idx = SMOOTHING_PRESETS
+                .iter()
+                .position(|p| *p == app.fan_smoothing)
+                .map(|i| (i + 1) % SMOOTHING_PRESETS.len())
+                .unwrap_or(0);
+            app.fan_smoothing = SMOOTHING_PRESETS[idx];
+        }
         KeyCode::Left | KeyCode::Char('h') => {
             match app.fan_mode_select {
                 FanModeSelect::Manual => {
@@ -533,6 +1238,9 @@ fn handle_fan_control_input(app: &mut App, key: KeyCode) {
                         app.temp_sensor_select -= 1;
                     }
                 }
+                FanModeSelect::Pid => {
+                    app.pid_setpoint = (app.pid_setpoint - 1.0).max(20.0);
+                }
                 _ => {}
             }
         }
@@ -546,16 +1254,25 @@ fn handle_fan_control_input(app: &mut App, key: KeyCode) {
                         app.temp_sensor_select += 1;
                     }
                 }
+                FanModeSelect::Pid => {
+                    app.pid_setpoint = (app.pid_setpoint + 1.0).min(100.0);
+                }
                 _ => {}
             }
         }
         KeyCode::Char('[') => {
-            if app.curve_select > 0 {
+            if app.fan_mode_select == FanModeSelect::Pid {
+                app.temp_sensor_select = app.temp_sensor_select.saturating_sub(1);
+            } else if app.curve_select > 0 {
                 app.curve_select -= 1;
             }
         }
         KeyCode::Char(']') => {
-            if app.curve_select + 1 < app.curves.len() {
+            if app.fan_mode_select == FanModeSelect::Pid {
+                if app.temp_sensor_select + 1 < app.temps.len() {
+                    app.temp_sensor_select += 1;
+                }
+            } else if app.curve_select + 1 < app.curves.len() {
                 app.curve_select += 1;
             }
         }
@@ -579,8 +1296,10 @@ fn load_fan_assignment(app: &mut App) {
             FanAssignment::Curve {
                 curve_name,
                 temp_sensor_id,
+                smoothing,
             } => {
                 app.fan_mode_select = FanModeSelect::Curve;
+                app.fan_smoothing = smoothing;
                 if let Some(idx) = app.curves.iter().position(|c| c.name == curve_name) {
                     app.curve_select = idx;
                 }
@@ -588,12 +1307,38 @@ fn load_fan_assignment(app: &mut App) {
                     app.temp_sensor_select = idx;
                 }
             }
+            FanAssignment::Pid {
+                temp_sensor_id,
+                setpoint_c,
+                ..
+            } => {
+                app.fan_mode_select = FanModeSelect::Pid;
+                app.pid_setpoint = setpoint_c;
+                if let Some(idx) = app.temps.iter().position(|t| t.id == temp_sensor_id) {
+                    app.temp_sensor_select = idx;
+                }
+            }
+            FanAssignment::MultiCurve { inputs, .. } => {
+                // Multi-curve assignments are shown read-only; preload the
+                // first input so [Enter] narrows back to a single curve.
+                app.fan_mode_select = FanModeSelect::Curve;
+                if let Some(input) = inputs.first() {
+                    app.fan_smoothing = input.smoothing;
+                    if let Some(idx) = app.curves.iter().position(|c| c.name == input.curve_name) {
+                        app.curve_select = idx;
+                    }
+                    if let Some(idx) = app.temps.iter().position(|t| t.id == input.temp_sensor_id) {
+                        app.temp_sensor_select = idx;
+                    }
+                }
+            }
         }
     } else {
         app.fan_mode_select = FanModeSelect::Auto;
     }
 }
 
+#[allow(clippy::collapsible_match)]
 fn handle_curve_editor_input(app: &mut App, key: KeyCode) {
     let curve_count = app.curves.len();
     match key {
@@ -617,14 +1362,20 @@ fn handle_curve_editor_input(app: &mut App, key: KeyCode) {
                 name: "new_curve".to_string(),
                 points: vec![
                     CurvePoint {
-                        temp_c: 30.0,
+                        input: 30.0,
                         pwm: 0,
                     },
                     CurvePoint {
-                        temp_c: 90.0,
+                        input: 90.0,
                         pwm: 255,
                     },
                 ],
+                hysteresis: 0.0,
+                hold_ms: 0,
+                ramp_up_rate: 0.0,
+                ramp_down_rate: 0.0,
+                relative: false,
+                unit: InputUnit::Celsius,
                 selected_point: 0,
                 editing_field: CurveField::Name,
                 is_new: true,
@@ -637,8 +1388,14 @@ fn handle_curve_editor_input(app: &mut App, key: KeyCode) {
                     app.editing_curve = Some(CurveEditState {
                         name: curve.name.clone(),
                         points: curve.points.clone(),
+                        hysteresis: curve.hysteresis,
+                        hold_ms: curve.hold_ms,
+                        ramp_up_rate: curve.ramp_up_rate,
+                        ramp_down_rate: curve.ramp_down_rate,
+                        relative: curve.relative,
+                        unit: curve.unit,
                         selected_point: 0,
-                        editing_field: CurveField::Temp,
+                        editing_field: CurveField::Input,
                         is_new: false,
                     });
                 }
@@ -651,6 +1408,7 @@ fn handle_curve_editor_input(app: &mut App, key: KeyCode) {
     }
 }
 
+#[allow(clippy::collapsible_match, clippy::collapsible_if)]
 fn handle_curve_edit_input(app: &mut App, key: KeyCode) {
     let Some(edit) = &mut app.editing_curve else {
         return;
@@ -662,9 +1420,13 @@ fn handle_curve_edit_input(app: &mut App, key: KeyCode) {
         }
         KeyCode::Tab => {
             edit.editing_field = match edit.editing_field {
-                CurveField::Name => CurveField::Temp,
-                CurveField::Temp => CurveField::Pwm,
-                CurveField::Pwm => CurveField::Name,
+                CurveField::Name => CurveField::Input,
+                CurveField::Input => CurveField::Pwm,
+                CurveField::Pwm => CurveField::Hysteresis,
+                CurveField::Hysteresis => CurveField::Hold,
+                CurveField::Hold => CurveField::RampUp,
+                CurveField::RampUp => CurveField::RampDown,
+                CurveField::RampDown => CurveField::Name,
             };
         }
         KeyCode::Up | KeyCode::Char('k') => {
@@ -677,34 +1439,55 @@ fn handle_curve_edit_input(app: &mut App, key: KeyCode) {
                 edit.selected_point += 1;
             }
         }
-        KeyCode::Left | KeyCode::Char('h') => {
-            if let Some(point) = edit.points.get_mut(edit.selected_point) {
-                match edit.editing_field {
-                    CurveField::Temp => point.temp_c = (point.temp_c - 1.0).max(0.0),
-                    CurveField::Pwm => point.pwm = point.pwm.saturating_sub(5),
-                    CurveField::Name => {}
+        KeyCode::Left | KeyCode::Char('h') => match edit.editing_field {
+            CurveField::Hysteresis => {
+                let (step, _, _) = axis_steps(edit.unit);
+                edit.hysteresis = (edit.hysteresis - step / 2.0).max(0.0);
+            }
+            CurveField::Hold => edit.hold_ms = edit.hold_ms.saturating_sub(500),
+            CurveField::RampUp => edit.ramp_up_rate = (edit.ramp_up_rate - 5.0).max(0.0),
+            CurveField::RampDown => edit.ramp_down_rate = (edit.ramp_down_rate - 5.0).max(0.0),
+            field => {
+                let (step, min, _) = axis_steps(edit.unit);
+                if let Some(point) = edit.points.get_mut(edit.selected_point) {
+                    match field {
+                        CurveField::Input => point.input = (point.input - step).max(min),
+                        CurveField::Pwm => point.pwm = point.pwm.saturating_sub(5),
+                        _ => {}
+                    }
                 }
             }
-        }
-        KeyCode::Right | KeyCode::Char('l') => {
-            if let Some(point) = edit.points.get_mut(edit.selected_point) {
-                match edit.editing_field {
-                    CurveField::Temp => point.temp_c = (point.temp_c + 1.0).min(120.0),
-                    CurveField::Pwm => point.pwm = point.pwm.saturating_add(5),
-                    CurveField::Name => {}
+        },
+        KeyCode::Right | KeyCode::Char('l') => match edit.editing_field {
+            CurveField::Hysteresis => {
+                let (step, _, _) = axis_steps(edit.unit);
+                edit.hysteresis = (edit.hysteresis + step / 2.0).min(step * 20.0);
+            }
+            CurveField::Hold => edit.hold_ms = (edit.hold_ms + 500).min(60_000),
+            CurveField::RampUp => edit.ramp_up_rate = (edit.ramp_up_rate + 5.0).min(255.0),
+            CurveField::RampDown => edit.ramp_down_rate = (edit.ramp_down_rate + 5.0).min(255.0),
+            field => {
+                let (step, _, max) = axis_steps(edit.unit);
+                if let Some(point) = edit.points.get_mut(edit.selected_point) {
+                    match field {
+                        CurveField::Input => point.input = (point.input + step).min(max),
+                        CurveField::Pwm => point.pwm = point.pwm.saturating_add(5),
+                        _ => {}
+                    }
                 }
             }
-        }
+        },
         KeyCode::Char('+') | KeyCode::Char('=') => {
             // Add a new point
-            let new_temp = edit
+            let (step, _, max) = axis_steps(edit.unit);
+            let new_input = edit
                 .points
                 .last()
-                .map(|p| p.temp_c + 10.0)
-                .unwrap_or(50.0)
-                .min(120.0);
+                .map(|p| p.input + step * 10.0)
+                .unwrap_or(max / 2.0)
+                .min(max);
             edit.points.push(CurvePoint {
-                temp_c: new_temp,
+                input: new_input,
                 pwm: 128,
             });
             edit.selected_point = edit.points.len() - 1;
@@ -718,6 +1501,15 @@ fn handle_curve_edit_input(app: &mut App, key: KeyCode) {
                 }
             }
         }
+        KeyCode::Char('u') if edit.editing_field != CurveField::Name => {
+            edit.relative = !edit.relative;
+        }
+        KeyCode::Char('x') if edit.editing_field != CurveField::Name => {
+            // Cycle the input unit; points keep their values
+            let units = InputUnit::ALL;
+            let i = units.iter().position(|&u| u == edit.unit).unwrap_or(0);
+            edit.unit = units[(i + 1) % units.len()];
+        }
         KeyCode::Backspace => {
             if edit.editing_field == CurveField::Name && !edit.name.is_empty() {
                 edit.name.pop();
@@ -738,7 +1530,21 @@ fn handle_curve_edit_input(app: &mut App, key: KeyCode) {
 }
 
 fn handle_config_input(app: &mut App, key: KeyCode) {
+    let profile_count = app.profiles.len();
     match key {
+        KeyCode::Up | KeyCode::Char('k') if profile_count > 0 => {
+            let i = app.profile_list_state.selected().unwrap_or(0);
+            let new_i = if i == 0 { profile_count - 1 } else { i - 1 };
+            app.profile_list_state.select(Some(new_i));
+        }
+        KeyCode::Down | KeyCode::Char('j') if profile_count > 0 => {
+            let i = app.profile_list_state.selected().unwrap_or(0);
+            let new_i = (i + 1) % profile_count;
+            app.profile_list_state.select(Some(new_i));
+        }
+        KeyCode::Enter => {
+            app.activate_selected_profile();
+        }
         KeyCode::Char('s') => {
             app.save_config();
         }
@@ -749,10 +1555,14 @@ fn handle_config_input(app: &mut App, key: KeyCode) {
                         app.status_message = message;
                         app.refresh_status();
                         app.refresh_curves();
+                        app.refresh_profiles();
                     }
                     Ok(Response::Error { message }) => {
                         app.status_message = format!("Error: {message}");
                     }
+                    Ok(Response::Denied { message }) => {
+                        app.status_message = format!("Permission denied: {message}");
+                    }
                     Err(e) => {
                         app.status_message = format!("Connection error: {e}");
                         app.connection = None;
@@ -794,6 +1604,10 @@ fn ui(f: &mut Frame, app: &App) {
     if app.editing_curve.is_some() {
         draw_curve_edit_overlay(f, app);
     }
+
+    if app.renaming.is_some() {
+        draw_rename_overlay(f, app);
+    }
 }
 
 fn draw_tabs(f: &mut Frame, app: &App, area: Rect) {
@@ -819,6 +1633,8 @@ fn draw_tabs(f: &mut Frame, app: &App, area: Rect) {
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let connected = if app.connection.is_some() {
         Span::styled(" CONNECTED ", Style::default().fg(Color::Green).bold())
+    } else if app.incompatible {
+        Span::styled(" INCOMPATIBLE ", Style::default().fg(Color::Red).bold())
     } else {
         Span::styled(" DISCONNECTED ", Style::default().fg(Color::Red).bold())
     };
@@ -826,13 +1642,24 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let msg = Span::raw(format!("  {}", app.status_message));
 
     let help = match app.tab {
-        Tab::Dashboard => " [r]efresh  [q]uit ",
-        Tab::FanControl => " [j/k]nav  [a]uto [m]anual [c]urve  [h/l]adjust  [Enter]apply  [q]uit ",
+        Tab::Dashboard => " [j/k]select  [n]ame  [H]idden  [+/-]zoom  [w]indow  [r]efresh  [q]uit ",
+        Tab::FanControl => {
+            " [j/k]nav  [a]uto [m]anual [c]urve [p]id  [h/l]adjust  [s]moothing  [C]alibrate  [H]idden  [Enter]apply  [q]uit "
+        }
         Tab::CurveEditor => " [j/k]nav  [n]ew [e]dit [d]elete  [q]uit ",
-        Tab::Config => " [s]ave  [r]eload  [q]uit ",
+        Tab::Config => " [j/k]nav  [Enter]switch profile  [s]ave  [r]eload  [q]uit ",
     };
 
-    let status_line = Line::from(vec![connected, msg]);
+    let mut spans = vec![connected];
+    if let Some(name) = &app.active_profile {
+        let modified = app.profiles.iter().any(|p| p.active && p.modified);
+        spans.push(Span::styled(
+            format!(" {name}{} ", if modified { "*" } else { "" }),
+            Style::default().fg(Color::Black).bg(Color::Cyan),
+        ));
+    }
+    spans.push(msg);
+    let status_line = Line::from(spans);
     let help_line = Line::from(Span::styled(help, Style::default().fg(Color::DarkGray)));
 
     let paragraph = Paragraph::new(vec![status_line, help_line])
@@ -842,10 +1669,15 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(area);
+        .split(rows[0]);
+    let highlight = Style::default().bg(Color::DarkGray);
 
     // Fan table
     let fan_rows: Vec<Row> = app
@@ -860,10 +1692,13 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
                 .rpm
                 .map(|r| format!("{r}"))
                 .unwrap_or_else(|| "-".to_string());
-            let pwm = fan
+            let mut pwm = fan
                 .pwm
                 .map(|p| format!("{p} ({:.0}%)", p as f64 / 255.0 * 100.0))
                 .unwrap_or_else(|| "-".to_string());
+            if let Some(target) = fan.target_pwm.filter(|&t| Some(t) != fan.applied_pwm) {
+                pwm.push_str(&format!(" → {target}"));
+            }
             let mode = fan
                 .pwm_enable
                 .map(|e| match e {
@@ -873,13 +1708,28 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
                     _ => "?",
                 })
                 .unwrap_or("-");
+            let mode_cell = if fan.forced.is_some() {
+                Cell::from(format!("{mode} (forced)")).style(Style::default().fg(Color::Red))
+            } else {
+                Cell::from(mode)
+            };
+
+            let rpm_cell = if fan.stalled {
+                Cell::from(format!("{rpm} STALL"))
+                    .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+            } else {
+                Cell::from(rpm)
+            };
+            let trend = sparkline(&fan_series(&app.history, &fan.id), SPARKLINE_WIDTH);
 
             Row::new(vec![
-                Cell::from(fan.id.clone()),
+                Cell::from(fan.id.clone()).style(hidden_style(fan.hidden)),
+                Cell::from(fan.legacy_id.clone()),
                 Cell::from(label.to_string()),
-                Cell::from(rpm),
+                rpm_cell,
                 Cell::from(pwm),
-                Cell::from(mode),
+                mode_cell,
+                Cell::from(trend).style(Style::default().fg(Color::Cyan)),
             ])
         })
         .collect();
@@ -887,24 +1737,30 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
     let fan_table = Table::new(
         fan_rows,
         [
-            Constraint::Percentage(25),
-            Constraint::Percentage(20),
+            Constraint::Percentage(22),
+            Constraint::Percentage(12),
+            Constraint::Percentage(12),
+            Constraint::Percentage(10),
+            Constraint::Percentage(17),
+            Constraint::Percentage(12),
             Constraint::Percentage(15),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
         ],
     )
     .header(
-        Row::new(vec!["Fan ID", "Label", "RPM", "PWM", "Mode"])
-            .style(Style::default().fg(Color::Cyan).bold()),
+        Row::new(vec![
+            "Fan ID", "Sysfs", "Label", "RPM", "PWM", "Mode", "Trend",
+        ])
+        .style(Style::default().fg(Color::Cyan).bold()),
     )
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Fans "),
-    );
+    .row_highlight_style(highlight)
+    .block(Block::default().borders(Borders::ALL).title(format!(
+        " Fans{} ",
+        hidden_note(&app.all_fans, |f| f.hidden, app.show_hidden)
+    )));
 
-    f.render_widget(fan_table, chunks[0]);
+    let fan_selected = (app.dashboard_select < app.fans.len()).then_some(app.dashboard_select);
+    let mut fan_state = TableState::default().with_selected(fan_selected);
+    f.render_stateful_widget(fan_table, chunks[0], &mut fan_state);
 
     // Temp table
     let temp_rows: Vec<Row> = app
@@ -918,22 +1774,42 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
             let value = temp
                 .temp_c
                 .map(|t| {
-                    let color = if t >= 80.0 {
+                    let over_limit = [temp.crit_c, temp.max_c]
+                        .iter()
+                        .flatten()
+                        .any(|&limit| t >= limit);
+                    let (warn, hot) = match temp.unit {
+                        InputUnit::Celsius => (60.0, 80.0),
+                        InputUnit::Percent => (50.0, 90.0),
+                        // No fixed scale for these
+                        InputUnit::Load | InputUnit::Rpm | InputUnit::Other => {
+                            (f64::INFINITY, f64::INFINITY)
+                        }
+                    };
+                    let color = if t >= hot || over_limit {
                         Color::Red
-                    } else if t >= 60.0 {
+                    } else if t >= warn {
                         Color::Yellow
                     } else {
                         Color::Green
                     };
-                    Span::styled(format!("{t:.1}°C"), Style::default().fg(color))
+                    Span::styled(temp.unit.format(t), Style::default().fg(color))
                 })
                 .unwrap_or_else(|| Span::raw("-"));
+            let filtered = temp
+                .filtered_c
+                .map(|t| temp.unit.format(t))
+                .unwrap_or_else(|| "-".to_string());
+            let trend = sparkline(&sensor_series(&app.history, &temp.id), SPARKLINE_WIDTH);
 
             Row::new(vec![
-                Cell::from(temp.id.clone()),
+                Cell::from(temp.id.clone()).style(hidden_style(temp.hidden)),
+                Cell::from(temp.legacy_id.clone()),
                 Cell::from(label.to_string()),
                 Cell::from(value),
+                Cell::from(filtered),
                 Cell::from(temp.hwmon_name.clone()),
+                Cell::from(trend).style(Style::default().fg(Color::Cyan)),
             ])
         })
         .collect();
@@ -941,23 +1817,296 @@ fn draw_dashboard(f: &mut Frame, app: &App, area: Rect) {
     let temp_table = Table::new(
         temp_rows,
         [
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
+            Constraint::Percentage(22),
+            Constraint::Percentage(12),
+            Constraint::Percentage(12),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(15),
         ],
     )
     .header(
-        Row::new(vec!["Sensor ID", "Label", "Temp", "Device"])
-            .style(Style::default().fg(Color::Cyan).bold()),
+        Row::new(vec![
+            "Sensor ID",
+            "Sysfs",
+            "Label",
+            "Reading",
+            "Filtered",
+            "Device",
+            "Trend",
+        ])
+        .style(Style::default().fg(Color::Cyan).bold()),
     )
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Temperatures "),
-    );
+    .row_highlight_style(highlight)
+    .block(Block::default().borders(Borders::ALL).title(format!(
+        " Sensors{} ",
+        hidden_note(&app.all_temps, |t| t.hidden, app.show_hidden)
+    )));
+
+    let temp_selected = app.dashboard_select.checked_sub(app.fans.len());
+    let mut temp_state = TableState::default().with_selected(temp_selected);
+    f.render_stateful_widget(temp_table, chunks[1], &mut temp_state);
+
+    draw_history_chart(f, app, rows[1]);
+}
+
+/// Width of the trend column's sparklines, in cells.
+const SPARKLINE_WIDTH: usize = 16;
+
+/// Chart the selected fan's PWM or sensor's readings over the history
+/// window, with the sensor's kernel limits and the knees of the curves
+/// involved drawn across it.
+fn draw_history_chart(f: &mut Frame, app: &App, area: Rect) {
+    let window = app.history_window;
+    let supported = app
+        .daemon
+        .as_ref()
+        .is_some_and(|d| d.features.iter().any(|f| f == "history"));
+    let fan = app.fans.get(app.dashboard_select);
+    let temp = app
+        .dashboard_select
+        .checked_sub(app.fans.len())
+        .and_then(|i| app.temps.get(i));
+    let message = if !supported {
+        Some("The daemon doesn't keep history")
+    } else if fan.is_none() && temp.is_none() {
+        Some("Select a fan or sensor with j/k")
+    } else {
+        None
+    };
+    if let Some(message) = message {
+        let block = Block::default().borders(Borders::ALL).title(" History ");
+        f.render_widget(Paragraph::new(message).block(block), area);
+        return;
+    }
+
+    let now = history::now_ms() as f64;
+    let to_x = |points: Vec<(u64, f64)>| -> Vec<(f64, f64)> {
+        points
+            .into_iter()
+            .map(|(t, v)| ((t as f64 - now) / 1000.0, v))
+            .collect()
+    };
+    // Horizontal marks: (legend name, value, color)
+    let mut marks: Vec<(String, f64, Color)> = Vec::new();
+    let (title, data, unit) = if let Some(fan) = fan {
+        for (curve, _) in fan_curves(app, &fan.id) {
+            let knees = curve.points.iter().map(|p| f64::from(p.pwm));
+            push_knees(&mut marks, &curve.name, knees);
+        }
+        let title = format!(" {} PWM, last {} ", fan.id, window.label());
+        (title, to_x(fan_series(&app.history, &fan.id)), None)
+    } else {
+        let temp = temp.expect("checked above");
+        if let Some(max) = temp.max_c {
+            marks.push(("max".to_string(), max, Color::Yellow));
+        }
+        if let Some(crit) = temp.crit_c {
+            marks.push(("crit".to_string(), crit, Color::Red));
+        }
+        for curve in sensor_curves(app, &temp.id) {
+            let knees = curve.points.iter().map(|p| p.input);
+            push_knees(&mut marks, &curve.name, knees);
+        }
+        for info in &app.assignments {
+            if let FanAssignment::Pid {
+                temp_sensor_id,
+                setpoint_c,
+                ..
+            } = &info.assignment
+            {
+                if *temp_sensor_id == temp.id {
+                    marks.push(("setpoint".to_string(), *setpoint_c, Color::Magenta));
+                }
+            }
+        }
+        let title = format!(
+            " {} {}, last {} ",
+            temp.id,
+            temp.unit.quantity(),
+            window.label()
+        );
+        (
+            title,
+            to_x(sensor_series(&app.history, &temp.id)),
+            Some(temp.unit),
+        )
+    };
+
+    // Fit the readings and the marks, with a little headroom
+    let (lo, hi) = match unit {
+        None => (0.0, 255.0),
+        Some(_) => {
+            let values = data
+                .iter()
+                .map(|&(_, v)| v)
+                .chain(marks.iter().map(|m| m.1));
+            let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+            if lo > hi {
+                (0.0, 100.0)
+            } else {
+                let pad = ((hi - lo) * 0.1).max(1.0);
+                (lo - pad, hi + pad)
+            }
+        }
+    };
+    let x_min = -(window.secs() as f64);
+    let mark_lines: Vec<Vec<(f64, f64)>> = marks
+        .iter()
+        .map(|&(_, v, _)| vec![(x_min, v), (0.0, v)])
+        .collect();
+
+    let mut datasets = vec![
+        Dataset::default()
+            .name(if unit.is_some() { "reading" } else { "PWM" })
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&data),
+    ];
+    for ((name, _, color), line) in marks.iter().zip(&mark_lines) {
+        let mut dataset = Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(*color))
+            .data(line);
+        if !name.is_empty() {
+            dataset = dataset.name(name.clone());
+        }
+        datasets.push(dataset);
+    }
+
+    let format_y = |v: f64| match unit {
+        Some(unit) => format_axis(unit, v),
+        None => format!("{v:.0}"),
+    };
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .x_axis(
+            Axis::default()
+                .bounds([x_min, 0.0])
+                .labels(vec![
+                    Span::raw(format!("-{}", window.label())),
+                    Span::raw(format_ago(window.secs() / 2)),
+                    Span::raw("now"),
+                ])
+                .style(Style::default().fg(Color::DarkGray)),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([lo, hi])
+                .labels(vec![
+                    Span::raw(format_y(lo)),
+                    Span::raw(format_y((lo + hi) / 2.0)),
+                    Span::raw(format_y(hi)),
+                ])
+                .style(Style::default().fg(Color::DarkGray)),
+        )
+        .hidden_legend_constraints((Constraint::Ratio(1, 3), Constraint::Ratio(1, 2)));
+    f.render_widget(chart, area);
+}
+
+/// Add one mark per curve knee, naming only the first so the legend gets
+/// one entry per curve.
+fn push_knees(
+    marks: &mut Vec<(String, f64, Color)>,
+    curve: &str,
+    knees: impl Iterator<Item = f64>,
+) {
+    for (i, knee) in knees.enumerate() {
+        let name = if i == 0 {
+            format!("{curve} knees")
+        } else {
+            String::new()
+        };
+        marks.push((name, knee, Color::Green));
+    }
+}
+
+/// Curves driving a fan, with the sensor each one reads.
+fn fan_curves<'a>(app: &'a App, fan_id: &str) -> Vec<(&'a CurveData, &'a str)> {
+    let Some((inputs, _)) = app
+        .assignments
+        .iter()
+        .find(|a| a.fan_id == fan_id)
+        .and_then(|a| a.assignment.curve_inputs())
+    else {
+        return Vec::new();
+    };
+    inputs
+        .into_iter()
+        .filter_map(|input| {
+            let curve = app.curves.iter().find(|c| c.name == input.curve_name)?;
+            Some((curve, input.temp_sensor_id))
+        })
+        .collect()
+}
+
+/// Curves a sensor feeds, on any fan.
+fn sensor_curves<'a>(app: &'a App, sensor_id: &str) -> Vec<&'a CurveData> {
+    let mut curves: Vec<&CurveData> = Vec::new();
+    for info in &app.assignments {
+        for (curve, sensor) in fan_curves(app, &info.fan_id) {
+            if sensor == sensor_id && !curves.iter().any(|c| c.name == curve.name) {
+                curves.push(curve);
+            }
+        }
+    }
+    curves
+}
+
+/// A sensor's readings from the history, as (time, value).
+fn sensor_series(samples: &[Sample], id: &str) -> Vec<(u64, f64)> {
+    samples
+        .iter()
+        .filter_map(|s| s.sensors.get(id).map(|&v| (s.time_ms, v)))
+        .collect()
+}
+
+/// A fan's applied PWM from the history, as (time, value).
+fn fan_series(samples: &[Sample], id: &str) -> Vec<(u64, f64)> {
+    samples
+        .iter()
+        .filter_map(|s| Some((s.time_ms, f64::from(s.fans.get(id)?.pwm?))))
+        .collect()
+}
 
-    f.render_widget(temp_table, chunks[1]);
+/// Utility: render values as at most `width` block characters, averaging
+/// neighbours as needed and scaling between the minimum and maximum.
+fn sparkline(series: &[(u64, f64)], width: usize) -> String {
+    const BARS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+    let values: Vec<f64> = series.iter().map(|&(_, v)| v).collect();
+    // Spread the window over the available cells
+    let step = values.len().div_ceil(width).max(1);
+    let buckets: Vec<f64> = values
+        .chunks(step)
+        .map(|c| c.iter().sum::<f64>() / c.len() as f64)
+        .collect();
+    let lo = buckets.iter().copied().fold(f64::INFINITY, f64::min);
+    let hi = buckets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    buckets
+        .iter()
+        .map(|&v| {
+            let level = if hi - lo > f64::EPSILON {
+                ((v - lo) / (hi - lo) * 7.0).round() as usize
+            } else {
+                3
+            };
+            BARS[level.min(7)]
+        })
+        .collect()
+}
+
+/// Utility: format a time in the past compactly, e.g. "-30s" or "-5m".
+fn format_ago(secs: u64) -> String {
+    if secs >= 60 && secs % 60 == 0 {
+        format!("-{}m", secs / 60)
+    } else {
+        format!("-{secs}s")
+    }
 }
 
 fn draw_fan_control(f: &mut Frame, app: &App, area: Rect) {
@@ -998,7 +2147,7 @@ fn draw_fan_control(f: &mut Frame, app: &App, area: Rect) {
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(5),  // Mode selector
-            Constraint::Length(5),  // Control value
+            Constraint::Length(6), // Control value
             Constraint::Min(0),    // Info
         ])
         .split(control_area);
@@ -1023,10 +2172,16 @@ fn draw_fan_control(f: &mut Frame, app: &App, area: Rect) {
             } else {
                 Span::styled(" ○ Curve ", Style::default().fg(Color::Gray))
             },
+            Span::raw("  "),
+            if app.fan_mode_select == FanModeSelect::Pid {
+                Span::styled(" ● PID ", Style::default().fg(Color::Blue).bold())
+            } else {
+                Span::styled(" ○ PID ", Style::default().fg(Color::Gray))
+            },
         ]),
         Line::from(""),
         Line::from(Span::styled(
-            " Press [a]uto [m]anual [c]urve to switch mode",
+            " Press [a]uto [m]anual [c]urve [p]id to switch mode",
             Style::default().fg(Color::DarkGray),
         )),
     ];
@@ -1087,12 +2242,35 @@ fn draw_fan_control(f: &mut Frame, app: &App, area: Rect) {
             vec![
                 Line::from(format!("Curve: {curve_name}  (use [/] to cycle)")),
                 Line::from(format!("Sensor: {sensor_name}  (use [h/l] to cycle)")),
+                Line::from(format!(
+                    "Smoothing: {}  (use [s] to cycle)",
+                    describe_smoothing(app.fan_smoothing.as_ref())
+                )),
                 Line::from(Span::styled(
                     "[Enter] to apply",
                     Style::default().fg(Color::DarkGray),
                 )),
             ]
         }
+        FanModeSelect::Pid => {
+            let sensor_name = app
+                .temps
+                .get(app.temp_sensor_select)
+                .map(|t| t.label.as_deref().unwrap_or(&t.id))
+                .unwrap_or("(none)");
+
+            vec![
+                Line::from(format!(
+                    "Setpoint: {:.0}°C  (use [h/l] to adjust)",
+                    app.pid_setpoint
+                )),
+                Line::from(format!("Sensor: {sensor_name}  (use [/] to cycle)")),
+                Line::from(Span::styled(
+                    "[Enter] to apply (retunes live if already under PID)",
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ]
+        }
     };
 
     let control_widget = Paragraph::new(control_text).block(
@@ -1116,18 +2294,67 @@ fn draw_fan_control(f: &mut Frame, app: &App, area: Rect) {
             Some(FanAssignment::Curve {
                 curve_name,
                 temp_sensor_id,
-            }) => format!("Curve: {curve_name} tracking {temp_sensor_id}"),
+                smoothing,
+            }) => format!(
+                "Curve: {curve_name} tracking {temp_sensor_id} ({})",
+                describe_smoothing(smoothing.as_ref())
+            ),
+            Some(FanAssignment::MultiCurve { inputs, combiner }) => format!(
+                "{} curves, {combiner:?} of: {}",
+                inputs.len(),
+                inputs
+                    .iter()
+                    .map(|i| format!("{} @ {}", i.curve_name, i.temp_sensor_id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Some(FanAssignment::Pid {
+                temp_sensor_id,
+                setpoint_c,
+                kp,
+                ki,
+                kd,
+                ..
+            }) => format!("PID: {temp_sensor_id} at {setpoint_c:.1}°C (kp {kp}, ki {ki}, kd {kd})"),
             None => "No assignment (automatic)".to_string(),
         };
 
-        vec![
+        let mut lines = vec![
             Line::from(format!("Fan: {}", fan.id)),
+            Line::from(format!("Sysfs: {}", fan.legacy_id)),
             Line::from(format!(
                 "RPM: {}",
                 fan.rpm.map(|r| r.to_string()).unwrap_or("-".to_string())
             )),
             Line::from(format!("Current assignment: {assign_str}")),
-        ]
+        ];
+        if let Some(input) = &fan.active_input {
+            lines.push(Line::from(format!("Driven by: {input}")));
+        }
+        if fan.stalled {
+            lines.push(Line::from(Span::styled(
+                "STALLED: fan reads 0 RPM while driven",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+        }
+        if let Some(progress) = &fan.calibrating {
+            lines.push(Line::from(Span::styled(
+                format!("Calibrating: {progress}"),
+                Style::default().fg(Color::Yellow),
+            )));
+        } else if let Some(calibration) = &fan.calibration {
+            lines.push(Line::from(format!(
+                "Calibration: {}",
+                calibration.describe()
+            )));
+        }
+        if let Some(forced) = &fan.forced {
+            lines.push(Line::from(Span::styled(
+                format!("Overridden: {forced}"),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+        }
+        lines
     } else {
         vec![Line::from("Select a fan from the list")]
     };
@@ -1137,7 +2364,51 @@ fn draw_fan_control(f: &mut Frame, app: &App, area: Rect) {
             .borders(Borders::ALL)
             .title(" Current Status "),
     );
-    f.render_widget(info_widget, control_chunks[2]);
+
+    // PID terms panel for fans under closed-loop control
+    match app.selected_fan().and_then(|f| f.pid) {
+        Some(terms) => {
+            let info_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(8)])
+                .split(control_chunks[2]);
+            f.render_widget(info_widget, info_chunks[0]);
+            draw_pid_terms(f, &terms, info_chunks[1]);
+        }
+        None => f.render_widget(info_widget, control_chunks[2]),
+    }
+}
+
+fn draw_pid_terms(f: &mut Frame, terms: &PidTerms, area: Rect) {
+    let bar = |value: f64| {
+        let width = (value.abs() / 255.0 * 20.0).round().min(20.0) as usize;
+        let glyph = if value >= 0.0 { "█" } else { "░" };
+        glyph.repeat(width)
+    };
+    let term_line = |name: &str, value: f64, color: Color| {
+        Line::from(vec![
+            Span::raw(format!("{name} {value:>+8.1} ")),
+            Span::styled(bar(value), Style::default().fg(color)),
+        ])
+    };
+
+    let lines = vec![
+        Line::from(format!(
+            "Setpoint {:.1}°C, error {:+.2}°C",
+            terms.setpoint_c, terms.error_c
+        )),
+        term_line("P", terms.p, Color::Yellow),
+        term_line("I", terms.i, Color::Magenta),
+        term_line("D", terms.d, Color::Cyan),
+        Line::from(format!(
+            "Output PWM {} ({:.0}%)",
+            terms.output,
+            terms.output as f64 / 255.0 * 100.0
+        )),
+    ];
+
+    let widget = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" PID "));
+    f.render_widget(widget, area);
 }
 
 fn draw_curve_editor(f: &mut Frame, app: &App, area: Rect) {
@@ -1154,7 +2425,7 @@ fn draw_curve_editor(f: &mut Frame, app: &App, area: Rect) {
             let points_str = c
                 .points
                 .iter()
-                .map(|p| format!("{:.0}°→{}", p.temp_c, p.pwm))
+                .map(|p| format!("{}→{}", format_axis(c.unit, p.input), p.pwm))
                 .collect::<Vec<_>>()
                 .join(", ");
             ListItem::new(format!("{}: {points_str}", c.name))
@@ -1195,26 +2466,29 @@ fn draw_curve_editor(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(preview_widget, chunks[1]);
 }
 
+#[allow(clippy::needless_range_loop)]
 fn render_curve_graph(curve: &CurveData) -> Vec<Line<'static>> {
     let graph_height = 12usize;
     let graph_width = 50usize;
 
     let mut lines = Vec::new();
     lines.push(Line::from(format!("  Curve: {}", curve.name)));
-    lines.push(Line::from(""));
+    lines.push(Line::from(format!("  Input: {}", curve.unit.quantity())));
 
     // Build a simple ASCII graph
     let mut grid = vec![vec![' '; graph_width]; graph_height];
 
-    // Map temp range and PWM range to graph coordinates
-    let min_temp = curve.points.first().map(|p| p.temp_c).unwrap_or(0.0);
-    let max_temp = curve.points.last().map(|p| p.temp_c).unwrap_or(100.0);
-    let temp_range = (max_temp - min_temp).max(1.0);
+    // Map input range and PWM range to graph coordinates
+    let min_input = curve.points.first().map(|p| p.input).unwrap_or(0.0);
+    let max_input = curve.points.last().map(|p| p.input).unwrap_or(100.0);
+    let span = max_input - min_input;
+    // Load curves may span less than one unit
+    let input_range = if span > 0.0 { span } else { 1.0 };
 
     for x in 0..graph_width {
-        let temp = min_temp + (x as f64 / graph_width as f64) * temp_range;
+        let input = min_input + (x as f64 / graph_width as f64) * input_range;
         // Simple interpolation
-        let pwm = interpolate_points(&curve.points, temp);
+        let pwm = interpolate_points(&curve.points, input);
         let y = ((pwm as f64 / 255.0) * (graph_height - 1) as f64).round() as usize;
         let y = y.min(graph_height - 1);
         let row = graph_height - 1 - y; // Invert for display
@@ -1230,10 +2504,11 @@ fn render_curve_graph(curve: &CurveData) -> Vec<Line<'static>> {
 
     let axis = format!("      └{}", "─".repeat(graph_width));
     lines.push(Line::from(axis));
+    let min_label = format_axis(curve.unit, min_input);
+    let max_label = format_axis(curve.unit, max_input);
     lines.push(Line::from(format!(
-        "       {min_temp:.0}°C{:>width$}{max_temp:.0}°C",
-        "",
-        width = graph_width - 8
+        "       {min_label}{max_label:>width$}",
+        width = graph_width - min_label.chars().count()
     )));
 
     // Point details
@@ -1242,34 +2517,54 @@ fn render_curve_graph(curve: &CurveData) -> Vec<Line<'static>> {
     for p in &curve.points {
         let pct = p.pwm as f64 / 255.0 * 100.0;
         lines.push(Line::from(format!(
-            "    {:.0}°C → PWM {} ({pct:.0}%)",
-            p.temp_c, p.pwm
+            "    {} → PWM {} ({pct:.0}%)",
+            format_axis(curve.unit, p.input),
+            p.pwm
+        )));
+    }
+    if curve.hysteresis > 0.0 || curve.hold_ms > 0 {
+        lines.push(Line::from(format!(
+            "  Ramp-down: {} hysteresis, {:.1}s hold",
+            curve.unit.format(curve.hysteresis),
+            curve.hold_ms as f64 / 1000.0
+        )));
+    }
+    if curve.ramp_up_rate > 0.0 || curve.ramp_down_rate > 0.0 {
+        lines.push(Line::from(format!(
+            "  Ramp rate: ↑ {}  ↓ {}",
+            format_rate(curve.ramp_up_rate),
+            format_rate(curve.ramp_down_rate)
         )));
     }
+    if curve.relative {
+        lines.push(Line::from(
+            "  PWM relative to each fan's calibrated usable range",
+        ));
+    }
 
     lines
 }
 
-fn interpolate_points(points: &[CurvePoint], temp: f64) -> u8 {
+fn interpolate_points(points: &[CurvePoint], input: f64) -> u8 {
     if points.is_empty() {
         return 0;
     }
-    if temp <= points[0].temp_c {
+    if input <= points[0].input {
         return points[0].pwm;
     }
     let last = &points[points.len() - 1];
-    if temp >= last.temp_c {
+    if input >= last.input {
         return last.pwm;
     }
     for window in points.windows(2) {
         let lo = &window[0];
         let hi = &window[1];
-        if temp >= lo.temp_c && temp <= hi.temp_c {
-            let range = hi.temp_c - lo.temp_c;
+        if input >= lo.input && input <= hi.input {
+            let range = hi.input - lo.input;
             if range == 0.0 {
                 return lo.pwm;
             }
-            let frac = (temp - lo.temp_c) / range;
+            let frac = (input - lo.input) / range;
             let pwm = lo.pwm as f64 + frac * (hi.pwm as f64 - lo.pwm as f64);
             return pwm.round().clamp(0.0, 255.0) as u8;
         }
@@ -1277,6 +2572,37 @@ fn interpolate_points(points: &[CurvePoint], temp: f64) -> u8 {
     last.pwm
 }
 
+fn draw_rename_overlay(f: &mut Frame, app: &App) {
+    let Some(rename) = &app.renaming else {
+        return;
+    };
+
+    let area = centered_rect(50, 20, f.area());
+    f.render_widget(Clear, area);
+
+    let lines = vec![
+        Line::from(Span::styled(
+            &rename.id,
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(vec![
+            Span::raw("Label: "),
+            Span::styled(
+                format!("{}_", rename.label),
+                Style::default().fg(Color::Cyan).bold(),
+            ),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            "[Enter]save  [Esc]cancel  (empty clears)",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    let paragraph =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Rename "));
+    f.render_widget(paragraph, area);
+}
+
 fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
     let Some(edit) = &app.editing_curve else {
         return;
@@ -1289,6 +2615,7 @@ fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Name
+            Constraint::Length(3), // Hysteresis / hold / ramp rates
             Constraint::Min(0),   // Points table
             Constraint::Length(3), // Help
         ])
@@ -1319,6 +2646,44 @@ fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
         );
     f.render_widget(name_widget, chunks[0]);
 
+    // Hysteresis / hold / ramp rate fields
+    let field_style = |field| {
+        if edit.editing_field == field {
+            Style::default().fg(Color::Yellow).bold()
+        } else {
+            Style::default()
+        }
+    };
+    let settings = Line::from(vec![
+        Span::raw("Hysteresis: "),
+        Span::styled(
+            edit.unit.format(edit.hysteresis),
+            field_style(CurveField::Hysteresis),
+        ),
+        Span::raw("   Hold: "),
+        Span::styled(
+            format!("{:.1}s", edit.hold_ms as f64 / 1000.0),
+            field_style(CurveField::Hold),
+        ),
+        Span::raw("   Ramp ↑ "),
+        Span::styled(
+            format_rate(edit.ramp_up_rate),
+            field_style(CurveField::RampUp),
+        ),
+        Span::raw("  ↓ "),
+        Span::styled(
+            format_rate(edit.ramp_down_rate),
+            field_style(CurveField::RampDown),
+        ),
+        Span::raw("   PWM: "),
+        Span::raw(if edit.relative { "usable range" } else { "raw" }),
+        Span::raw("   Input: "),
+        Span::raw(edit.unit.to_string()),
+    ]);
+    let settings_widget =
+        Paragraph::new(settings).block(Block::default().borders(Borders::ALL).title(" Response "));
+    f.render_widget(settings_widget, chunks[1]);
+
     // Points table
     let point_rows: Vec<Row> = edit
         .points
@@ -1332,7 +2697,8 @@ fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
                 Style::default()
             };
 
-            let temp_style = if i == edit.selected_point && edit.editing_field == CurveField::Temp {
+            let input_style = if i == edit.selected_point && edit.editing_field == CurveField::Input
+            {
                 Style::default().fg(Color::Yellow).bold()
             } else {
                 style
@@ -1345,7 +2711,7 @@ fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
 
             Row::new(vec![
                 Cell::from(format!("{}", i + 1)).style(style),
-                Cell::from(format!("{:.0}°C", p.temp_c)).style(temp_style),
+                Cell::from(format_axis(edit.unit, p.input)).style(input_style),
                 Cell::from(format!("{} ({pct:.0}%)", p.pwm)).style(pwm_style),
             ])
         })
@@ -1360,7 +2726,7 @@ fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
         ],
     )
     .header(
-        Row::new(vec!["#", "Temp", "PWM"])
+        Row::new(vec!["#", edit.unit.quantity(), "PWM"])
             .style(Style::default().fg(Color::Cyan).bold()),
     )
     .block(
@@ -1369,29 +2735,46 @@ fn draw_curve_edit_overlay(f: &mut Frame, app: &App) {
             .title(" Points "),
     );
 
-    f.render_widget(points_table, chunks[1]);
+    f.render_widget(points_table, chunks[2]);
 
     // Help
     let help = Paragraph::new(
-        " [j/k]select  [h/l]adjust  [+]add  [-]remove  [Tab]field  [Enter]save  [Esc]cancel ",
+        " [j/k]select  [h/l]adjust  [+]add  [-]remove  [Tab]field  [u]sable range  [x]unit  [Enter]save  [Esc]cancel ",
     )
     .style(Style::default().fg(Color::DarkGray))
     .block(Block::default().borders(Borders::ALL));
 
-    f.render_widget(help, chunks[2]);
+    f.render_widget(help, chunks[3]);
 }
 
 fn draw_config(f: &mut Frame, app: &App, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(6),  // Config info
+            Constraint::Length(9), // Config info
             Constraint::Min(0),     // Current assignments
         ])
         .split(area);
 
+    let daemon = match &app.daemon {
+        Some(d) => format!(
+            "Daemon: fanctl-daemon {} (protocol {})",
+            d.version,
+            protocol::PROTOCOL_VERSION
+        ),
+        None => "Daemon: not connected".to_string(),
+    };
     let config_info = vec![
         Line::from(format!("Config path: {}", app.config_path)),
+        Line::from(daemon),
+        Line::from(match &app.schedule {
+            Some(schedule) => format!("Schedule: {}", schedule.describe()),
+            None => "Schedule: none".to_string(),
+        }),
+        Line::from(match &app.trigger {
+            Some(trigger) => format!("Trigger: {}", trigger.describe()),
+            None => "Trigger: none".to_string(),
+        }),
         Line::from(""),
         Line::from(vec![
             Span::styled("[s]", Style::default().fg(Color::Cyan).bold()),
@@ -1410,6 +2793,38 @@ fn draw_config(f: &mut Frame, app: &App, area: Rect) {
     );
     f.render_widget(config_widget, chunks[0]);
 
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(chunks[1]);
+
+    // Profiles
+    let items: Vec<ListItem> = app
+        .profiles
+        .iter()
+        .map(|p| {
+            let marker = match (p.active, p.modified) {
+                (true, true) => " (active, modified)",
+                (true, false) => " (active)",
+                _ => "",
+            };
+            ListItem::new(format!(
+                "{}{marker}: {} fan(s)",
+                p.name,
+                p.assignments.len()
+            ))
+        })
+        .collect();
+    let profile_list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" Profiles "))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("▶ ");
+    f.render_stateful_widget(profile_list, bottom[0], &mut app.profile_list_state.clone());
+
     // Current assignments
     let assignment_rows: Vec<Row> = app
         .assignments
@@ -1423,7 +2838,16 @@ fn draw_config(f: &mut Frame, app: &App, area: Rect) {
                 FanAssignment::Curve {
                     curve_name,
                     temp_sensor_id,
+                    ..
                 } => format!("Curve: {curve_name} → {temp_sensor_id}"),
+                FanAssignment::MultiCurve { inputs, combiner } => {
+                    format!("Multi-curve: {combiner:?} of {} inputs", inputs.len())
+                }
+                FanAssignment::Pid {
+                    temp_sensor_id,
+                    setpoint_c,
+                    ..
+                } => format!("PID: {temp_sensor_id} at {setpoint_c:.1}°C"),
             };
             Row::new(vec![
                 Cell::from(a.fan_id.clone()),
@@ -1446,7 +2870,63 @@ fn draw_config(f: &mut Frame, app: &App, area: Rect) {
             .title(" Current Fan Assignments "),
     );
 
-    f.render_widget(assignment_table, chunks[1]);
+    f.render_widget(assignment_table, bottom[1]);
+}
+
+/// Utility: describe a smoothing setting.
+fn describe_smoothing(smoothing: Option<&Smoothing>) -> String {
+    smoothing
+        .map(Smoothing::describe)
+        .unwrap_or_else(|| "raw".to_string())
+}
+
+/// Utility: format a curve x-axis value compactly, e.g. "45°C" or "1.5".
+fn format_axis(unit: InputUnit, value: f64) -> String {
+    match unit {
+        InputUnit::Load | InputUnit::Other => format!("{value:.1}"),
+        _ => format!("{value:.0}{}", unit.symbol()),
+    }
+}
+
+/// Utility: point editor step and range (step, min, max) for a unit.
+fn axis_steps(unit: InputUnit) -> (f64, f64, f64) {
+    match unit {
+        InputUnit::Celsius => (1.0, 0.0, 120.0),
+        InputUnit::Percent => (1.0, 0.0, 100.0),
+        InputUnit::Load => (0.5, 0.0, 256.0),
+        InputUnit::Rpm => (100.0, 0.0, 10_000.0),
+        // Virtual differences can go negative
+        InputUnit::Other => (1.0, -1000.0, 1000.0),
+    }
+}
+
+/// Utility: format a ramp rate in PWM units per second (0 = unlimited).
+fn format_rate(rate: f64) -> String {
+    if rate > 0.0 {
+        format!("{rate:.0}/s")
+    } else {
+        "∞".to_string()
+    }
+}
+
+/// Dim the id of an entry ignore rules hide, when it is shown anyway.
+fn hidden_style(hidden: bool) -> Style {
+    if hidden {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default()
+    }
+}
+
+/// " (2 hidden)" for a table title, or nothing if none are hidden or
+/// hidden entries are shown.
+fn hidden_note<T>(all: &[T], hidden: impl Fn(&T) -> bool, shown: bool) -> String {
+    let count = all.iter().filter(|x| hidden(x)).count();
+    if shown || count == 0 {
+        String::new()
+    } else {
+        format!(" ({count} hidden)")
+    }
 }
 
 /// Utility: create a centered rect.
@@ -0,0 +1,313 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Fan calibration.
+//!
+//! Many fans don't start below PWM ~60 and stop below ~40, so the bottom of
+//! a curve often does nothing. A [`Calibrator`] steps a fan down from full
+//! speed while reading its tachometer to find where it stops, then back up
+//! from standstill to find where it starts. The daemon drives it one tick
+//! at a time so the control loop never blocks.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Default time to let the fan settle at each PWM step.
+pub const DEFAULT_SETTLE_MS: u64 = 3000;
+
+/// PWM decrement while looking for the stop threshold.
+const STEP_DOWN: u8 = 8;
+
+/// PWM increment while looking for the start threshold.
+const STEP_UP: u8 = 4;
+
+/// Consecutive unreadable tachometer samples before giving up.
+const MAX_MISSED_READINGS: u32 = 10;
+
+/// Settle times to wait at PWM 0 for the fan to stop before giving up.
+const STOP_TIMEOUT_SETTLES: u32 = 10;
+
+/// One measured point of a fan's PWM→RPM response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub pwm: u8,
+    pub rpm: u32,
+}
+
+/// Measured characteristics of a fan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FanCalibration {
+    /// Lowest PWM that starts the fan from standstill
+    pub start_pwm: u8,
+    /// Lowest PWM that keeps an already spinning fan turning
+    pub stop_pwm: u8,
+    /// Speed at PWM 255
+    pub max_rpm: u32,
+    /// Measured PWM→RPM response, ascending by PWM
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response: Vec<CalibrationPoint>,
+}
+
+impl FanCalibration {
+    /// Map a PWM on the full 0-255 scale into the fan's usable range.
+    /// 0 still means off; anything else lands in `start_pwm..=255` so the
+    /// fan is guaranteed to turn.
+    pub fn scale(&self, pwm: u8) -> u8 {
+        if pwm == 0 {
+            return 0;
+        }
+        let lo = self.start_pwm as f64;
+        (lo + (255.0 - lo) * pwm as f64 / 255.0).round() as u8
+    }
+
+    /// Short human-readable summary, e.g. "start 64, stop 40, max 1800 RPM".
+    pub fn describe(&self) -> String {
+        format!(
+            "start {}, stop {}, max {} RPM",
+            self.start_pwm, self.stop_pwm, self.max_rpm
+        )
+    }
+}
+
+/// Result of feeding a reading to a [`Calibrator`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalibrationProgress {
+    /// Keep holding [`Calibrator::pwm`].
+    Running,
+    /// The fan has been measured.
+    Done(FanCalibration),
+    /// The fan couldn't be measured.
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Full speed, to measure max RPM
+    SpinUp,
+    /// Stepping down until the fan stops
+    StepDown,
+    /// Held at 0 so the fan comes to a standstill
+    Stopping,
+    /// Stepping up from standstill until the fan starts
+    StepUp,
+}
+
+/// Running calibration of one fan.
+#[derive(Debug, Clone)]
+pub struct Calibrator {
+    phase: Phase,
+    pwm: u8,
+    step_started: Instant,
+    settle: Duration,
+    response: Vec<CalibrationPoint>,
+    stop_pwm: u8,
+    missed: u32,
+}
+
+impl Calibrator {
+    /// Start a calibration that waits `settle_ms` at each step. The fan
+    /// should be put at [`Calibrator::pwm`] (full speed) straight away.
+    pub fn new(settle_ms: u64, now: Instant) -> Self {
+        Self {
+            phase: Phase::SpinUp,
+            pwm: 255,
+            step_started: now,
+            settle: Duration::from_millis(settle_ms),
+            response: Vec::new(),
+            stop_pwm: 0,
+            missed: 0,
+        }
+    }
+
+    /// The PWM the fan should be held at.
+    pub fn pwm(&self) -> u8 {
+        self.pwm
+    }
+
+    /// Short description of the current step, for status display.
+    pub fn describe(&self) -> String {
+        match self.phase {
+            Phase::SpinUp => "measuring full speed".to_string(),
+            Phase::StepDown => format!("finding stop PWM ({})", self.pwm),
+            Phase::Stopping => "waiting for standstill".to_string(),
+            Phase::StepUp => format!("finding start PWM ({})", self.pwm),
+        }
+    }
+
+    /// Feed the current tachometer reading. Readings taken before the fan
+    /// has settled at the current step are ignored.
+    pub fn update(&mut self, rpm: Option<u32>, now: Instant) -> CalibrationProgress {
+        if now.saturating_duration_since(self.step_started) < self.settle {
+            return CalibrationProgress::Running;
+        }
+        let Some(rpm) = rpm else {
+            // Tolerate the odd failed read, but not a dead tachometer
+            self.missed += 1;
+            if self.missed >= MAX_MISSED_READINGS {
+                return CalibrationProgress::Failed("tachometer stopped responding".to_string());
+            }
+            return CalibrationProgress::Running;
+        };
+        self.missed = 0;
+
+        match self.phase {
+            Phase::SpinUp => {
+                if rpm == 0 {
+                    return CalibrationProgress::Failed(
+                        "fan does not spin at full PWM".to_string(),
+                    );
+                }
+                self.response.push(CalibrationPoint { pwm: 255, rpm });
+                self.phase = Phase::StepDown;
+                self.step(255 - STEP_DOWN, now);
+            }
+            Phase::StepDown if rpm == 0 => {
+                // The last step that still turned
+                self.stop_pwm = self.response.last().map_or(255, |p| p.pwm);
+                self.phase = Phase::Stopping;
+                self.step(0, now);
+            }
+            Phase::StepDown => {
+                self.response.push(CalibrationPoint { pwm: self.pwm, rpm });
+                if self.pwm == 0 {
+                    // Never stops
+                    return CalibrationProgress::Done(self.finish(0));
+                }
+                self.step(self.pwm.saturating_sub(STEP_DOWN), now);
+            }
+            Phase::Stopping if rpm > 0 => {
+                // Still coasting, or it never stops at all
+                if now.saturating_duration_since(self.step_started)
+                    >= self.settle * STOP_TIMEOUT_SETTLES
+                {
+                    return CalibrationProgress::Failed("fan did not stop".to_string());
+                }
+            }
+            Phase::Stopping => {
+                self.phase = Phase::StepUp;
+                self.step(self.stop_pwm, now);
+            }
+            Phase::StepUp if rpm > 0 => {
+                return CalibrationProgress::Done(self.finish(self.pwm));
+            }
+            Phase::StepUp => {
+                if self.pwm == 255 {
+                    return CalibrationProgress::Failed("fan did not restart".to_string());
+                }
+                self.step(self.pwm.saturating_add(STEP_UP), now);
+            }
+        }
+        CalibrationProgress::Running
+    }
+
+    fn step(&mut self, pwm: u8, now: Instant) {
+        self.pwm = pwm;
+        self.step_started = now;
+    }
+
+    fn finish(&mut self, start_pwm: u8) -> FanCalibration {
+        let mut response = std::mem::take(&mut self.response);
+        response.sort_by_key(|p| p.pwm);
+        FanCalibration {
+            start_pwm,
+            stop_pwm: self.stop_pwm,
+            max_rpm: response.last().map_or(0, |p| p.rpm),
+            response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fan that keeps turning down to PWM 40 but needs 60 to start.
+    struct SimFan {
+        spinning: bool,
+    }
+
+    impl SimFan {
+        fn rpm(&mut self, pwm: u8) -> u32 {
+            self.spinning = if self.spinning { pwm >= 40 } else { pwm >= 60 };
+            if self.spinning { pwm as u32 * 7 } else { 0 }
+        }
+    }
+
+    #[test]
+    fn test_finds_thresholds() {
+        let mut fan = SimFan { spinning: true };
+        let start = Instant::now();
+        let mut cal = Calibrator::new(1000, start);
+
+        let mut result = None;
+        for tick in 1..500 {
+            let rpm = fan.rpm(cal.pwm());
+            match cal.update(Some(rpm), start + Duration::from_millis(tick * 500)) {
+                CalibrationProgress::Running => {}
+                other => {
+                    result = Some(other);
+                    break;
+                }
+            }
+        }
+
+        let Some(CalibrationProgress::Done(c)) = result else {
+            panic!("calibration did not finish: {result:?}");
+        };
+        assert_eq!(c.stop_pwm, 47);
+        assert_eq!(c.start_pwm, 63);
+        assert_eq!(c.max_rpm, 255 * 7);
+        assert_eq!(c.response.first().map(|p| p.pwm), Some(47));
+        assert_eq!(c.scale(0), 0);
+        assert_eq!(c.scale(255), 255);
+        assert!(c.scale(1) >= 63);
+    }
+
+    #[test]
+    fn test_waits_for_coasting_fan_to_stop() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut cal = Calibrator::new(1000, start);
+        cal.update(Some(1800), at(1000));
+        cal.update(Some(0), at(2000));
+        assert_eq!(cal.pwm(), 0);
+
+        // Settled, but still spinning down
+        assert_eq!(
+            cal.update(Some(300), at(3000)),
+            CalibrationProgress::Running
+        );
+        assert_eq!(cal.pwm(), 0);
+        assert_eq!(cal.update(Some(0), at(3500)), CalibrationProgress::Running);
+        assert_eq!(cal.pwm(), cal.stop_pwm);
+    }
+
+    #[test]
+    fn test_fan_that_never_stops_fails() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut cal = Calibrator::new(1000, start);
+        cal.update(Some(1800), at(1000));
+        cal.update(Some(0), at(2000));
+
+        assert_eq!(
+            cal.update(Some(300), at(3000)),
+            CalibrationProgress::Running
+        );
+        assert_eq!(
+            cal.update(Some(300), at(12000)),
+            CalibrationProgress::Failed("fan did not stop".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dead_fan_fails() {
+        let start = Instant::now();
+        let mut cal = Calibrator::new(1000, start);
+        assert_eq!(cal.update(Some(0), start), CalibrationProgress::Running);
+        assert!(matches!(
+            cal.update(Some(0), start + Duration::from_secs(1)),
+            CalibrationProgress::Failed(_)
+        ));
+    }
+}
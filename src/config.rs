@@ -6,9 +6,22 @@
 //! Persists fan assignments and curve definitions to TOML.
 //! Default path: `/etc/fanctl/config.toml`
 
-use crate::curve::{self, FanCurve};
+use crate::calibration::FanCalibration;
+use crate::curve::{self, FanCurve, InputUnit};
+use crate::failsafe::FailsafeConfig;
+use crate::history::HistoryConfig;
+use crate::hwmon::{self, Fan, TempSensor, TempStatus};
+use crate::ignore::IgnoreRule;
+use crate::labels::LmSensorsConfig;
+use crate::metrics::MetricsConfig;
+use crate::pid::PidParams;
+use crate::schedule::ScheduleConfig;
+use crate::smoothing::Smoothing;
+use crate::stall::StallConfig;
+use crate::sysload;
+use crate::trigger::TriggerConfig;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -19,9 +32,15 @@ pub const DEFAULT_CONFIG_PATH: &str = "/etc/fanctl/config.toml";
 /// Default daemon socket path.
 pub const DEFAULT_SOCKET_PATH: &str = "/run/fanctl.sock";
 
+/// Default daemon socket permissions (see [`DaemonConfig::socket_mode`]).
+pub const DEFAULT_SOCKET_MODE: &str = "0666";
+
 /// Default poll interval in milliseconds.
 pub const DEFAULT_POLL_INTERVAL_MS: u64 = 2000;
 
+/// Id prefix for virtual sensors, e.g. "virtual/hottest".
+pub const VIRTUAL_SENSOR_PREFIX: &str = "virtual/";
+
 // ---------------------------------------------------------------------------
 // Config types
 // ---------------------------------------------------------------------------
@@ -37,7 +56,68 @@ pub struct Config {
     #[serde(default)]
     pub curves: Vec<FanCurve>,
 
-    /// Per-fan assignments, keyed by fan id (e.g. "hwmon3/pwm1").
+    /// Composite sensors computed from other sensors each tick.
+    #[serde(default)]
+    pub virtual_sensors: Vec<VirtualSensor>,
+
+    /// Tachometer-based stall detection.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub stall: StallConfig,
+
+    /// Over-temperature and lost-sensor protection.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub failsafe: FailsafeConfig,
+
+    /// Measured start/stop thresholds, keyed by fan id.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub calibrations: HashMap<String, FanCalibration>,
+
+    /// Time-of-day profile switching.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub schedule: ScheduleConfig,
+
+    /// Profile switching on process presence and system load.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub triggers: TriggerConfig,
+
+    /// Recent readings kept for charts.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub history: HistoryConfig,
+
+    /// Label and ignore statements imported from lm-sensors.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub lm_sensors: LmSensorsConfig,
+
+    /// Friendly names keyed by fan or sensor id, e.g. "CPU" or "Rear
+    /// exhaust". These replace kernel and lm-sensors labels.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+
+    /// Rules hiding bogus sensors and unusable fans.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<IgnoreRule>,
+
+    /// Profile the current assignments were last switched to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+
+    /// Per-fan assignments, keyed by stable fan id
+    /// (e.g. "nct6775@platform/nct6775.656/pwm1"). Legacy "hwmon3/pwm1"
+    /// keys are migrated on load by [`Config::migrate_legacy_ids`].
+    #[serde(default)]
+    pub fans: HashMap<String, FanAssignment>,
+
+    /// Named sets of fan assignments that replace `fans` wholesale when
+    /// switched to.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// A named, complete set of fan assignments (e.g. "quiet", "render").
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Assignments keyed by stable fan id. Fans left out are handed back
+    /// to BIOS control when the profile is switched to.
     #[serde(default)]
     pub fans: HashMap<String, FanAssignment>,
 }
@@ -56,6 +136,40 @@ pub struct DaemonConfig {
     /// Whether to restore fans to automatic on daemon exit.
     #[serde(default = "default_true")]
     pub restore_on_exit: bool,
+
+    /// Sysfs mount point to discover hwmon devices under. Point this at a
+    /// fake tree to run the daemon without real hardware.
+    #[serde(default = "default_sysfs_root")]
+    pub sysfs_root: String,
+
+    /// Procfs mount point that load and running processes are read from
+    /// for triggers.
+    #[serde(default = "default_procfs_root")]
+    pub procfs_root: String,
+
+    /// Group to give the socket, by name or gid. Unset leaves it with the
+    /// daemon's group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket_group: Option<String>,
+
+    /// Socket permission bits in octal. Connecting needs write permission,
+    /// so the default lets every local user read status; changing fan
+    /// control is gated separately by `control_users` and `control_groups`.
+    #[serde(default = "default_socket_mode")]
+    pub socket_mode: String,
+
+    /// Users, by name or uid, allowed to change fan control. Root always
+    /// may.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub control_users: Vec<String>,
+
+    /// Groups, by name or gid, whose members may change fan control.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub control_groups: Vec<String>,
+
+    /// Prometheus exporter (`[daemon.metrics]`). Unset serves no metrics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsConfig>,
 }
 
 impl Default for DaemonConfig {
@@ -64,12 +178,19 @@ impl Default for DaemonConfig {
             poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
             socket_path: DEFAULT_SOCKET_PATH.to_string(),
             restore_on_exit: true,
+            sysfs_root: hwmon::DEFAULT_SYSFS_ROOT.to_string(),
+            procfs_root: sysload::DEFAULT_PROCFS_ROOT.to_string(),
+            socket_group: None,
+            socket_mode: DEFAULT_SOCKET_MODE.to_string(),
+            control_users: Vec::new(),
+            control_groups: Vec::new(),
+            metrics: None,
         }
     }
 }
 
 /// How a fan should be controlled.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "mode")]
 pub enum FanAssignment {
     /// Automatic (BIOS) control -- daemon doesn't touch this fan.
@@ -88,9 +209,287 @@ pub enum FanAssignment {
     Curve {
         /// Name of the curve (must match a curve in `Config::curves`)
         curve_name: String,
-        /// Id of the temp sensor to read (e.g. "hwmon3/temp1")
+        /// Id of the temp sensor to read (e.g. "k10temp@pci0000:00/0000:00:18.3/temp1")
         temp_sensor_id: String,
+        /// Optional filter applied to the reading before curve lookup
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        smoothing: Option<Smoothing>,
     },
+
+    /// Several (curve, sensor) pairs evaluated independently, with the
+    /// results merged by a combiner (highest demand by default).
+    #[serde(rename = "multi_curve")]
+    MultiCurve {
+        /// The curve inputs to evaluate
+        inputs: Vec<CurveInput>,
+        /// How the per-input PWM demands are merged
+        #[serde(default)]
+        combiner: Combiner,
+    },
+
+    /// Closed-loop control holding a sensor at a temperature setpoint.
+    #[serde(rename = "pid")]
+    Pid {
+        /// Id of the temp sensor to regulate
+        temp_sensor_id: String,
+        /// Target temperature in degrees Celsius
+        setpoint_c: f64,
+        /// Proportional gain (PWM units per °C)
+        kp: f64,
+        /// Integral gain (PWM units per °C·s)
+        ki: f64,
+        /// Derivative gain (PWM units per °C/s)
+        kd: f64,
+        /// Lowest PWM the controller will output
+        min_pwm: u8,
+        /// Highest PWM the controller will output
+        max_pwm: u8,
+    },
+}
+
+/// One (curve, sensor) pair of a [`FanAssignment::MultiCurve`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CurveInput {
+    /// Name of the curve (must match a curve in `Config::curves`)
+    pub curve_name: String,
+    /// Id of the temp sensor to read
+    pub temp_sensor_id: String,
+    /// Optional filter applied to the reading before curve lookup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothing: Option<Smoothing>,
+}
+
+/// A borrowed [`CurveInput`], as [`FanAssignment::curve_inputs`] gives
+/// them for single- and multi-curve assignments alike.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurveInputRef<'a> {
+    pub curve_name: &'a str,
+    pub temp_sensor_id: &'a str,
+    pub smoothing: Option<Smoothing>,
+}
+
+impl<'a> From<&'a CurveInput> for CurveInputRef<'a> {
+    fn from(input: &'a CurveInput) -> Self {
+        Self {
+            curve_name: &input.curve_name,
+            temp_sensor_id: &input.temp_sensor_id,
+            smoothing: input.smoothing,
+        }
+    }
+}
+
+/// How multiple PWM demands on one fan are merged.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Combiner {
+    /// Highest demand wins
+    #[default]
+    Max,
+    /// Mean of all demands
+    Average,
+}
+
+impl Combiner {
+    /// Merge PWM demands. Returns `None` if there are none.
+    pub fn combine(self, demands: &[u8]) -> Option<u8> {
+        match self {
+            Combiner::Max => demands.iter().copied().max(),
+            Combiner::Average if demands.is_empty() => None,
+            Combiner::Average => {
+                let sum: u32 = demands.iter().map(|&d| d as u32).sum();
+                Some((sum as f64 / demands.len() as f64).round() as u8)
+            }
+        }
+    }
+}
+
+impl FanAssignment {
+    /// Short name of the control mode, as written in the config file.
+    pub fn mode(&self) -> &'static str {
+        match self {
+            FanAssignment::Auto => "auto",
+            FanAssignment::Manual { .. } => "manual",
+            FanAssignment::Curve { .. } => "curve",
+            FanAssignment::MultiCurve { .. } => "multi_curve",
+            FanAssignment::Pid { .. } => "pid",
+        }
+    }
+
+    /// The curve inputs this assignment evaluates, and how to merge them.
+    /// Returns `None` for assignments the curve engine doesn't drive.
+    pub fn curve_inputs(&self) -> Option<(Vec<CurveInputRef<'_>>, Combiner)> {
+        match self {
+            FanAssignment::Curve {
+                curve_name,
+                temp_sensor_id,
+                smoothing,
+            } => Some((
+                vec![CurveInputRef {
+                    curve_name,
+                    temp_sensor_id,
+                    smoothing: *smoothing,
+                }],
+                Combiner::Max,
+            )),
+            FanAssignment::MultiCurve { inputs, combiner } => {
+                Some((inputs.iter().map(CurveInputRef::from).collect(), *combiner))
+            }
+            _ => None,
+        }
+    }
+
+    /// The sensor and tuning of a PID assignment.
+    pub fn pid_params(&self) -> Option<(&str, PidParams)> {
+        match self {
+            FanAssignment::Pid {
+                temp_sensor_id,
+                setpoint_c,
+                kp,
+                ki,
+                kd,
+                min_pwm,
+                max_pwm,
+            } => Some((
+                temp_sensor_id,
+                PidParams {
+                    setpoint_c: *setpoint_c,
+                    kp: *kp,
+                    ki: *ki,
+                    kd: *kd,
+                    min_pwm: *min_pwm,
+                    max_pwm: *max_pwm,
+                },
+            )),
+            _ => None,
+        }
+    }
+
+    /// Ids of every sensor this assignment reads.
+    pub fn sensor_ids(&self) -> Vec<&str> {
+        match self {
+            FanAssignment::Auto | FanAssignment::Manual { .. } => Vec::new(),
+            FanAssignment::Curve { temp_sensor_id, .. }
+            | FanAssignment::Pid { temp_sensor_id, .. } => vec![temp_sensor_id],
+            FanAssignment::MultiCurve { inputs, .. } => {
+                inputs.iter().map(|i| i.temp_sensor_id.as_str()).collect()
+            }
+        }
+    }
+
+    /// Build a PID assignment from a sensor id and tuning.
+    pub fn pid(temp_sensor_id: String, params: PidParams) -> Self {
+        FanAssignment::Pid {
+            temp_sensor_id,
+            setpoint_c: params.setpoint_c,
+            kp: params.kp,
+            ki: params.ki,
+            kd: params.kd,
+            min_pwm: params.min_pwm,
+            max_pwm: params.max_pwm,
+        }
+    }
+}
+
+/// A named composite of other temperature sensors.
+///
+/// Referenced by curve assignments as `virtual/<name>`, exactly like a
+/// physical sensor id. Inputs may be physical sensors or virtual sensors
+/// defined earlier in the list.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VirtualSensor {
+    /// Unique name for this sensor
+    pub name: String,
+    /// How the inputs are combined
+    pub kind: VirtualSensorKind,
+    /// Ids of the sensors to combine
+    pub inputs: Vec<String>,
+    /// Per-input weights, only used by `weighted`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weights: Vec<f64>,
+    /// Unit of the result; defaults to the unit of the first input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<InputUnit>,
+}
+
+/// Combiner for a [`VirtualSensor`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VirtualSensorKind {
+    /// Hottest input
+    Max,
+    /// Coolest input
+    Min,
+    /// Mean of the inputs
+    Average,
+    /// Weighted mean of the inputs
+    Weighted,
+    /// First input minus second input
+    Difference,
+}
+
+impl VirtualSensor {
+    /// The sensor id curves use to reference this sensor.
+    pub fn id(&self) -> String {
+        format!("{VIRTUAL_SENSOR_PREFIX}{}", self.name)
+    }
+
+    /// Check that the inputs match what the combiner needs.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.inputs.is_empty() {
+            return Err(format!("Virtual sensor '{}' has no inputs", self.name));
+        }
+        match self.kind {
+            VirtualSensorKind::Difference if self.inputs.len() != 2 => Err(format!(
+                "Virtual sensor '{}': difference needs exactly 2 inputs",
+                self.name
+            )),
+            VirtualSensorKind::Weighted if self.weights.len() != self.inputs.len() => Err(format!(
+                "Virtual sensor '{}': weighted needs one weight per input",
+                self.name
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Compute the sensor value from current readings. Inputs without a
+    /// reading are skipped (except for `difference`, which needs both).
+    pub fn evaluate(&self, readings: &HashMap<String, f64>) -> Option<f64> {
+        if self.validate().is_err() {
+            return None;
+        }
+        let values: Vec<(f64, f64)> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| {
+                let weight = self.weights.get(i).copied().unwrap_or(1.0);
+                readings.get(id).map(|&v| (v, weight))
+            })
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+
+        match self.kind {
+            VirtualSensorKind::Max => values.iter().map(|&(v, _)| v).reduce(f64::max),
+            VirtualSensorKind::Min => values.iter().map(|&(v, _)| v).reduce(f64::min),
+            VirtualSensorKind::Average => {
+                Some(values.iter().map(|&(v, _)| v).sum::<f64>() / values.len() as f64)
+            }
+            VirtualSensorKind::Weighted => {
+                let total: f64 = values.iter().map(|&(_, w)| w).sum();
+                if total <= 0.0 {
+                    return None;
+                }
+                Some(values.iter().map(|&(v, w)| v * w).sum::<f64>() / total)
+            }
+            VirtualSensorKind::Difference => {
+                let a = readings.get(&self.inputs[0])?;
+                let b = readings.get(&self.inputs[1])?;
+                Some(a - b)
+            }
+        }
+    }
 }
 
 impl Default for Config {
@@ -101,11 +500,320 @@ impl Default for Config {
                 curve::default_silent_curve(),
                 curve::default_performance_curve(),
             ],
+            virtual_sensors: Vec::new(),
+            stall: StallConfig::default(),
+            failsafe: FailsafeConfig::default(),
+            calibrations: HashMap::new(),
+            schedule: ScheduleConfig::default(),
+            triggers: TriggerConfig::default(),
+            history: HistoryConfig::default(),
+            lm_sensors: LmSensorsConfig::default(),
+            labels: HashMap::new(),
+            ignore: Vec::new(),
+            active_profile: None,
             fans: HashMap::new(),
+            profiles: HashMap::new(),
         }
     }
 }
 
+impl Config {
+    /// Check that every curve an assignment names exists.
+    pub fn check_assignment(&self, assignment: &FanAssignment) -> Result<(), String> {
+        let names: Vec<&str> = match assignment {
+            FanAssignment::Curve { curve_name, .. } => vec![curve_name],
+            FanAssignment::MultiCurve { inputs, .. } => {
+                inputs.iter().map(|i| i.curve_name.as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+        match names
+            .into_iter()
+            .find(|n| !self.curves.iter().any(|c| c.name == *n))
+        {
+            Some(missing) => Err(format!("Unknown curve: {missing}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that every curve an assignment names is for the unit its
+    /// sensor reads, and that a PID setpoint is compared with a
+    /// temperature. `base_unit` gives the unit of non-virtual sensors;
+    /// sensors whose unit isn't known are let through.
+    pub fn check_units(
+        &self,
+        assignment: &FanAssignment,
+        base_unit: &dyn Fn(&str) -> Option<InputUnit>,
+    ) -> Result<(), String> {
+        if let Some((sensor_id, _)) = assignment.pid_params() {
+            return match self.sensor_unit(sensor_id, base_unit) {
+                Some(unit) if !unit.is_celsius() => Err(format!(
+                    "PID setpoints are in celsius but {sensor_id} reads {unit}"
+                )),
+                _ => Ok(()),
+            };
+        }
+        let Some((inputs, _)) = assignment.curve_inputs() else {
+            return Ok(());
+        };
+        for input in inputs {
+            if let Some(curve) = self.curves.iter().find(|c| c.name == input.curve_name) {
+                self.check_curve_unit(curve, input.temp_sensor_id, base_unit)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `curve` may be fed from sensor `sensor_id`.
+    pub fn check_curve_unit(
+        &self,
+        curve: &FanCurve,
+        sensor_id: &str,
+        base_unit: &dyn Fn(&str) -> Option<InputUnit>,
+    ) -> Result<(), String> {
+        match self.sensor_unit(sensor_id, base_unit) {
+            Some(unit) if unit != curve.unit => Err(format!(
+                "Curve '{}' is for {} but {sensor_id} reads {unit}",
+                curve.name, curve.unit
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Unit of sensor `id`. Virtual sensors without an explicit unit take
+    /// the unit of their first input.
+    pub fn sensor_unit(
+        &self,
+        id: &str,
+        base_unit: &dyn Fn(&str) -> Option<InputUnit>,
+    ) -> Option<InputUnit> {
+        let mut id = id;
+        // Virtual inputs only reference earlier entries, but don't trust a
+        // hand-edited file not to loop
+        for _ in 0..=self.virtual_sensors.len() {
+            let Some(v) = self.virtual_sensors.iter().find(|v| v.id() == id) else {
+                return base_unit(id);
+            };
+            if v.unit.is_some() {
+                return v.unit;
+            }
+            id = v.inputs.first()?;
+        }
+        None
+    }
+
+    /// Check the virtual sensors: each must suit its combiner, have an id
+    /// no other sensor uses and combine inputs of a single unit.
+    /// `base_unit` gives the unit of non-virtual sensors, so any id it
+    /// knows is taken.
+    pub fn validate(&self, base_unit: &dyn Fn(&str) -> Option<InputUnit>) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for sensor in &self.virtual_sensors {
+            sensor.validate()?;
+            if !names.insert(sensor.name.as_str()) {
+                return Err(format!(
+                    "Virtual sensor '{}' is defined more than once",
+                    sensor.name
+                ));
+            }
+            let id = sensor.id();
+            if base_unit(&id).is_some() {
+                return Err(format!(
+                    "Virtual sensor '{}' clashes with discovered sensor {id}",
+                    sensor.name
+                ));
+            }
+            // Inputs of unknown unit are left to the missing-reading checks
+            let units: Vec<(&str, InputUnit)> = sensor
+                .inputs
+                .iter()
+                .filter_map(|id| Some((id.as_str(), self.sensor_unit(id, base_unit)?)))
+                .collect();
+            if let Some((id, unit)) = units.iter().find(|(_, u)| *u != units[0].1) {
+                let (first_id, first_unit) = units[0];
+                return Err(format!(
+                    "Virtual sensor '{}' mixes {first_unit} ({first_id}) and {unit} ({id}) inputs",
+                    sensor.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the virtual sensors to `readings`, which holds this tick's
+    /// physical and other readings. Sensors are evaluated in order, so each
+    /// may build on the ones defined before it.
+    pub fn evaluate_virtual_sensors(&self, readings: &mut HashMap<String, f64>) {
+        for sensor in &self.virtual_sensors {
+            if let Some(value) = sensor.evaluate(readings) {
+                readings.insert(sensor.id(), value);
+            }
+        }
+    }
+
+    /// Statuses for the virtual sensors from readings already passed
+    /// through [`Config::evaluate_virtual_sensors`]. `base_unit` is as for
+    /// [`Config::sensor_unit`].
+    pub fn virtual_sensor_statuses(
+        &self,
+        readings: &HashMap<String, f64>,
+        base_unit: &dyn Fn(&str) -> Option<InputUnit>,
+    ) -> Vec<TempStatus> {
+        self.virtual_sensors
+            .iter()
+            .map(|v| {
+                let id = v.id();
+                TempStatus {
+                    temp_c: readings.get(&id).copied(),
+                    unit: self.sensor_unit(&id, base_unit).unwrap_or_default(),
+                    legacy_id: id.clone(),
+                    id,
+                    label: Some(v.name.clone()),
+                    hwmon_name: "virtual".to_string(),
+                    filtered_c: None,
+                    crit_c: None,
+                    max_c: None,
+                    hidden: false,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the live assignments still match the active profile.
+    pub fn profile_modified(&self) -> bool {
+        self.active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get(name))
+            .is_some_and(|p| p.fans != self.fans)
+    }
+
+    /// Whether reading sensor `id` involves sensor `target`, either
+    /// directly or as an input of a (possibly nested) virtual sensor.
+    pub fn sensor_depends_on(&self, id: &str, target: &str) -> bool {
+        let mut pending = vec![id];
+        // Virtual inputs only reference earlier entries, but don't trust a
+        // hand-edited file not to loop
+        let mut expanded = HashSet::new();
+        while let Some(id) = pending.pop() {
+            if id == target {
+                return true;
+            }
+            if !expanded.insert(id) {
+                continue;
+            }
+            if let Some(v) = self.virtual_sensors.iter().find(|v| v.id() == id) {
+                pending.extend(v.inputs.iter().map(String::as_str));
+            }
+        }
+        false
+    }
+
+    /// Rewrite legacy `hwmonN/...` fan and label keys and sensor references,
+    /// including those inside profiles, to the stable ids of the currently
+    /// discovered hardware.
+    ///
+    /// Legacy ids are resolved against the current boot's hwmon numbering,
+    /// which is the best guess available for configs written before stable
+    /// ids existed. Returns the number of ids that were rewritten.
+    pub fn migrate_legacy_ids(&mut self, fans: &[Fan], sensors: &[TempSensor]) -> usize {
+        let mut migrated = 0;
+
+        migrated += migrate_fan_keys(&mut self.fans, fans, "");
+        for (name, profile) in &mut self.profiles {
+            let context = format!(" in profile '{name}'");
+            migrated += migrate_fan_keys(&mut profile.fans, fans, &context);
+        }
+
+        let legacy_labels: Vec<String> = self
+            .labels
+            .keys()
+            .filter(|k| hwmon::is_legacy_id(k))
+            .cloned()
+            .collect();
+        for old_id in legacy_labels {
+            let new_id = match hwmon::find_fan(fans, &old_id) {
+                Some(fan) => fan.id.clone(),
+                None => match hwmon::find_temp_sensor(sensors, &old_id) {
+                    Some(sensor) => sensor.id.clone(),
+                    None => continue,
+                },
+            };
+            if self.labels.contains_key(&new_id) {
+                log::warn!("Ignoring label for legacy id {old_id}: {new_id} is already labelled");
+                continue;
+            }
+            if let Some(label) = self.labels.remove(&old_id) {
+                log::info!("Migrated label id {old_id} -> {new_id}");
+                self.labels.insert(new_id, label);
+                migrated += 1;
+            }
+        }
+
+        let mut sensor_refs: Vec<&mut String> = Vec::new();
+        let profile_assignments = self.profiles.values_mut().flat_map(|p| p.fans.values_mut());
+        for assignment in self.fans.values_mut().chain(profile_assignments) {
+            match assignment {
+                FanAssignment::Curve { temp_sensor_id, .. }
+                | FanAssignment::Pid { temp_sensor_id, .. } => sensor_refs.push(temp_sensor_id),
+                FanAssignment::MultiCurve { inputs, .. } => {
+                    sensor_refs.extend(inputs.iter_mut().map(|i| &mut i.temp_sensor_id))
+                }
+                _ => {}
+            }
+        }
+        sensor_refs.extend(
+            self.virtual_sensors
+                .iter_mut()
+                .flat_map(|v| v.inputs.iter_mut()),
+        );
+        for sensor_id in sensor_refs {
+            if !hwmon::is_legacy_id(sensor_id) {
+                continue;
+            }
+            if let Some(sensor) = hwmon::find_temp_sensor(sensors, sensor_id) {
+                log::info!("Migrated sensor id {sensor_id} -> {}", sensor.id);
+                *sensor_id = sensor.id.clone();
+                migrated += 1;
+            }
+        }
+
+        migrated
+    }
+}
+
+/// Rekey assignments stored under legacy fan ids. `context` is appended to
+/// log messages, e.g. " in profile 'quiet'".
+fn migrate_fan_keys(
+    assignments: &mut HashMap<String, FanAssignment>,
+    fans: &[Fan],
+    context: &str,
+) -> usize {
+    let mut migrated = 0;
+    let legacy_keys: Vec<String> = assignments
+        .keys()
+        .filter(|k| hwmon::is_legacy_id(k))
+        .cloned()
+        .collect();
+    for old_id in legacy_keys {
+        let Some(fan) = hwmon::find_fan(fans, &old_id) else {
+            continue;
+        };
+        if assignments.contains_key(&fan.id) {
+            log::warn!(
+                "Ignoring legacy fan id {old_id}{context}: {} is already configured",
+                fan.id
+            );
+            continue;
+        }
+        if let Some(assignment) = assignments.remove(&old_id) {
+            log::info!("Migrated fan id {old_id} -> {}{context}", fan.id);
+            assignments.insert(fan.id.clone(), assignment);
+            migrated += 1;
+        }
+    }
+    migrated
+}
+
 // ---------------------------------------------------------------------------
 // Load / Save
 // ---------------------------------------------------------------------------
@@ -166,6 +874,363 @@ fn default_socket_path() -> String {
     DEFAULT_SOCKET_PATH.to_string()
 }
 
+fn default_socket_mode() -> String {
+    DEFAULT_SOCKET_MODE.to_string()
+}
+
+fn default_sysfs_root() -> String {
+    hwmon::DEFAULT_SYSFS_ROOT.to_string()
+}
+
+fn default_procfs_root() -> String {
+    sysload::DEFAULT_PROCFS_ROOT.to_string()
+}
+
 fn default_true() -> bool {
     true
 }
+
+/// Leave sections the user never touched out of the written file.
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeSysfs;
+
+    #[test]
+    fn test_migrate_legacy_ids() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.link_device("platform/nct6775.656").unwrap();
+        chip.add_fan(1, 0, None).unwrap();
+        chip.add_temp(1, 40_000, None).unwrap();
+        let fans = hwmon::discover_fans(&sysfs.root()).unwrap();
+        let sensors = hwmon::discover_temp_sensors(&sysfs.root()).unwrap();
+
+        let mut config = Config::default();
+        config.fans.insert(
+            "hwmon0/pwm1".to_string(),
+            FanAssignment::Curve {
+                curve_name: "silent".to_string(),
+                temp_sensor_id: "hwmon0/temp1".to_string(),
+                smoothing: None,
+            },
+        );
+
+        assert_eq!(config.migrate_legacy_ids(&fans, &sensors), 2);
+        let Some(FanAssignment::Curve { temp_sensor_id, .. }) =
+            config.fans.get("nct6775@platform/nct6775.656/pwm1")
+        else {
+            panic!("fan was not migrated");
+        };
+        assert_eq!(temp_sensor_id, "nct6775@platform/nct6775.656/temp1");
+    }
+
+    #[test]
+    fn test_migrate_legacy_ids_in_profiles() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.link_device("platform/nct6775.656").unwrap();
+        chip.add_fan(1, 0, None).unwrap();
+        chip.add_fan(2, 0, None).unwrap();
+        chip.add_temp(1, 40_000, None).unwrap();
+        let fans = hwmon::discover_fans(&sysfs.root()).unwrap();
+        let sensors = hwmon::discover_temp_sensors(&sysfs.root()).unwrap();
+
+        let mut quiet = Profile::default();
+        quiet.fans.insert(
+            "hwmon0/pwm1".to_string(),
+            FanAssignment::MultiCurve {
+                inputs: vec![CurveInput {
+                    curve_name: "silent".to_string(),
+                    temp_sensor_id: "hwmon0/temp1".to_string(),
+                    smoothing: None,
+                }],
+                combiner: Combiner::Max,
+            },
+        );
+        quiet
+            .fans
+            .insert("hwmon0/pwm2".to_string(), FanAssignment::Manual { pwm: 90 });
+        let mut config = Config::default();
+        config.profiles.insert("quiet".to_string(), quiet);
+
+        assert_eq!(config.migrate_legacy_ids(&fans, &sensors), 3);
+        let quiet = &config.profiles["quiet"];
+        assert_eq!(
+            quiet.fans.get("nct6775@platform/nct6775.656/pwm2"),
+            Some(&FanAssignment::Manual { pwm: 90 })
+        );
+        let Some(FanAssignment::MultiCurve { inputs, .. }) =
+            quiet.fans.get("nct6775@platform/nct6775.656/pwm1")
+        else {
+            panic!("profile fan was not migrated");
+        };
+        assert_eq!(
+            inputs[0].temp_sensor_id,
+            "nct6775@platform/nct6775.656/temp1"
+        );
+    }
+
+    #[test]
+    fn test_smoothing_roundtrip() {
+        let mut config = Config::default();
+        config.fans.insert(
+            "k10temp/pwm1".to_string(),
+            FanAssignment::Curve {
+                curve_name: "silent".to_string(),
+                temp_sensor_id: "k10temp/temp1".to_string(),
+                smoothing: Some(Smoothing::Ema {
+                    time_constant_ms: 5000,
+                }),
+            },
+        );
+
+        let text = toml::to_string_pretty(&config).unwrap();
+        let parsed: Config = toml::from_str(&text).unwrap();
+        let Some(FanAssignment::Curve { smoothing, .. }) = parsed.fans.get("k10temp/pwm1") else {
+            panic!("assignment lost");
+        };
+        assert_eq!(
+            *smoothing,
+            Some(Smoothing::Ema {
+                time_constant_ms: 5000
+            })
+        );
+    }
+
+    #[test]
+    fn test_virtual_sensor_kinds() {
+        let readings: HashMap<String, f64> = [("cpu", 70.0), ("gpu", 50.0)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let sensor = |kind, weights: Vec<f64>| VirtualSensor {
+            name: "v".to_string(),
+            kind,
+            inputs: vec!["cpu".to_string(), "gpu".to_string(), "nvme".to_string()],
+            weights,
+            unit: None,
+        };
+
+        assert_eq!(
+            sensor(VirtualSensorKind::Max, vec![]).evaluate(&readings),
+            Some(70.0)
+        );
+        assert_eq!(
+            sensor(VirtualSensorKind::Average, vec![]).evaluate(&readings),
+            Some(60.0)
+        );
+        assert_eq!(
+            sensor(VirtualSensorKind::Weighted, vec![3.0, 1.0, 5.0]).evaluate(&readings),
+            Some(65.0)
+        );
+        // Difference needs exactly two inputs
+        assert_eq!(
+            sensor(VirtualSensorKind::Difference, vec![]).evaluate(&readings),
+            None
+        );
+    }
+
+    #[test]
+    fn test_validate_virtual_sensors() {
+        let config = |names: &[&str]| Config {
+            virtual_sensors: names
+                .iter()
+                .map(|name| VirtualSensor {
+                    name: name.to_string(),
+                    kind: VirtualSensorKind::Max,
+                    inputs: vec!["k10temp/temp1".to_string()],
+                    weights: vec![],
+                    unit: None,
+                })
+                .collect(),
+            ..Config::default()
+        };
+        // A chip called "virtual" without a device link
+        let base = |id: &str| id.ends_with("/temp1").then_some(InputUnit::Celsius);
+
+        assert!(config(&["hottest", "coolest"]).validate(&base).is_ok());
+        assert_eq!(
+            config(&["hottest", "hottest"]).validate(&base),
+            Err("Virtual sensor 'hottest' is defined more than once".to_string())
+        );
+        assert_eq!(
+            config(&["temp1"]).validate(&base),
+            Err("Virtual sensor 'temp1' clashes with discovered sensor virtual/temp1".to_string())
+        );
+
+        let mut mixed = config(&["busiest"]);
+        mixed.virtual_sensors[0]
+            .inputs
+            .extend(["load/cpu".to_string(), "missing/temp2".to_string()]);
+        let base = |id: &str| match id {
+            "load/cpu" => Some(InputUnit::Percent),
+            "k10temp/temp1" => Some(InputUnit::Celsius),
+            _ => None,
+        };
+        assert_eq!(
+            mixed.validate(&base),
+            Err(
+                "Virtual sensor 'busiest' mixes celsius (k10temp/temp1) and percent (load/cpu) inputs"
+                    .to_string()
+            )
+        );
+        mixed.virtual_sensors[0].inputs.remove(1);
+        assert!(mixed.validate(&base).is_ok());
+    }
+
+    #[test]
+    fn test_combiner() {
+        assert_eq!(Combiner::Max.combine(&[40, 200, 90]), Some(200));
+        assert_eq!(Combiner::Average.combine(&[40, 200, 90]), Some(110));
+        assert_eq!(Combiner::Max.combine(&[]), None);
+    }
+
+    #[test]
+    fn test_untouched_sections_not_saved() {
+        let toml = toml::to_string_pretty(&Config::default()).unwrap();
+        for section in [
+            "[stall]",
+            "[failsafe]",
+            "[schedule]",
+            "[triggers]",
+            "[history]",
+            "[lm_sensors]",
+        ] {
+            assert!(!toml.contains(section), "{section} in:\n{toml}");
+        }
+
+        let mut config = Config::default();
+        config.stall.enabled = !config.stall.enabled;
+        let toml = toml::to_string_pretty(&config).unwrap();
+        assert!(toml.contains("[stall]"));
+    }
+
+    #[test]
+    fn test_profiles_roundtrip() {
+        let mut config = Config::default();
+        let quiet = Profile {
+            fans: [(
+                "nct6775/pwm1".to_string(),
+                FanAssignment::Curve {
+                    curve_name: "silent".to_string(),
+                    temp_sensor_id: "k10temp/temp1".to_string(),
+                    smoothing: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        config.profiles.insert("quiet".to_string(), quiet.clone());
+        config.fans = quiet.fans.clone();
+        config.active_profile = Some("quiet".to_string());
+
+        let text = toml::to_string_pretty(&config).unwrap();
+        for section in ["[schedule]", "[triggers]", "[history]", "[lm_sensors]"] {
+            assert!(!text.contains(section), "empty {section} written");
+        }
+        let parsed: Config = toml::from_str(&text).unwrap();
+        assert_eq!(parsed.profiles.get("quiet"), Some(&quiet));
+        assert!(!parsed.profile_modified());
+        assert!(parsed.check_assignment(&quiet.fans["nct6775/pwm1"]).is_ok());
+
+        config.fans.insert(
+            "nct6775/pwm1".to_string(),
+            FanAssignment::Manual { pwm: 90 },
+        );
+        assert!(config.profile_modified());
+        let missing = FanAssignment::Curve {
+            curve_name: "gaming".to_string(),
+            temp_sensor_id: "k10temp/temp1".to_string(),
+            smoothing: None,
+        };
+        assert_eq!(
+            config.check_assignment(&missing),
+            Err("Unknown curve: gaming".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_units() {
+        let config: Config = toml::from_str(
+            r#"
+            [[curves]]
+            name = "cpu"
+            points = [{ temp_c = 40.0, pwm = 0 }, { temp_c = 80.0, pwm = 255 }]
+
+            [[curves]]
+            name = "busy"
+            unit = "percent"
+            points = [{ input = 20.0, pwm = 0 }, { input = 90.0, pwm = 255 }]
+
+            [[virtual_sensors]]
+            name = "hottest"
+            kind = "max"
+            inputs = ["k10temp/temp1", "nvme/temp1"]
+
+            [[virtual_sensors]]
+            name = "busiest"
+            kind = "max"
+            inputs = ["load/cpu", "load/cpu_max"]
+            unit = "percent"
+            "#,
+        )
+        .unwrap();
+        let base = |id: &str| match id {
+            "load/cpu" | "load/cpu_max" => Some(InputUnit::Percent),
+            _ if id.contains("/temp") => Some(InputUnit::Celsius),
+            _ => None,
+        };
+        let curve = |curve: &str, sensor: &str| FanAssignment::Curve {
+            curve_name: curve.to_string(),
+            temp_sensor_id: sensor.to_string(),
+            smoothing: None,
+        };
+
+        assert_eq!(
+            config.sensor_unit("virtual/hottest", &base),
+            Some(InputUnit::Celsius)
+        );
+        assert_eq!(
+            config.sensor_unit("virtual/busiest", &base),
+            Some(InputUnit::Percent)
+        );
+        assert!(
+            config
+                .check_units(&curve("cpu", "virtual/hottest"), &base)
+                .is_ok()
+        );
+        assert!(
+            config
+                .check_units(&curve("busy", "load/cpu"), &base)
+                .is_ok()
+        );
+        assert_eq!(
+            config.check_units(&curve("busy", "k10temp/temp1"), &base),
+            Err("Curve 'busy' is for percent but k10temp/temp1 reads celsius".to_string())
+        );
+        // Unknown sensors are left to the existing checks
+        assert!(config.check_units(&curve("cpu", "missing"), &base).is_ok());
+
+        let pid = |sensor: &str| {
+            let params = PidParams {
+                setpoint_c: 60.0,
+                kp: 8.0,
+                ki: 0.5,
+                kd: 0.0,
+                min_pwm: 40,
+                max_pwm: 255,
+            };
+            FanAssignment::pid(sensor.to_string(), params)
+        };
+        assert!(config.check_units(&pid("virtual/hottest"), &base).is_ok());
+        assert_eq!(
+            config.check_units(&pid("load/cpu"), &base),
+            Err("PID setpoints are in celsius but load/cpu reads percent".to_string())
+        );
+    }
+}
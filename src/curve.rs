@@ -3,52 +3,208 @@
 
 //! Fan curve definitions and interpolation.
 //!
-//! A curve maps temperature readings to PWM duty values (0-255).
+//! A curve maps sensor readings -- usually temperatures, but also load or
+//! fan speed, see [`InputUnit`] -- to PWM duty values (0-255).
 //! Points are linearly interpolated between defined thresholds.
+//! [`CurveEvaluator`] layers hysteresis and a minimum hold time on top so
+//! a temperature hovering around a knee doesn't make the fan surge, and
+//! [`SlewLimiter`] ramps the applied PWM toward the target gradually.
 
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// What a curve's x-axis, and the sensor feeding it, measures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputUnit {
+    /// Degrees Celsius
+    #[default]
+    Celsius,
+    /// Percent, e.g. CPU utilisation or pressure
+    Percent,
+    /// Load average (runnable tasks)
+    Load,
+    /// Fan speed in revolutions per minute
+    Rpm,
+    /// Any other value, e.g. from a virtual sensor
+    Other,
+}
+
+impl InputUnit {
+    pub const ALL: [InputUnit; 5] = [
+        InputUnit::Celsius,
+        InputUnit::Percent,
+        InputUnit::Load,
+        InputUnit::Rpm,
+        InputUnit::Other,
+    ];
+
+    pub fn is_celsius(&self) -> bool {
+        *self == InputUnit::Celsius
+    }
+
+    /// Suffix for values, e.g. "°C".
+    pub fn symbol(self) -> &'static str {
+        match self {
+            InputUnit::Celsius => "°C",
+            InputUnit::Percent => "%",
+            InputUnit::Rpm => " RPM",
+            InputUnit::Load | InputUnit::Other => "",
+        }
+    }
+
+    /// What the axis shows, e.g. "Temperature".
+    pub fn quantity(self) -> &'static str {
+        match self {
+            InputUnit::Celsius => "Temperature",
+            InputUnit::Percent => "Utilisation",
+            InputUnit::Load => "Load",
+            InputUnit::Rpm => "Fan speed",
+            InputUnit::Other => "Input",
+        }
+    }
+
+    /// Format a reading, e.g. "45.5°C", "37.0%", "1200 RPM" or "2.15".
+    pub fn format(self, value: f64) -> String {
+        match self {
+            InputUnit::Load | InputUnit::Other => format!("{value:.2}"),
+            InputUnit::Rpm => format!("{value:.0} RPM"),
+            _ => format!("{value:.1}{}", self.symbol()),
+        }
+    }
+}
+
+impl std::fmt::Display for InputUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InputUnit::Celsius => "celsius",
+            InputUnit::Percent => "percent",
+            InputUnit::Load => "load",
+            InputUnit::Rpm => "rpm",
+            InputUnit::Other => "other",
+        })
+    }
+}
+
+impl std::str::FromStr for InputUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "celsius" => Ok(InputUnit::Celsius),
+            "percent" => Ok(InputUnit::Percent),
+            "load" => Ok(InputUnit::Load),
+            "rpm" => Ok(InputUnit::Rpm),
+            "other" => Ok(InputUnit::Other),
+            _ => Err(format!(
+                "Unknown unit '{s}', expected celsius, percent, load, rpm or other"
+            )),
+        }
+    }
+}
 
 /// A single point on a fan curve.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct CurvePoint {
-    /// Temperature in degrees Celsius
-    pub temp_c: f64,
+    /// Input value in the curve's [`FanCurve::unit`]. Configs written
+    /// before curves had units call it `temp_c`.
+    #[serde(alias = "temp_c")]
+    pub input: f64,
     /// PWM duty value (0-255)
     pub pwm: u8,
 }
 
-/// A named fan curve with an ordered list of temperature-to-PWM points.
+/// A named fan curve with an ordered list of input-to-PWM points.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FanCurve {
     /// Unique name for this curve
     pub name: String,
-    /// Points sorted by ascending temperature.
+    /// Points sorted by ascending input.
     /// Must have at least 2 points.
     pub points: Vec<CurvePoint>,
+    /// Drop in the input (in the curve's unit) below the reading that set
+    /// the current PWM before the PWM is allowed to decrease. 0 disables
+    /// hysteresis.
+    #[serde(default, alias = "hysteresis_c")]
+    pub hysteresis: f64,
+    /// Minimum time (ms) the PWM is held after a change before it is
+    /// allowed to decrease. 0 disables the hold.
+    #[serde(default)]
+    pub hold_ms: u64,
+    /// Maximum PWM increase per second. 0 means unlimited.
+    #[serde(default)]
+    pub ramp_up_rate: f64,
+    /// Maximum PWM decrease per second. 0 means unlimited.
+    #[serde(default)]
+    pub ramp_down_rate: f64,
+    /// Treat PWM values as relative to the fan's calibrated usable range
+    /// (see [`FanCalibration::scale`](crate::calibration::FanCalibration::scale))
+    /// instead of raw duty. Ignored for uncalibrated fans.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub relative: bool,
+    /// Unit of the x-axis. A curve may only be bound to sensors reading
+    /// in the same unit.
+    #[serde(default, skip_serializing_if = "InputUnit::is_celsius")]
+    pub unit: InputUnit,
 }
 
 impl FanCurve {
-    /// Create a new fan curve. Points are sorted by temperature automatically.
+    /// Create a new fan curve. Points are sorted by input automatically.
     pub fn new(name: String, mut points: Vec<CurvePoint>) -> Self {
-        points.sort_by(|a, b| a.temp_c.partial_cmp(&b.temp_c).unwrap());
-        Self { name, points }
+        points.sort_by(|a, b| a.input.partial_cmp(&b.input).unwrap());
+        Self {
+            name,
+            points,
+            hysteresis: 0.0,
+            hold_ms: 0,
+            ramp_up_rate: 0.0,
+            ramp_down_rate: 0.0,
+            relative: false,
+            unit: InputUnit::Celsius,
+        }
     }
 
-    /// Interpolate the PWM value for a given temperature.
+    /// Set the hysteresis band and minimum hold time.
+    pub fn with_hysteresis(mut self, hysteresis: f64, hold_ms: u64) -> Self {
+        self.hysteresis = hysteresis;
+        self.hold_ms = hold_ms;
+        self
+    }
+
+    /// Set the maximum ramp-up and ramp-down rates in PWM units per second.
+    pub fn with_ramp_rates(mut self, up: f64, down: f64) -> Self {
+        self.ramp_up_rate = up;
+        self.ramp_down_rate = down;
+        self
+    }
+
+    /// Make the curve's PWM values relative to the fan's usable range.
+    pub fn with_relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
+    }
+
+    /// Set the unit of the x-axis.
+    pub fn with_unit(mut self, unit: InputUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Interpolate the PWM value for a given input.
     ///
     /// - Below the lowest point: returns the lowest point's PWM
     /// - Above the highest point: returns the highest point's PWM
     /// - Between two points: linear interpolation
-    pub fn interpolate(&self, temp_c: f64) -> u8 {
+    pub fn interpolate(&self, input: f64) -> u8 {
         if self.points.is_empty() {
             return 0;
         }
-        if self.points.len() == 1 || temp_c <= self.points[0].temp_c {
+        if self.points.len() == 1 || input <= self.points[0].input {
             return self.points[0].pwm;
         }
 
         let last = &self.points[self.points.len() - 1];
-        if temp_c >= last.temp_c {
+        if input >= last.input {
             return last.pwm;
         }
 
@@ -57,12 +213,12 @@ impl FanCurve {
             let lo = &window[0];
             let hi = &window[1];
 
-            if temp_c >= lo.temp_c && temp_c <= hi.temp_c {
-                let range_t = hi.temp_c - lo.temp_c;
+            if input >= lo.input && input <= hi.input {
+                let range_t = hi.input - lo.input;
                 if range_t == 0.0 {
                     return lo.pwm;
                 }
-                let frac = (temp_c - lo.temp_c) / range_t;
+                let frac = (input - lo.input) / range_t;
                 let pwm_f = lo.pwm as f64 + frac * (hi.pwm as f64 - lo.pwm as f64);
                 return pwm_f.round().clamp(0.0, 255.0) as u8;
             }
@@ -77,26 +233,155 @@ impl FanCurve {
             return Err("Curve must have at least 2 points".to_string());
         }
         for (i, p) in self.points.iter().enumerate() {
-            if i > 0 && p.temp_c <= self.points[i - 1].temp_c {
+            if i > 0 && p.input <= self.points[i - 1].input {
                 return Err(format!(
-                    "Points must have strictly increasing temperatures (point {i})"
+                    "Points must have strictly increasing inputs (point {i})"
                 ));
             }
         }
+        if !self.hysteresis.is_finite() || self.hysteresis < 0.0 {
+            return Err("Hysteresis must not be negative".to_string());
+        }
+        for rate in [self.ramp_up_rate, self.ramp_down_rate] {
+            if !rate.is_finite() || rate < 0.0 {
+                return Err("Ramp rates must be non-negative PWM units per second".to_string());
+            }
+        }
         Ok(())
     }
 }
 
+/// Stateful curve evaluation with hysteresis and minimum hold time.
+///
+/// Increases in PWM are applied immediately. A decrease is only applied
+/// once the input has fallen `hysteresis` below the reading that
+/// set the current PWM *and* `hold_ms` has passed since the last change.
+/// The daemon keeps one evaluator per fan.
+#[derive(Debug, Clone, Default)]
+pub struct CurveEvaluator {
+    /// PWM currently applied, with the input that produced it and
+    /// when it was set.
+    last: Option<(u8, f64, Instant)>,
+}
+
+impl CurveEvaluator {
+    /// Create an evaluator with nothing applied yet; its first reading
+    /// takes effect straight away.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate `curve` at `input`, returning the PWM to apply.
+    pub fn evaluate(&mut self, curve: &FanCurve, input: f64, now: Instant) -> u8 {
+        let target = curve.interpolate(input);
+
+        let Some((pwm, anchor, changed_at)) = self.last else {
+            self.last = Some((target, input, now));
+            return target;
+        };
+
+        if target > pwm {
+            self.last = Some((target, input, now));
+            return target;
+        }
+        if target == pwm {
+            // Track the highest reading at this level so the band is
+            // measured from the peak.
+            self.last = Some((pwm, anchor.max(input), changed_at));
+            return pwm;
+        }
+
+        let cooled = input <= anchor - curve.hysteresis;
+        let held =
+            now.saturating_duration_since(changed_at) >= Duration::from_millis(curve.hold_ms);
+        if cooled && held {
+            self.last = Some((target, input, now));
+            target
+        } else {
+            pwm
+        }
+    }
+
+    /// Forget the current state, e.g. after the assignment changes.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Limits how fast the applied PWM may move toward a target.
+///
+/// The applied value is tracked as a float so that slow rates still make
+/// progress when the poll interval is short.
+#[derive(Debug, Clone, Default)]
+pub struct SlewLimiter {
+    applied: Option<(f64, Instant)>,
+}
+
+impl SlewLimiter {
+    /// Create an unseeded limiter; until [`SlewLimiter::seed`] is called
+    /// the first step jumps straight to its target.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start ramping from `pwm` (typically the value currently in sysfs).
+    pub fn seed(&mut self, pwm: u8, now: Instant) {
+        self.applied = Some((pwm as f64, now));
+    }
+
+    /// Whether the limiter has a starting point yet.
+    pub fn is_seeded(&self) -> bool {
+        self.applied.is_some()
+    }
+
+    /// Move toward `target`, limited to `up_rate`/`down_rate` PWM units per
+    /// second (0 = unlimited). Returns the PWM to apply.
+    pub fn step(&mut self, target: u8, up_rate: f64, down_rate: f64, now: Instant) -> u8 {
+        let target_f = target as f64;
+        let Some((applied, last)) = self.applied else {
+            self.applied = Some((target_f, now));
+            return target;
+        };
+
+        let dt = now.saturating_duration_since(last).as_secs_f64();
+        let next = if target_f > applied && up_rate > 0.0 {
+            (applied + up_rate * dt).min(target_f)
+        } else if target_f < applied && down_rate > 0.0 {
+            (applied - down_rate * dt).max(target_f)
+        } else {
+            target_f
+        };
+
+        self.applied = Some((next, now));
+        next.round().clamp(0.0, 255.0) as u8
+    }
+}
+
 /// A default "silent" curve: low speed until 50C, ramp up to full at 90C.
 pub fn default_silent_curve() -> FanCurve {
     FanCurve::new(
         "silent".to_string(),
         vec![
-            CurvePoint { temp_c: 30.0, pwm: 0 },
-            CurvePoint { temp_c: 50.0, pwm: 64 },
-            CurvePoint { temp_c: 70.0, pwm: 153 },
-            CurvePoint { temp_c: 80.0, pwm: 204 },
-            CurvePoint { temp_c: 90.0, pwm: 255 },
+            CurvePoint {
+                input: 30.0,
+                pwm: 0,
+            },
+            CurvePoint {
+                input: 50.0,
+                pwm: 64,
+            },
+            CurvePoint {
+                input: 70.0,
+                pwm: 153,
+            },
+            CurvePoint {
+                input: 80.0,
+                pwm: 204,
+            },
+            CurvePoint {
+                input: 90.0,
+                pwm: 255,
+            },
         ],
     )
 }
@@ -106,10 +391,22 @@ pub fn default_performance_curve() -> FanCurve {
     FanCurve::new(
         "performance".to_string(),
         vec![
-            CurvePoint { temp_c: 30.0, pwm: 64 },
-            CurvePoint { temp_c: 50.0, pwm: 128 },
-            CurvePoint { temp_c: 65.0, pwm: 204 },
-            CurvePoint { temp_c: 75.0, pwm: 255 },
+            CurvePoint {
+                input: 30.0,
+                pwm: 64,
+            },
+            CurvePoint {
+                input: 50.0,
+                pwm: 128,
+            },
+            CurvePoint {
+                input: 65.0,
+                pwm: 204,
+            },
+            CurvePoint {
+                input: 75.0,
+                pwm: 255,
+            },
         ],
     )
 }
@@ -141,19 +438,126 @@ mod tests {
         let curve = FanCurve::new(
             "test".to_string(),
             vec![
-                CurvePoint { temp_c: 0.0, pwm: 0 },
-                CurvePoint { temp_c: 100.0, pwm: 200 },
+                CurvePoint { input: 0.0, pwm: 0 },
+                CurvePoint {
+                    input: 100.0,
+                    pwm: 200,
+                },
             ],
         );
         assert_eq!(curve.interpolate(50.0), 100);
     }
 
+    #[test]
+    fn test_unit_defaults_to_celsius() {
+        let celsius: FanCurve = toml::from_str(
+            "name = \"cpu\"\nhysteresis_c = 2.0\n\
+             points = [{ temp_c = 40.0, pwm = 0 }, { temp_c = 80.0, pwm = 255 }]",
+        )
+        .unwrap();
+        assert_eq!(celsius.unit, InputUnit::Celsius);
+        assert_eq!(celsius.hysteresis, 2.0);
+        assert_eq!(celsius.points[1].input, 80.0);
+        assert!(!toml::to_string(&celsius).unwrap().contains("unit"));
+
+        let load = celsius.with_unit(InputUnit::Percent);
+        let text = toml::to_string(&load).unwrap();
+        assert!(text.contains("unit = \"percent\""));
+        assert_eq!(
+            toml::from_str::<FanCurve>(&text).unwrap().unit,
+            InputUnit::Percent
+        );
+        assert_eq!(InputUnit::Percent.format(37.25), "37.2%");
+        assert_eq!("load".parse(), Ok(InputUnit::Load));
+    }
+
     #[test]
     fn test_validation_too_few_points() {
         let curve = FanCurve::new(
             "bad".to_string(),
-            vec![CurvePoint { temp_c: 50.0, pwm: 128 }],
+            vec![CurvePoint {
+                input: 50.0,
+                pwm: 128,
+            }],
         );
         assert!(curve.validate().is_err());
     }
+
+    #[test]
+    fn test_evaluator_hysteresis() {
+        let curve = FanCurve::new(
+            "test".to_string(),
+            vec![
+                CurvePoint { input: 0.0, pwm: 0 },
+                CurvePoint {
+                    input: 100.0,
+                    pwm: 200,
+                },
+            ],
+        )
+        .with_hysteresis(5.0, 0);
+        let mut eval = CurveEvaluator::new();
+        let now = Instant::now();
+
+        assert_eq!(eval.evaluate(&curve, 50.0, now), 100);
+        // Rises apply immediately
+        assert_eq!(eval.evaluate(&curve, 60.0, now), 120);
+        // Small drops inside the band are ignored
+        assert_eq!(eval.evaluate(&curve, 57.0, now), 120);
+        // Crossing the band lets the PWM fall
+        assert_eq!(eval.evaluate(&curve, 55.0, now), 110);
+    }
+
+    #[test]
+    fn test_evaluator_hold_time() {
+        let curve = FanCurve::new(
+            "test".to_string(),
+            vec![
+                CurvePoint { input: 0.0, pwm: 0 },
+                CurvePoint {
+                    input: 100.0,
+                    pwm: 200,
+                },
+            ],
+        )
+        .with_hysteresis(0.0, 1000);
+        let mut eval = CurveEvaluator::new();
+        let start = Instant::now();
+
+        assert_eq!(eval.evaluate(&curve, 80.0, start), 160);
+        assert_eq!(
+            eval.evaluate(&curve, 40.0, start + Duration::from_millis(500)),
+            160
+        );
+        assert_eq!(
+            eval.evaluate(&curve, 40.0, start + Duration::from_millis(1000)),
+            80
+        );
+    }
+
+    #[test]
+    fn test_slew_limiter() {
+        let mut slew = SlewLimiter::new();
+        let start = Instant::now();
+        slew.seed(0, start);
+
+        assert_eq!(
+            slew.step(255, 50.0, 10.0, start + Duration::from_secs(2)),
+            100
+        );
+        assert_eq!(
+            slew.step(255, 50.0, 10.0, start + Duration::from_secs(4)),
+            200
+        );
+        assert_eq!(
+            slew.step(255, 50.0, 10.0, start + Duration::from_secs(6)),
+            255
+        );
+        assert_eq!(
+            slew.step(0, 50.0, 10.0, start + Duration::from_secs(8)),
+            235
+        );
+        // Unlimited rate jumps straight to the target
+        assert_eq!(slew.step(0, 50.0, 0.0, start + Duration::from_secs(9)), 0);
+    }
 }
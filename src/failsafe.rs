@@ -0,0 +1,354 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Over-temperature and lost-sensor failsafe.
+//!
+//! Each tick the daemon checks every sensor against the kernel's
+//! `tempN_crit` / `tempN_max` thresholds and the user's own limits, and
+//! checks that every sensor an assignment reads still returns a value. A
+//! tripped sensor overrides the assignments of the fans it affects until it
+//! has stayed clear for [`FailsafeConfig::clear_ms`].
+
+use crate::hwmon::TempSensor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Default time a condition must stay clear before fans are released.
+pub const DEFAULT_CLEAR_MS: u64 = 10_000;
+
+/// Failsafe settings (`[failsafe]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailsafeConfig {
+    /// Whether the failsafe runs at all.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Trip when a sensor reaches its kernel `tempN_crit` threshold.
+    #[serde(default = "default_true")]
+    pub kernel_crit: bool,
+
+    /// Trip when a sensor reaches its kernel `tempN_max` threshold. Off by
+    /// default because some drivers set it well inside normal operation.
+    #[serde(default)]
+    pub kernel_max: bool,
+
+    /// User limits in °C, keyed by sensor id (physical or virtual).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub limits: HashMap<String, f64>,
+
+    /// Trip when a sensor used by an assignment stops returning readings.
+    #[serde(default = "default_true")]
+    pub missing_reading: bool,
+
+    /// What to do with the affected fans.
+    #[serde(default)]
+    pub action: FailsafeAction,
+
+    /// Which fans a tripped sensor overrides.
+    #[serde(default)]
+    pub scope: FailsafeScope,
+
+    /// How long a condition must stay clear before normal control
+    /// resumes, in milliseconds.
+    #[serde(default = "default_clear_ms")]
+    pub clear_ms: u64,
+}
+
+impl Default for FailsafeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            kernel_crit: true,
+            kernel_max: false,
+            limits: HashMap::new(),
+            missing_reading: true,
+            action: FailsafeAction::default(),
+            scope: FailsafeScope::default(),
+            clear_ms: DEFAULT_CLEAR_MS,
+        }
+    }
+}
+
+/// How fans are driven while the failsafe is active.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailsafeAction {
+    /// PWM 255
+    #[default]
+    FullSpeed,
+    /// Hand the fan back to BIOS/firmware control
+    Auto,
+}
+
+impl FailsafeAction {
+    /// Short human-readable description, e.g. "full speed".
+    pub fn describe(&self) -> &'static str {
+        match self {
+            FailsafeAction::FullSpeed => "full speed",
+            FailsafeAction::Auto => "automatic",
+        }
+    }
+}
+
+/// Which fans a tripped sensor overrides.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailsafeScope {
+    /// Every fan
+    #[default]
+    All,
+    /// Only fans whose assignment reads the sensor (directly or through a
+    /// virtual sensor)
+    Affected,
+}
+
+/// Why a sensor tripped the failsafe.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailsafeReason {
+    /// Reached the kernel `tempN_crit` threshold
+    Critical { temp_c: f64, limit_c: f64 },
+    /// Reached a user-configured limit
+    Limit { temp_c: f64, limit_c: f64 },
+    /// Reached the kernel `tempN_max` threshold
+    Max { temp_c: f64, limit_c: f64 },
+    /// A sensor an assignment depends on returned no reading
+    NoReading,
+}
+
+impl FailsafeReason {
+    /// Short human-readable description, e.g. "96.0°C >= crit 95.0°C".
+    pub fn describe(&self) -> String {
+        match self {
+            FailsafeReason::Critical { temp_c, limit_c } => {
+                format!("{temp_c:.1}°C >= crit {limit_c:.1}°C")
+            }
+            FailsafeReason::Limit { temp_c, limit_c } => {
+                format!("{temp_c:.1}°C >= limit {limit_c:.1}°C")
+            }
+            FailsafeReason::Max { temp_c, limit_c } => {
+                format!("{temp_c:.1}°C >= max {limit_c:.1}°C")
+            }
+            FailsafeReason::NoReading => "no reading".to_string(),
+        }
+    }
+
+    /// Machine-readable name of the reason, matching its serialized `kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FailsafeReason::Critical { .. } => "critical",
+            FailsafeReason::Limit { .. } => "limit",
+            FailsafeReason::Max { .. } => "max",
+            FailsafeReason::NoReading => "no_reading",
+        }
+    }
+}
+
+/// Find every sensor currently over a threshold or missing a reading.
+///
+/// `watched` lists the sensor ids assignments read; only those are checked
+/// for missing readings. At most one reason is reported per sensor, the
+/// most severe first.
+pub fn check_sensors(
+    sensors: &[TempSensor],
+    temp_map: &HashMap<String, f64>,
+    watched: &[&str],
+    config: &FailsafeConfig,
+) -> Vec<(String, FailsafeReason)> {
+    let mut tripped: Vec<(String, FailsafeReason)> = Vec::new();
+    let mut trip = |id: &str, reason| {
+        if !tripped.iter().any(|(t, _)| t == id) {
+            tripped.push((id.to_string(), reason));
+        }
+    };
+
+    if config.kernel_crit {
+        for sensor in sensors {
+            if let (Some(&temp_c), Some(limit_c)) = (temp_map.get(&sensor.id), sensor.crit_c) {
+                if temp_c >= limit_c {
+                    trip(&sensor.id, FailsafeReason::Critical { temp_c, limit_c });
+                }
+            }
+        }
+    }
+    for (id, &limit_c) in &config.limits {
+        if let Some(&temp_c) = temp_map.get(id) {
+            if temp_c >= limit_c {
+                trip(id, FailsafeReason::Limit { temp_c, limit_c });
+            }
+        }
+    }
+    if config.kernel_max {
+        for sensor in sensors {
+            if let (Some(&temp_c), Some(limit_c)) = (temp_map.get(&sensor.id), sensor.max_c) {
+                if temp_c >= limit_c {
+                    trip(&sensor.id, FailsafeReason::Max { temp_c, limit_c });
+                }
+            }
+        }
+    }
+    if config.missing_reading {
+        for &id in watched {
+            if !temp_map.contains_key(id) {
+                trip(id, FailsafeReason::NoReading);
+            }
+        }
+    }
+
+    tripped
+}
+
+/// A change in the failsafe state of one sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailsafeChange {
+    /// The sensor newly tripped the failsafe.
+    Triggered {
+        sensor_id: String,
+        reason: FailsafeReason,
+    },
+    /// The sensor has been clear for the configured time.
+    Cleared { sensor_id: String },
+}
+
+/// State of one tripped sensor.
+#[derive(Debug, Clone)]
+struct Trip {
+    reason: FailsafeReason,
+    /// When the condition was first seen clear again
+    clear_since: Option<Instant>,
+}
+
+/// Running failsafe state across all sensors.
+#[derive(Debug, Clone, Default)]
+pub struct Failsafe {
+    tripped: HashMap<String, Trip>,
+}
+
+impl Failsafe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether any sensor is currently tripped.
+    pub fn is_active(&self) -> bool {
+        !self.tripped.is_empty()
+    }
+
+    /// The tripped sensors and why they tripped.
+    pub fn tripped(&self) -> impl Iterator<Item = (&str, &FailsafeReason)> {
+        self.tripped.iter().map(|(id, t)| (id.as_str(), &t.reason))
+    }
+
+    /// Feed this tick's conditions (from [`check_sensors`]) and return the
+    /// sensors that tripped or cleared.
+    pub fn update(
+        &mut self,
+        conditions: Vec<(String, FailsafeReason)>,
+        config: &FailsafeConfig,
+        now: Instant,
+    ) -> Vec<FailsafeChange> {
+        let mut changes = Vec::new();
+
+        for trip in self.tripped.values_mut() {
+            trip.clear_since.get_or_insert(now);
+        }
+        for (sensor_id, reason) in conditions {
+            match self.tripped.get_mut(&sensor_id) {
+                Some(trip) => {
+                    trip.reason = reason;
+                    trip.clear_since = None;
+                }
+                None => {
+                    self.tripped.insert(
+                        sensor_id.clone(),
+                        Trip {
+                            reason,
+                            clear_since: None,
+                        },
+                    );
+                    changes.push(FailsafeChange::Triggered { sensor_id, reason });
+                }
+            }
+        }
+
+        let hold = Duration::from_millis(config.clear_ms);
+        self.tripped.retain(|sensor_id, trip| {
+            let cleared = trip
+                .clear_since
+                .is_some_and(|since| now.saturating_duration_since(since) >= hold);
+            if cleared {
+                changes.push(FailsafeChange::Cleared {
+                    sensor_id: sensor_id.clone(),
+                });
+            }
+            !cleared
+        });
+
+        changes
+    }
+
+    /// Forget every trip, e.g. when the failsafe is disabled.
+    pub fn reset(&mut self) {
+        self.tripped.clear();
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_clear_ms() -> u64 {
+    DEFAULT_CLEAR_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeSysfs;
+    use crate::hwmon;
+
+    #[test]
+    fn test_check_sensors() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("k10temp").unwrap();
+        chip.add_temp(1, 96_000, Some("Tctl")).unwrap();
+        chip.write_attr("temp1_crit", 95_000).unwrap();
+        chip.add_temp(2, 50_000, None).unwrap();
+        let sensors = hwmon::discover_temp_sensors(&sysfs.root()).unwrap();
+        let temp_map = hwmon::read_temp_map(&sensors, &HashMap::new());
+
+        let mut config = FailsafeConfig::default();
+        config.limits.insert("k10temp/temp2".to_string(), 50.0);
+        let tripped = check_sensors(&sensors, &temp_map, &["gone/temp1"], &config);
+
+        assert_eq!(tripped.len(), 3);
+        assert!(matches!(tripped[0].1, FailsafeReason::Critical { .. }));
+        assert_eq!(tripped[1].0, "k10temp/temp2");
+        assert_eq!(
+            tripped[2],
+            ("gone/temp1".to_string(), FailsafeReason::NoReading)
+        );
+    }
+
+    #[test]
+    fn test_clears_after_hold() {
+        let config = FailsafeConfig::default();
+        let mut failsafe = Failsafe::new();
+        let start = Instant::now();
+        let at = |s| start + Duration::from_secs(s);
+        let hot = || vec![("cpu".to_string(), FailsafeReason::NoReading)];
+
+        let changes = failsafe.update(hot(), &config, at(0));
+        assert!(matches!(changes[..], [FailsafeChange::Triggered { .. }]));
+        assert!(failsafe.update(Vec::new(), &config, at(1)).is_empty());
+        // Flapping back resets the hold
+        assert!(failsafe.update(hot(), &config, at(5)).is_empty());
+        assert!(failsafe.update(Vec::new(), &config, at(6)).is_empty());
+        assert!(failsafe.update(Vec::new(), &config, at(15)).is_empty());
+        assert!(failsafe.is_active());
+        let changes = failsafe.update(Vec::new(), &config, at(16));
+        assert!(matches!(changes[..], [FailsafeChange::Cleared { .. }]));
+        assert!(!failsafe.is_active());
+    }
+}
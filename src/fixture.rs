@@ -0,0 +1,228 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Fake sysfs and procfs trees for tests and development.
+//!
+//! Builds a `class/hwmon/hwmonN/...` hierarchy with `pwmN`, `pwmN_enable`,
+//! `fanN_input` and `tempN_input` files so that discovery, the curve engine
+//! and the socket protocol can be exercised without real fans. Point the
+//! daemon at one with `--sysfs-root`. [`FakeProcfs`] does the same for the
+//! load and process readings behind `--procfs-root`.
+
+use crate::hwmon::HwmonRoot;
+use crate::sysload::ProcRoot;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A fake sysfs root containing a `class/hwmon` directory.
+#[derive(Debug)]
+pub struct FakeSysfs {
+    root: PathBuf,
+    remove_on_drop: bool,
+}
+
+/// A single fake `hwmonN` chip directory.
+#[derive(Debug, Clone)]
+pub struct FakeChip {
+    dir: PathBuf,
+    sysfs_root: PathBuf,
+}
+
+impl FakeSysfs {
+    /// Create a fake tree in a fresh temporary directory. The directory is
+    /// removed again when the value is dropped.
+    pub fn new() -> io::Result<Self> {
+        let root = fresh_temp_dir("sysfs");
+        let mut sysfs = Self::create(&root)?;
+        sysfs.remove_on_drop = true;
+        Ok(sysfs)
+    }
+
+    /// Create (or reuse) a fake tree at `path`. The tree is left on disk
+    /// so it can be handed to `fanctl-daemon --sysfs-root`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let sysfs = Self {
+            root: path.to_path_buf(),
+            remove_on_drop: false,
+        };
+        fs::create_dir_all(sysfs.root().hwmon_dir())?;
+        Ok(sysfs)
+    }
+
+    /// The sysfs root to pass to discovery.
+    pub fn root(&self) -> HwmonRoot {
+        HwmonRoot::new(&self.root)
+    }
+
+    /// The directory the tree lives in.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Add a chip with the given hwmon `name`. Chips are numbered
+    /// `hwmon0`, `hwmon1`, ... in creation order.
+    pub fn add_chip(&self, name: &str) -> io::Result<FakeChip> {
+        let hwmon_dir = self.root().hwmon_dir();
+        let index = fs::read_dir(&hwmon_dir)?.count();
+        let dir = hwmon_dir.join(format!("hwmon{index}"));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("name"), format!("{name}\n"))?;
+        Ok(FakeChip {
+            dir,
+            sysfs_root: self.root.clone(),
+        })
+    }
+
+    /// Add a chip that lives under `devices/<device>/hwmon` with a
+    /// `class/hwmon/hwmonN` symlink to it, as the kernel lays out chips
+    /// such as thermal zones that have no `device` link of their own.
+    pub fn add_chip_in(&self, name: &str, device: &str) -> io::Result<FakeChip> {
+        let hwmon_dir = self.root().hwmon_dir();
+        let basename = format!("hwmon{}", fs::read_dir(&hwmon_dir)?.count());
+        let dir = self
+            .root
+            .join("devices")
+            .join(device)
+            .join("hwmon")
+            .join(&basename);
+        fs::create_dir_all(&dir)?;
+        std::os::unix::fs::symlink(&dir, hwmon_dir.join(&basename))?;
+        fs::write(dir.join("name"), format!("{name}\n"))?;
+        Ok(FakeChip {
+            dir,
+            sysfs_root: self.root.clone(),
+        })
+    }
+}
+
+impl Drop for FakeSysfs {
+    fn drop(&mut self) {
+        if self.remove_on_drop {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// A fake procfs root with `stat`, `loadavg` and per-process entries,
+/// removed again when dropped.
+#[derive(Debug)]
+pub struct FakeProcfs {
+    root: PathBuf,
+}
+
+impl FakeProcfs {
+    /// Create an empty tree in a fresh temporary directory.
+    pub fn new() -> io::Result<Self> {
+        let root = fresh_temp_dir("proc");
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    /// The procfs root to read from.
+    pub fn root(&self) -> ProcRoot {
+        ProcRoot::new(&self.root)
+    }
+
+    /// The directory the tree lives in.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Write a file such as `stat` or `pressure/cpu`, creating parent
+    /// directories as needed.
+    pub fn write(&self, file: &str, contents: &str) -> io::Result<()> {
+        let path = self.root.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+
+    /// Add a `<pid>` directory with `comm` and a NUL-separated `cmdline`.
+    pub fn add_process(&self, pid: u32, comm: &str, argv: &[&str]) -> io::Result<()> {
+        let dir = self.root.join(pid.to_string());
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("comm"), format!("{comm}\n"))?;
+        let cmdline: String = argv.iter().map(|arg| format!("{arg}\0")).collect();
+        fs::write(dir.join("cmdline"), cmdline)
+    }
+}
+
+impl Drop for FakeProcfs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// A temporary directory path no other fake tree in this process uses,
+/// cleared of anything a previous run left behind.
+fn fresh_temp_dir(kind: &str) -> PathBuf {
+    let id = NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed);
+    let root = std::env::temp_dir().join(format!("fanctl-{kind}-{}-{id}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    root
+}
+
+impl FakeChip {
+    /// The `hwmonN` directory of this chip.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Create `devices/<device>` and point the chip's `device` symlink at
+    /// it, as the kernel does for PCI and platform devices.
+    pub fn link_device(&self, device: &str) -> io::Result<&Self> {
+        let target = self.sysfs_root.join("devices").join(device);
+        fs::create_dir_all(&target)?;
+        let link = self.dir.join("device");
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&target, link)?;
+        Ok(self)
+    }
+
+    /// Add PWM channel `n` with an initial duty value. The channel starts in
+    /// automatic mode (`pwmN_enable = 2`). A tachometer (`fanN_input`) is
+    /// created when `rpm` is given.
+    pub fn add_fan(&self, n: u32, pwm: u8, rpm: Option<u32>) -> io::Result<&Self> {
+        self.write_attr(&format!("pwm{n}"), pwm)?;
+        self.write_attr(&format!("pwm{n}_enable"), 2)?;
+        if let Some(rpm) = rpm {
+            self.set_rpm(n, rpm)?;
+        }
+        Ok(self)
+    }
+
+    /// Add temperature channel `n` reading `millidegrees` with an optional
+    /// `tempN_label`.
+    pub fn add_temp(&self, n: u32, millidegrees: i64, label: Option<&str>) -> io::Result<&Self> {
+        self.set_temp(n, millidegrees)?;
+        if let Some(label) = label {
+            self.write_attr(&format!("temp{n}_label"), label)?;
+        }
+        Ok(self)
+    }
+
+    /// Update the tachometer reading for channel `n`.
+    pub fn set_rpm(&self, n: u32, rpm: u32) -> io::Result<()> {
+        self.write_attr(&format!("fan{n}_input"), rpm)
+    }
+
+    /// Update the temperature reading for channel `n`.
+    pub fn set_temp(&self, n: u32, millidegrees: i64) -> io::Result<()> {
+        self.write_attr(&format!("temp{n}_input"), millidegrees)
+    }
+
+    /// Write an arbitrary attribute file in the chip directory.
+    pub fn write_attr(&self, attr: &str, value: impl std::fmt::Display) -> io::Result<()> {
+        fs::write(self.dir.join(attr), format!("{value}\n"))
+    }
+
+    /// Read back an attribute file, trimmed.
+    pub fn read_attr(&self, attr: &str) -> io::Result<String> {
+        fs::read_to_string(self.dir.join(attr)).map(|s| s.trim().to_string())
+    }
+}
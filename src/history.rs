@@ -0,0 +1,402 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Recent sensor and fan history.
+//!
+//! The daemon records a [`Sample`] of every reading, the PWM it applied
+//! and what set it at most once per `interval_ms`, keeping `retention_secs`
+//! worth in a ring buffer. Clients ask for a window of it with
+//! [`History::query`], which can average samples into coarser buckets so
+//! an hour of history doesn't cost an hour of samples on the wire. The
+//! buffer can optionally be written to disk and loaded back on start.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default time samples are kept for.
+pub const DEFAULT_RETENTION_SECS: u64 = 3600;
+
+/// Default minimum time between samples.
+pub const DEFAULT_INTERVAL_MS: u64 = 1000;
+
+/// Default time between writes of the buffer to `persist_path`.
+pub const DEFAULT_PERSIST_SECS: u64 = 300;
+
+/// History settings (`[history]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryConfig {
+    /// Whether samples are recorded at all.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// How long samples are kept, in seconds.
+    #[serde(default = "default_retention_secs")]
+    pub retention_secs: u64,
+
+    /// Minimum time between samples, in milliseconds. Samples are taken on
+    /// control-loop ticks, so this is rounded up to the poll interval.
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+
+    /// File the buffer is saved to periodically and on exit, and loaded
+    /// from on start. Unset keeps history in memory only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persist_path: Option<String>,
+
+    /// Time between saves to `persist_path`, in seconds.
+    #[serde(default = "default_persist_secs")]
+    pub persist_secs: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_secs: DEFAULT_RETENTION_SECS,
+            interval_ms: DEFAULT_INTERVAL_MS,
+            persist_path: None,
+            persist_secs: DEFAULT_PERSIST_SECS,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// Number of samples that cover `retention_secs`.
+    pub fn capacity(&self) -> usize {
+        let samples = self.retention_secs.saturating_mul(1000) / self.interval_ms.max(1);
+        samples.max(1) as usize
+    }
+}
+
+/// Readings from one control-loop tick.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    /// Unix time in milliseconds
+    pub time_ms: u64,
+    /// Sensor readings by id: temperatures, load, tachometers and virtual
+    /// sensors, each in its own unit
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sensors: BTreeMap<String, f64>,
+    /// Fan state by id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fans: BTreeMap<String, FanSample>,
+}
+
+/// One fan's state in a [`Sample`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FanSample {
+    /// PWM the daemon applied, or the PWM read back for fans it doesn't
+    /// drive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pwm: Option<u8>,
+    /// Fan speed in RPM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpm: Option<u32>,
+    /// What set the PWM, e.g. "cpu @ k10temp/temp1", "manual" or
+    /// "failsafe: full speed"; absent under BIOS control
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Ring buffer of samples, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Change how many samples are kept, dropping the oldest if needed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.trim();
+    }
+
+    /// Add a sample, evicting the oldest once the buffer is full.
+    pub fn push(&mut self, sample: Sample) {
+        self.samples.push_back(sample);
+        self.trim();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Time of the newest sample.
+    pub fn last_time_ms(&self) -> Option<u64> {
+        self.samples.back().map(|s| s.time_ms)
+    }
+
+    /// Samples at or after `since_ms` (Unix time), keeping only `ids`
+    /// (sensor or fan ids; empty keeps everything). With a `resolution_ms`
+    /// samples are averaged into buckets that wide, each stamped with the
+    /// start of its bucket; a fan's source is the last one in the bucket.
+    pub fn query(
+        &self,
+        ids: &[String],
+        since_ms: Option<u64>,
+        resolution_ms: Option<u64>,
+    ) -> Vec<Sample> {
+        let wanted = |id: &String| ids.is_empty() || ids.contains(id);
+        let selected = self
+            .samples
+            .iter()
+            .filter(|s| since_ms.is_none_or(|since| s.time_ms >= since))
+            .map(|s| Sample {
+                time_ms: s.time_ms,
+                sensors: s
+                    .sensors
+                    .iter()
+                    .filter(|(id, _)| wanted(id))
+                    .map(|(id, &v)| (id.clone(), v))
+                    .collect(),
+                fans: s
+                    .fans
+                    .iter()
+                    .filter(|(id, _)| wanted(id))
+                    .map(|(id, f)| (id.clone(), f.clone()))
+                    .collect(),
+            });
+
+        let resolution = resolution_ms.unwrap_or(0);
+        if resolution == 0 {
+            return selected.collect();
+        }
+        let mut out = Vec::new();
+        let mut bucket: Vec<Sample> = Vec::new();
+        for sample in selected {
+            let start = sample.time_ms - sample.time_ms % resolution;
+            if bucket
+                .first()
+                .is_some_and(|b| b.time_ms - b.time_ms % resolution != start)
+            {
+                out.push(average(&bucket, resolution));
+                bucket.clear();
+            }
+            bucket.push(sample);
+        }
+        if !bucket.is_empty() {
+            out.push(average(&bucket, resolution));
+        }
+        out
+    }
+
+    /// Write the buffer to `path` as JSON, replacing it atomically.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_vec(&self.samples)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Read a buffer written by [`History::save`], dropping samples older
+    /// than `retention_secs` before `now_ms` and any beyond `capacity`.
+    /// A missing file gives an empty buffer.
+    pub fn load(
+        path: &Path,
+        capacity: usize,
+        retention_secs: u64,
+        now_ms: u64,
+    ) -> io::Result<Self> {
+        let mut history = Self::new(capacity);
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(history),
+            Err(e) => return Err(e),
+        };
+        let samples: Vec<Sample> = serde_json::from_slice(&contents)?;
+        let cutoff = now_ms.saturating_sub(retention_secs.saturating_mul(1000));
+        for sample in samples {
+            if sample.time_ms >= cutoff && sample.time_ms <= now_ms {
+                history.push(sample);
+            }
+        }
+        Ok(history)
+    }
+
+    fn trim(&mut self) {
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// Current Unix time in milliseconds.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Merge one bucket of samples into a single sample.
+fn average(bucket: &[Sample], resolution: u64) -> Sample {
+    let mut sensors: BTreeMap<String, (f64, u32)> = BTreeMap::new();
+    let mut pwm: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+    let mut rpm: BTreeMap<String, (u64, u32)> = BTreeMap::new();
+    let mut fans: BTreeMap<String, FanSample> = BTreeMap::new();
+    for sample in bucket {
+        for (id, &value) in &sample.sensors {
+            let entry = sensors.entry(id.clone()).or_default();
+            entry.0 += value;
+            entry.1 += 1;
+        }
+        for (id, fan) in &sample.fans {
+            if let Some(p) = fan.pwm {
+                let entry = pwm.entry(id.clone()).or_default();
+                entry.0 += u32::from(p);
+                entry.1 += 1;
+            }
+            if let Some(r) = fan.rpm {
+                let entry = rpm.entry(id.clone()).or_default();
+                entry.0 += u64::from(r);
+                entry.1 += 1;
+            }
+            fans.entry(id.clone()).or_default().source = fan.source.clone();
+        }
+    }
+    for (id, fan) in &mut fans {
+        fan.pwm = pwm
+            .get(id)
+            .map(|&(sum, n)| (f64::from(sum) / f64::from(n)).round() as u8);
+        fan.rpm = rpm
+            .get(id)
+            .map(|&(sum, n)| (sum as f64 / f64::from(n)).round() as u32);
+    }
+
+    let time_ms = bucket
+        .first()
+        .map_or(0, |s| s.time_ms - s.time_ms % resolution);
+    Sample {
+        time_ms,
+        sensors: sensors
+            .into_iter()
+            .map(|(id, (sum, n))| (id, sum / f64::from(n)))
+            .collect(),
+        fans,
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_retention_secs() -> u64 {
+    DEFAULT_RETENTION_SECS
+}
+
+fn default_interval_ms() -> u64 {
+    DEFAULT_INTERVAL_MS
+}
+
+fn default_persist_secs() -> u64 {
+    DEFAULT_PERSIST_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time_ms: u64, temp: f64, pwm: u8, source: &str) -> Sample {
+        Sample {
+            time_ms,
+            sensors: [("cpu".to_string(), temp), ("gpu".to_string(), temp + 10.0)]
+                .into_iter()
+                .collect(),
+            fans: [(
+                "pwm1".to_string(),
+                FanSample {
+                    pwm: Some(pwm),
+                    rpm: Some(u32::from(pwm) * 10),
+                    source: Some(source.to_string()),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let cfg = HistoryConfig {
+            retention_secs: 3,
+            ..HistoryConfig::default()
+        };
+        let mut history = History::new(cfg.capacity());
+        for i in 0..5 {
+            history.push(sample(i * 1000, 40.0, 100, "manual"));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.query(&[], None, None)[0].time_ms, 2000);
+        assert_eq!(history.last_time_ms(), Some(4000));
+
+        history.set_capacity(1);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn test_query_downsamples() {
+        let mut history = History::new(100);
+        history.push(sample(1000, 40.0, 100, "a"));
+        history.push(sample(2000, 50.0, 200, "b"));
+        history.push(sample(5500, 60.0, 50, "c"));
+
+        let all = history.query(&["cpu".to_string(), "pwm1".to_string()], Some(1500), None);
+        assert_eq!(all.len(), 2);
+        assert!(!all[0].sensors.contains_key("gpu"));
+
+        let coarse = history.query(&[], None, Some(5000));
+        assert_eq!(coarse.len(), 2);
+        assert_eq!(coarse[0].time_ms, 0);
+        assert_eq!(coarse[0].sensors["cpu"], 45.0);
+        assert_eq!(
+            coarse[0].fans["pwm1"],
+            FanSample {
+                pwm: Some(150),
+                rpm: Some(1500),
+                source: Some("b".to_string()),
+            }
+        );
+        assert_eq!(coarse[1].time_ms, 5000);
+        assert_eq!(coarse[1].sensors["gpu"], 70.0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "fanctl-history-{}/history.json",
+            std::process::id()
+        ));
+        let mut history = History::new(10);
+        history.push(sample(1_000, 40.0, 100, "manual"));
+        history.push(sample(50_000, 45.0, 120, "manual"));
+        history.save(&path).unwrap();
+
+        // The first sample has aged out of a 30 s retention
+        let loaded = History::load(&path, 10, 30, 60_000).unwrap();
+        assert_eq!(
+            loaded.query(&[], None, None),
+            vec![sample(50_000, 45.0, 120, "manual")]
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+
+        assert!(History::load(&path, 10, 30, 60_000).unwrap().is_empty());
+    }
+}
@@ -3,16 +3,63 @@
 
 //! hwmon sysfs discovery and control.
 //!
-//! Scans `/sys/class/hwmon/` for fan and temperature sensor entries,
-//! and provides read/write access to PWM and sensor values.
-
+//! Scans `<sysfs>/class/hwmon/` for fan and temperature sensor entries,
+//! and provides read/write access to PWM and sensor values. The sysfs
+//! root is configurable via [`HwmonRoot`] so discovery can run against
+//! a fake tree (see [`crate::fixture`]).
+
+use crate::calibration::FanCalibration;
+use crate::curve::InputUnit;
+use crate::pid::PidTerms;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
-const HWMON_ROOT: &str = "/sys/class/hwmon";
+/// Default sysfs mount point.
+pub const DEFAULT_SYSFS_ROOT: &str = "/sys";
+
+/// Location of the hwmon class directory relative to the sysfs root.
+const HWMON_CLASS_DIR: &str = "class/hwmon";
+
+// ---------------------------------------------------------------------------
+// Sysfs root
+// ---------------------------------------------------------------------------
+
+/// The sysfs tree that hwmon devices are discovered under.
+///
+/// Defaults to the real `/sys`; tests and development setups can point it
+/// at a directory containing a fake `class/hwmon` hierarchy instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HwmonRoot {
+    sysfs_root: PathBuf,
+}
+
+impl HwmonRoot {
+    /// Create a root from a sysfs mount point (e.g. "/sys").
+    pub fn new(sysfs_root: impl Into<PathBuf>) -> Self {
+        Self {
+            sysfs_root: sysfs_root.into(),
+        }
+    }
+
+    /// The sysfs mount point this root was created from.
+    pub fn sysfs_root(&self) -> &Path {
+        &self.sysfs_root
+    }
+
+    /// The `class/hwmon` directory containing `hwmonN` entries.
+    pub fn hwmon_dir(&self) -> PathBuf {
+        self.sysfs_root.join(HWMON_CLASS_DIR)
+    }
+}
+
+impl Default for HwmonRoot {
+    fn default() -> Self {
+        Self::new(DEFAULT_SYSFS_ROOT)
+    }
+}
 
 // ---------------------------------------------------------------------------
 // Data types
@@ -21,8 +68,11 @@ const HWMON_ROOT: &str = "/sys/class/hwmon";
 /// A discovered fan (PWM output + optional tachometer input).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fan {
-    /// Unique identifier, e.g. "hwmon3/pwm1"
+    /// Stable identifier derived from the chip name, device path and
+    /// channel, e.g. "nct6775@platform/nct6775.656/pwm1"
     pub id: String,
+    /// Boot-specific identifier, e.g. "hwmon3/pwm1"
+    pub legacy_id: String,
     /// Human-readable label if available
     pub label: Option<String>,
     /// Absolute path to the `pwmN` file
@@ -35,23 +85,48 @@ pub struct Fan {
     pub hwmon_name: String,
 }
 
+impl Fan {
+    /// Sensor id of the tachometer, e.g. "nct6775@platform/nct6775.656/fan1",
+    /// so fan speed can feed a curve like any other input.
+    pub fn tach_id(&self) -> Option<String> {
+        self.rpm_path.as_ref()?;
+        let (prefix, channel) = self.id.rsplit_once('/')?;
+        Some(format!("{prefix}/fan{}", channel.strip_prefix("pwm")?))
+    }
+
+    fn read_rpm(&self) -> Option<u32> {
+        self.rpm_path
+            .as_ref()
+            .and_then(|p| read_trimmed(p).and_then(|s| s.parse::<u32>().ok()))
+    }
+}
+
 /// A discovered temperature sensor.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TempSensor {
-    /// Unique identifier, e.g. "hwmon3/temp1"
+    /// Stable identifier, e.g. "k10temp@pci0000:00/0000:00:18.3/temp1"
     pub id: String,
+    /// Boot-specific identifier, e.g. "hwmon3/temp1"
+    pub legacy_id: String,
     /// Human-readable label if available
     pub label: Option<String>,
     /// Absolute path to the `tempN_input` file (millidegrees C)
     pub input_path: PathBuf,
     /// Name of the parent hwmon device
     pub hwmon_name: String,
+    /// Kernel-provided critical threshold (`tempN_crit`) in °C
+    pub crit_c: Option<f64>,
+    /// Kernel-provided maximum threshold (`tempN_max`) in °C
+    pub max_c: Option<f64>,
 }
 
 /// Live readings for a fan.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FanStatus {
     pub id: String,
+    /// Boot-specific "hwmonN/pwmM" form of the id
+    #[serde(default)]
+    pub legacy_id: String,
     pub label: Option<String>,
     pub hwmon_name: String,
     /// Current PWM value 0-255
@@ -60,55 +135,100 @@ pub struct FanStatus {
     pub pwm_enable: Option<u8>,
     /// Current fan speed in RPM
     pub rpm: Option<u32>,
+    /// PWM the daemon's control loop is aiming for, if it controls this fan
+    #[serde(default)]
+    pub target_pwm: Option<u8>,
+    /// PWM the daemon last wrote after ramp limiting
+    #[serde(default)]
+    pub applied_pwm: Option<u8>,
+    /// The curve input that set the target on the last tick, e.g.
+    /// "cpu @ k10temp/temp1", or "average of N inputs" when an averaged
+    /// multi-curve assignment has no single winner
+    #[serde(default)]
+    pub active_input: Option<String>,
+    /// P/I/D contributions on the last tick, for PID assignments
+    #[serde(default)]
+    pub pid: Option<PidTerms>,
+    /// Driven but reading 0 RPM for longer than the stall grace period
+    #[serde(default)]
+    pub stalled: bool,
+    /// Why the daemon is overriding this fan's assignment, if it is
+    /// (e.g. "failsafe: full speed")
+    #[serde(default)]
+    pub forced: Option<String>,
+    /// Progress of a running calibration, e.g. "finding stop PWM (96)"
+    #[serde(default)]
+    pub calibrating: Option<String>,
+    /// Stored calibration result
+    #[serde(default)]
+    pub calibration: Option<FanCalibration>,
+    /// Matched by an ignore rule; clients leave it out unless asked
+    #[serde(default)]
+    pub hidden: bool,
 }
 
 /// Live reading for a temperature sensor.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TempStatus {
     pub id: String,
+    /// Boot-specific "hwmonN/tempM" form of the id
+    #[serde(default)]
+    pub legacy_id: String,
     pub label: Option<String>,
     pub hwmon_name: String,
     /// Temperature in degrees Celsius
     pub temp_c: Option<f64>,
+    /// Smoothed temperature as fed to a curve, if an assignment filters
+    /// this sensor
+    #[serde(default)]
+    pub filtered_c: Option<f64>,
+    /// Kernel-provided critical threshold in °C
+    #[serde(default)]
+    pub crit_c: Option<f64>,
+    /// Kernel-provided maximum threshold in °C
+    #[serde(default)]
+    pub max_c: Option<f64>,
+    /// Unit of `temp_c` and `filtered_c`; only load sensors, fan
+    /// tachometers and virtual sensors built on them aren't °C
+    #[serde(default, skip_serializing_if = "InputUnit::is_celsius")]
+    pub unit: InputUnit,
+    /// Matched by an ignore rule; clients leave it out unless asked
+    #[serde(default)]
+    pub hidden: bool,
 }
 
 // ---------------------------------------------------------------------------
 // Discovery
 // ---------------------------------------------------------------------------
 
-/// Scan `/sys/class/hwmon` and return all discovered fans.
-pub fn discover_fans() -> io::Result<Vec<Fan>> {
+/// Scan the hwmon class directory under `root` and return all discovered fans.
+pub fn discover_fans(root: &HwmonRoot) -> io::Result<Vec<Fan>> {
     let mut fans = Vec::new();
 
-    for entry in fs::read_dir(HWMON_ROOT)? {
-        let entry = entry?;
-        let hwmon_dir = entry.path();
-        let hwmon_name = read_trimmed(&hwmon_dir.join("name")).unwrap_or_default();
-        let hwmon_basename = entry.file_name().to_string_lossy().to_string();
-
+    for chip in list_chips(root)? {
         // Look for pwmN files (N = 1, 2, 3, ...)
         for n in 1..=16 {
-            let pwm_path = hwmon_dir.join(format!("pwm{n}"));
-            let pwm_enable_path = hwmon_dir.join(format!("pwm{n}_enable"));
+            let pwm_path = chip.dir.join(format!("pwm{n}"));
+            let pwm_enable_path = chip.dir.join(format!("pwm{n}_enable"));
 
             if !pwm_path.exists() {
                 break;
             }
 
-            let id = format!("{hwmon_basename}/pwm{n}");
-            let label = read_trimmed(&hwmon_dir.join(format!("fan{n}_label")));
+            let label = read_trimmed(&chip.dir.join(format!("fan{n}_label")));
             let rpm_path = {
-                let p = hwmon_dir.join(format!("fan{n}_input"));
+                let p = chip.dir.join(format!("fan{n}_input"));
                 if p.exists() { Some(p) } else { None }
             };
 
             fans.push(Fan {
-                id,
+                id: format!("{}/pwm{n}", chip.stable_prefix),
+                legacy_id: format!("{}/pwm{n}", chip.basename),
                 label,
                 pwm_path,
                 pwm_enable_path,
                 rpm_path,
-                hwmon_name: hwmon_name.clone(),
+                hwmon_name: chip.name.clone(),
             });
         }
     }
@@ -117,31 +237,34 @@ pub fn discover_fans() -> io::Result<Vec<Fan>> {
     Ok(fans)
 }
 
-/// Scan `/sys/class/hwmon` and return all discovered temperature sensors.
-pub fn discover_temp_sensors() -> io::Result<Vec<TempSensor>> {
+/// Scan the hwmon class directory under `root` and return all discovered
+/// temperature sensors.
+pub fn discover_temp_sensors(root: &HwmonRoot) -> io::Result<Vec<TempSensor>> {
     let mut sensors = Vec::new();
 
-    for entry in fs::read_dir(HWMON_ROOT)? {
-        let entry = entry?;
-        let hwmon_dir = entry.path();
-        let hwmon_name = read_trimmed(&hwmon_dir.join("name")).unwrap_or_default();
-        let hwmon_basename = entry.file_name().to_string_lossy().to_string();
-
+    for chip in list_chips(root)? {
         for n in 1..=32 {
-            let input_path = hwmon_dir.join(format!("temp{n}_input"));
+            let input_path = chip.dir.join(format!("temp{n}_input"));
 
             if !input_path.exists() {
                 break;
             }
 
-            let id = format!("{hwmon_basename}/temp{n}");
-            let label = read_trimmed(&hwmon_dir.join(format!("temp{n}_label")));
+            let label = read_trimmed(&chip.dir.join(format!("temp{n}_label")));
+            let threshold = |attr: &str| {
+                read_millidegrees(&chip.dir.join(format!("temp{n}_{attr}")))
+                    // Some drivers report 0 for "not set"
+                    .filter(|&c| c > 0.0)
+            };
 
             sensors.push(TempSensor {
-                id,
+                id: format!("{}/temp{n}", chip.stable_prefix),
+                legacy_id: format!("{}/temp{n}", chip.basename),
                 label,
                 input_path,
-                hwmon_name: hwmon_name.clone(),
+                hwmon_name: chip.name.clone(),
+                crit_c: threshold("crit"),
+                max_c: threshold("max"),
             });
         }
     }
@@ -150,6 +273,28 @@ pub fn discover_temp_sensors() -> io::Result<Vec<TempSensor>> {
     Ok(sensors)
 }
 
+/// Find a fan by its stable id or its legacy `hwmonN/pwmM` id.
+pub fn find_fan<'a>(fans: &'a [Fan], id: &str) -> Option<&'a Fan> {
+    fans.iter()
+        .find(|f| f.id == id)
+        .or_else(|| fans.iter().find(|f| f.legacy_id == id))
+}
+
+/// Find a temp sensor by its stable id or its legacy `hwmonN/tempM` id.
+pub fn find_temp_sensor<'a>(sensors: &'a [TempSensor], id: &str) -> Option<&'a TempSensor> {
+    sensors
+        .iter()
+        .find(|s| s.id == id)
+        .or_else(|| sensors.iter().find(|s| s.legacy_id == id))
+}
+
+/// Returns true if `id` has the boot-specific `hwmonN/...` form.
+pub fn is_legacy_id(id: &str) -> bool {
+    id.split_once('/')
+        .and_then(|(chip, _)| chip.strip_prefix("hwmon"))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
 // ---------------------------------------------------------------------------
 // Reading
 // ---------------------------------------------------------------------------
@@ -160,34 +305,51 @@ pub fn read_fan_status(fan: &Fan) -> FanStatus {
         .and_then(|s| s.parse::<u8>().ok());
     let pwm_enable = read_trimmed(&fan.pwm_enable_path)
         .and_then(|s| s.parse::<u8>().ok());
-    let rpm = fan.rpm_path.as_ref().and_then(|p| {
-        read_trimmed(p).and_then(|s| s.parse::<u32>().ok())
-    });
+    let rpm = fan.read_rpm();
 
     FanStatus {
         id: fan.id.clone(),
+        legacy_id: fan.legacy_id.clone(),
         label: fan.label.clone(),
         hwmon_name: fan.hwmon_name.clone(),
         pwm,
         pwm_enable,
         rpm,
+        target_pwm: None,
+        applied_pwm: None,
+        active_input: None,
+        pid: None,
+        stalled: false,
+        forced: None,
+        calibrating: None,
+        calibration: None,
+        hidden: false,
     }
 }
 
 /// Read current status for a temperature sensor.
 pub fn read_temp_status(sensor: &TempSensor) -> TempStatus {
-    let temp_c = read_trimmed(&sensor.input_path)
-        .and_then(|s| s.parse::<i64>().ok())
-        .map(|millic| millic as f64 / 1000.0);
-
     TempStatus {
         id: sensor.id.clone(),
+        legacy_id: sensor.legacy_id.clone(),
         label: sensor.label.clone(),
         hwmon_name: sensor.hwmon_name.clone(),
-        temp_c,
+        temp_c: read_millidegrees(&sensor.input_path),
+        filtered_c: None,
+        crit_c: sensor.crit_c,
+        max_c: sensor.max_c,
+        unit: InputUnit::Celsius,
+        hidden: false,
     }
 }
 
+/// Read a millidegree attribute as °C.
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    read_trimmed(path)
+        .and_then(|s| s.parse::<i64>().ok())
+        .map(|millic| millic as f64 / 1000.0)
+}
+
 /// Read all fan statuses.
 pub fn read_all_fan_statuses(fans: &[Fan]) -> Vec<FanStatus> {
     fans.iter().map(read_fan_status).collect()
@@ -198,9 +360,44 @@ pub fn read_all_temp_statuses(sensors: &[TempSensor]) -> Vec<TempStatus> {
     sensors.iter().map(read_temp_status).collect()
 }
 
-/// Build a map of sensor id -> current temp for quick lookup by the curve engine.
-pub fn read_temp_map(sensors: &[TempSensor]) -> HashMap<String, f64> {
-    let mut map = HashMap::new();
+/// Read fan tachometers as curve inputs, keyed by [`Fan::tach_id`].
+pub fn read_tach_map(fans: &[Fan]) -> HashMap<String, f64> {
+    fans.iter()
+        .filter_map(|f| Some((f.tach_id()?, f64::from(f.read_rpm()?))))
+        .collect()
+}
+
+/// Statuses for fan tachometers from an already-read tach map.
+pub fn tach_statuses(fans: &[Fan], tach_map: &HashMap<String, f64>) -> Vec<TempStatus> {
+    fans.iter()
+        .filter_map(|f| {
+            let id = f.tach_id()?;
+            let legacy_id = f.legacy_id.replace("/pwm", "/fan");
+            Some(TempStatus {
+                temp_c: tach_map.get(&id).copied(),
+                id,
+                legacy_id,
+                label: f.label.clone(),
+                hwmon_name: f.hwmon_name.clone(),
+                filtered_c: None,
+                crit_c: None,
+                max_c: None,
+                unit: InputUnit::Rpm,
+                hidden: false,
+            })
+        })
+        .collect()
+}
+
+/// Build a map of sensor id -> current reading for quick lookup by the
+/// curve engine.
+///
+/// `extra` holds readings from other sources already taken this tick, such
+/// as load sensors (see [`crate::sysload::LoadSensors`]) and fan
+/// tachometers. Virtual sensors are added on top by
+/// [`crate::config::Config::evaluate_virtual_sensors`].
+pub fn read_temp_map(sensors: &[TempSensor], extra: &HashMap<String, f64>) -> HashMap<String, f64> {
+    let mut map = extra.clone();
     for s in sensors {
         if let Some(t) = read_temp_status(s).temp_c {
             map.insert(s.id.clone(), t);
@@ -251,6 +448,245 @@ pub fn restore_all_automatic(fans: &[Fan]) {
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// A `hwmonN` directory along with the attributes used to identify it.
+struct Chip {
+    dir: PathBuf,
+    /// Contents of the `name` attribute
+    name: String,
+    /// Directory name, e.g. "hwmon3"
+    basename: String,
+    /// Where the `hwmonN` directory itself sits under `devices`, e.g.
+    /// "virtual/thermal/thermal_zone0"
+    location: Option<String>,
+    /// "name@device" (or just "name" for chips without a device link)
+    stable_prefix: String,
+}
+
+fn list_chips(root: &HwmonRoot) -> io::Result<Vec<Chip>> {
+    let devices_dir = fs::canonicalize(root.sysfs_root().join("devices")).ok();
+    let relative = |path: &Path| {
+        devices_dir
+            .as_deref()
+            .and_then(|d| path.strip_prefix(d).ok())
+            .map(|p| p.to_string_lossy().to_string())
+    };
+    let mut chips = Vec::new();
+
+    for entry in fs::read_dir(root.hwmon_dir())? {
+        let entry = entry?;
+        let dir = entry.path();
+        let name = read_trimmed(&dir.join("name")).unwrap_or_default();
+        let basename = entry.file_name().to_string_lossy().to_string();
+
+        // The `device` symlink resolves to the PCI/platform device the chip
+        // belongs to, which unlike the hwmonN index is stable across boots.
+        let device = fs::canonicalize(dir.join("device")).ok().map(|dev| {
+            relative(&dev)
+                .unwrap_or_else(|| dev.to_string_lossy().to_string())
+                .trim_start_matches('/')
+                .to_string()
+        });
+        let stable_prefix = match device {
+            Some(device) if !device.is_empty() => format!("{name}@{device}"),
+            _ => name.clone(),
+        };
+        // Chips without a device link still sit somewhere under `devices`,
+        // either directly or in a `hwmon` subdirectory of their parent
+        let location = fs::canonicalize(&dir).ok().and_then(|path| {
+            let parent = path.parent()?;
+            let parent = match parent.file_name() {
+                Some(dir) if dir == "hwmon" => parent.parent()?,
+                _ => parent,
+            };
+            relative(parent).filter(|p| !p.is_empty())
+        });
+
+        chips.push(Chip {
+            dir,
+            name,
+            basename,
+            location,
+            stable_prefix,
+        });
+    }
+
+    // Chips sharing a name without a device link would collide; tell them
+    // apart by their place in the device tree, and only by the hwmonN
+    // index, which can change between boots, as a last resort.
+    for chip in colliding(&mut chips) {
+        if let Some(location) = &chip.location {
+            chip.stable_prefix = format!("{}@{location}", chip.name);
+        }
+    }
+    for chip in colliding(&mut chips) {
+        log::warn!(
+            "Several hwmon chips are called '{}'; ids of {} depend on its \
+             index and may change between boots",
+            chip.stable_prefix,
+            chip.basename
+        );
+        chip.stable_prefix = format!("{}#{}", chip.stable_prefix, chip.basename);
+    }
+
+    Ok(chips)
+}
+
+/// Chips whose stable prefix isn't unique.
+fn colliding(chips: &mut [Chip]) -> impl Iterator<Item = &mut Chip> {
+    let mut seen = HashMap::new();
+    for chip in chips.iter() {
+        *seen.entry(chip.stable_prefix.clone()).or_insert(0) += 1;
+    }
+    chips
+        .iter_mut()
+        .filter(move |chip| seen[&chip.stable_prefix] > 1)
+}
+
 fn read_trimmed(path: &Path) -> Option<String> {
     fs::read_to_string(path).ok().map(|s| s.trim().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeSysfs;
+
+    #[test]
+    fn test_discover_fake_tree() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 128, Some(900)).unwrap();
+        chip.add_fan(2, 255, None).unwrap();
+        chip.add_temp(1, 45_500, Some("SYSTIN")).unwrap();
+
+        let fans = discover_fans(&sysfs.root()).unwrap();
+        assert_eq!(fans.len(), 2);
+        assert_eq!(fans[0].id, "nct6775/pwm1");
+        assert_eq!(fans[0].legacy_id, "hwmon0/pwm1");
+        assert_eq!(fans[0].hwmon_name, "nct6775");
+        assert!(fans[1].rpm_path.is_none());
+        assert_eq!(fans[0].tach_id().as_deref(), Some("nct6775/fan1"));
+        assert_eq!(fans[1].tach_id(), None);
+        let tach = read_tach_map(&fans);
+        assert_eq!(tach.get("nct6775/fan1"), Some(&900.0));
+        assert_eq!(tach_statuses(&fans, &tach)[0].unit, InputUnit::Rpm);
+
+        let sensors = discover_temp_sensors(&sysfs.root()).unwrap();
+        assert_eq!(sensors.len(), 1);
+        assert_eq!(sensors[0].label.as_deref(), Some("SYSTIN"));
+        assert_eq!(read_temp_status(&sensors[0]).temp_c, Some(45.5));
+        assert_eq!(sensors[0].crit_c, None);
+    }
+
+    #[test]
+    fn test_discover_thresholds() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nvme").unwrap();
+        chip.add_temp(1, 40_000, Some("Composite")).unwrap();
+        chip.write_attr("temp1_crit", 84_850).unwrap();
+        chip.write_attr("temp1_max", 0).unwrap();
+
+        let sensors = discover_temp_sensors(&sysfs.root()).unwrap();
+        assert_eq!(sensors[0].crit_c, Some(84.85));
+        // 0 means "not set"
+        assert_eq!(sensors[0].max_c, None);
+    }
+
+    #[test]
+    fn test_write_fake_pwm() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs
+            .add_chip("it8688")
+            .unwrap()
+            .add_fan(1, 0, Some(0))
+            .unwrap();
+
+        let fans = discover_fans(&sysfs.root()).unwrap();
+        set_manual_pwm(&fans[0], 200).unwrap();
+        let status = read_fan_status(&fans[0]);
+        assert_eq!(status.pwm, Some(200));
+        assert_eq!(status.pwm_enable, Some(1));
+
+        restore_automatic(&fans[0]).unwrap();
+        assert_eq!(read_fan_status(&fans[0]).pwm_enable, Some(2));
+    }
+
+    #[test]
+    fn test_stable_ids_survive_renumbering() {
+        // The same chips, enumerated in a different order on another boot
+        let build = |order: [usize; 4]| {
+            let sysfs = FakeSysfs::new().unwrap();
+            for i in order {
+                match i {
+                    0 => {
+                        let k10 = sysfs.add_chip("k10temp").unwrap();
+                        k10.link_device("pci0000:00/0000:00:18.3").unwrap();
+                        k10.add_temp(1, 50_000, Some("Tctl")).unwrap();
+                    }
+                    1 | 2 => {
+                        let zone = format!("virtual/thermal/thermal_zone{}", i - 1);
+                        let acpi = sysfs.add_chip_in("acpitz", &zone).unwrap();
+                        acpi.add_temp(1, 30_000, None).unwrap();
+                    }
+                    _ => {
+                        let nct = sysfs.add_chip("nct6775").unwrap();
+                        nct.add_fan(1, 128, Some(900)).unwrap();
+                    }
+                }
+            }
+            let sensors = discover_temp_sensors(&sysfs.root()).unwrap();
+            let fans = discover_fans(&sysfs.root()).unwrap();
+            let ids: Vec<(String, String)> = sensors
+                .iter()
+                .map(|s| (s.id.clone(), s.legacy_id.clone()))
+                .chain(fans.iter().map(|f| (f.id.clone(), f.legacy_id.clone())))
+                .collect();
+            ids
+        };
+
+        let first = build([0, 1, 2, 3]);
+        let second = build([3, 2, 1, 0]);
+        let stable = |ids: &[(String, String)]| -> Vec<String> {
+            ids.iter().map(|(id, _)| id.clone()).collect()
+        };
+        assert_eq!(
+            stable(&first),
+            [
+                "acpitz@virtual/thermal/thermal_zone0/temp1",
+                "acpitz@virtual/thermal/thermal_zone1/temp1",
+                "k10temp@pci0000:00/0000:00:18.3/temp1",
+                "nct6775/pwm1",
+            ]
+        );
+        assert_eq!(stable(&first), stable(&second));
+        // Only the boot-specific ids moved
+        assert_eq!(first[2].1, "hwmon0/temp1");
+        assert_eq!(second[2].1, "hwmon3/temp1");
+    }
+
+    #[test]
+    fn test_indistinguishable_chips_fall_back_to_index() {
+        let sysfs = FakeSysfs::new().unwrap();
+        sysfs
+            .add_chip("acpitz")
+            .unwrap()
+            .add_temp(1, 30_000, None)
+            .unwrap();
+        sysfs
+            .add_chip("acpitz")
+            .unwrap()
+            .add_temp(1, 35_000, None)
+            .unwrap();
+
+        let sensors = discover_temp_sensors(&sysfs.root()).unwrap();
+        assert_eq!(sensors[0].id, "acpitz#hwmon0/temp1");
+        assert_eq!(sensors[1].id, "acpitz#hwmon1/temp1");
+    }
+
+    #[test]
+    fn test_is_legacy_id() {
+        assert!(is_legacy_id("hwmon3/pwm1"));
+        assert!(!is_legacy_id("nct6775@platform/nct6775.656/pwm1"));
+        assert!(!is_legacy_id("hwmon/pwm1"));
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Hiding bogus sensors and unusable fans.
+//!
+//! Boards often expose sensors stuck at -128°C or 127°C and PWM channels
+//! that aren't wired to anything. `[[ignore]]` rules in the config match
+//! those by id, hwmon name glob, label regex or a reading outside a
+//! plausible range. Matches stay discovered, so they can still be revealed
+//! and assigned, but are marked hidden in status and left out of metrics,
+//! and hidden sensors only trip the failsafe if an assignment reads them.
+
+use crate::curve::InputUnit;
+use crate::hwmon::{FanStatus, TempStatus};
+use crate::labels::glob_match;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// One `[[ignore]]` entry. Every condition given must match; entries with
+/// none are rejected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IgnoreRule {
+    /// Fan or sensor id, stable or legacy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Glob on the hwmon name, e.g. "acpitz" or "nvme*"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chip: Option<String>,
+
+    /// Regular expression searched for in the label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Only sensors reading in this unit. Rules with a range and no unit
+    /// only apply to temperatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<InputUnit>,
+
+    /// Lowest plausible reading. Range and unit conditions only apply to
+    /// sensors, so a rule with one never hides a fan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    /// Highest plausible reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+impl IgnoreRule {
+    fn has_range(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+}
+
+/// Rules from the config with their label patterns compiled.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<(IgnoreRule, Option<Regex>)>,
+}
+
+impl IgnoreRules {
+    /// Check and compile the config's rules.
+    pub fn new(rules: &[IgnoreRule]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        for (i, rule) in rules.iter().enumerate() {
+            let n = i + 1;
+            if *rule == IgnoreRule::default() {
+                return Err(format!(
+                    "Ignore rule {n} needs at least one of id, chip, label, unit, min or max"
+                ));
+            }
+            if let Some((min, max)) = rule.min.zip(rule.max).filter(|(min, max)| min > max) {
+                return Err(format!("Ignore rule {n} has min {min} above max {max}"));
+            }
+            let label = match &rule.label {
+                Some(pattern) => Some(
+                    Regex::new(pattern)
+                        .map_err(|e| format!("Ignore rule {n} has a bad label pattern: {e}"))?,
+                ),
+                None => None,
+            };
+            compiled.push((rule.clone(), label));
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether a fan should be hidden.
+    pub fn hides_fan(&self, fan: &FanStatus) -> bool {
+        let ids = [fan.id.as_str(), fan.legacy_id.as_str()];
+        self.rules.iter().any(|(rule, label)| {
+            !rule.has_range()
+                && rule.unit.is_none()
+                && matches(
+                    rule,
+                    label.as_ref(),
+                    &ids,
+                    &fan.hwmon_name,
+                    fan.label.as_deref(),
+                )
+        })
+    }
+
+    /// Whether a sensor should be hidden, judging its range by `reading`.
+    /// Sensors without a reading are never out of range.
+    pub fn hides_sensor(
+        &self,
+        id: &str,
+        legacy_id: &str,
+        chip: &str,
+        label: Option<&str>,
+        reading: Option<f64>,
+        unit: InputUnit,
+    ) -> bool {
+        self.rules.iter().any(|(rule, pattern)| {
+            let in_range = |value: f64| {
+                rule.min.is_none_or(|min| value >= min) && rule.max.is_none_or(|max| value <= max)
+            };
+            let unit_matches = match rule.unit {
+                Some(u) => u == unit,
+                None => !rule.has_range() || unit == InputUnit::Celsius,
+            };
+            let range_matches = !rule.has_range() || reading.is_some_and(|r| !in_range(r));
+            unit_matches
+                && range_matches
+                && matches(rule, pattern.as_ref(), &[id, legacy_id], chip, label)
+        })
+    }
+
+    /// Set `hidden` on every status the rules match.
+    pub fn mark(&self, fans: &mut [FanStatus], temps: &mut [TempStatus]) {
+        for fan in fans {
+            fan.hidden = self.hides_fan(fan);
+        }
+        for temp in temps {
+            temp.hidden = self.hides_sensor(
+                &temp.id,
+                &temp.legacy_id,
+                &temp.hwmon_name,
+                temp.label.as_deref(),
+                temp.temp_c,
+                temp.unit,
+            );
+        }
+    }
+}
+
+/// The id, chip and label conditions of a rule; absent ones match anything.
+fn matches(
+    rule: &IgnoreRule,
+    pattern: Option<&Regex>,
+    ids: &[&str],
+    chip: &str,
+    label: Option<&str>,
+) -> bool {
+    rule.id.as_deref().is_none_or(|id| ids.contains(&id))
+        && rule
+            .chip
+            .as_deref()
+            .is_none_or(|glob| glob_match(glob, chip))
+        && pattern.is_none_or(|re| label.is_some_and(|l| re.is_match(l)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp(id: &str, chip: &str, label: Option<&str>, reading: Option<f64>) -> TempStatus {
+        reading_in(id, chip, label, reading, InputUnit::Celsius)
+    }
+
+    fn reading_in(
+        id: &str,
+        chip: &str,
+        label: Option<&str>,
+        reading: Option<f64>,
+        unit: InputUnit,
+    ) -> TempStatus {
+        TempStatus {
+            id: id.to_string(),
+            legacy_id: format!("hwmon0/{}", id.rsplit('/').next().unwrap()),
+            label: label.map(str::to_string),
+            hwmon_name: chip.to_string(),
+            temp_c: reading,
+            filtered_c: None,
+            crit_c: None,
+            max_c: None,
+            unit,
+            hidden: false,
+        }
+    }
+
+    fn rules(toml: &str) -> Result<IgnoreRules, String> {
+        #[derive(Deserialize)]
+        struct File {
+            ignore: Vec<IgnoreRule>,
+        }
+        IgnoreRules::new(&toml::from_str::<File>(toml).unwrap().ignore)
+    }
+
+    #[test]
+    fn test_hide_sensors() {
+        let rules = rules(
+            r#"
+            [[ignore]]
+            id = "hwmon0/temp4"
+
+            [[ignore]]
+            chip = "acpi*"
+
+            [[ignore]]
+            chip = "nct6775"
+            label = "^AUXTIN[0-9]"
+
+            [[ignore]]
+            min = -40.0
+            max = 120.0
+
+            [[ignore]]
+            unit = "rpm"
+            max = 10000.0
+            "#,
+        )
+        .unwrap();
+
+        let mut temps = vec![
+            temp("nct6775/temp1", "nct6775", Some("SYSTIN"), Some(35.0)),
+            temp("nct6775/temp2", "nct6775", Some("AUXTIN1"), Some(35.0)),
+            temp("nct6775/temp3", "nct6775", Some("CPUTIN"), Some(-128.0)),
+            temp("nct6775/temp4", "nct6775", None, Some(35.0)),
+            temp("acpitz/temp1", "acpitz", None, Some(27.8)),
+            temp("k10temp/temp1", "k10temp", Some("AUXTIN1"), None),
+            reading_in("nct6775/fan1", "nct6775", None, Some(900.0), InputUnit::Rpm),
+            reading_in(
+                "nct6775/fan2",
+                "nct6775",
+                None,
+                Some(65535.0),
+                InputUnit::Rpm,
+            ),
+        ];
+        rules.mark(&mut [], &mut temps);
+        let hidden: Vec<bool> = temps.iter().map(|t| t.hidden).collect();
+        assert_eq!(hidden, [false, true, true, true, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_range_never_hides_fans() {
+        let range = rules("[[ignore]]\nchip = \"nct6775\"\nmax = 100.0").unwrap();
+        let fan = FanStatus {
+            id: "nct6775/pwm3".to_string(),
+            legacy_id: "hwmon0/pwm3".to_string(),
+            label: None,
+            hwmon_name: "nct6775".to_string(),
+            pwm: Some(0),
+            pwm_enable: Some(2),
+            rpm: Some(0),
+            target_pwm: None,
+            applied_pwm: None,
+            active_input: None,
+            pid: None,
+            stalled: false,
+            forced: None,
+            calibrating: None,
+            calibration: None,
+            hidden: false,
+        };
+        assert!(!range.hides_fan(&fan));
+        let by_id = rules("[[ignore]]\nid = \"nct6775/pwm3\"").unwrap();
+        assert!(by_id.hides_fan(&fan));
+    }
+
+    #[test]
+    fn test_bad_rules() {
+        assert!(rules("[[ignore]]").unwrap_err().contains("at least one"));
+        assert!(
+            rules("[[ignore]]\nlabel = \"(\"")
+                .unwrap_err()
+                .contains("bad label")
+        );
+        assert!(
+            rules("[[ignore]]\nmin = 5.0\nmax = 1.0")
+                .unwrap_err()
+                .contains("above max")
+        );
+    }
+}
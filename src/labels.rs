@@ -0,0 +1,396 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Friendly names for fans and sensors.
+//!
+//! Few boards provide `fanN_label` or `tempN_label`, so names come from two
+//! more places. lm-sensors config files (`/etc/sensors3.conf` and
+//! `/etc/sensors.d`) can be imported on start: their `label` statements
+//! name channels the way `sensors` shows them and their `ignore`
+//! statements drop channels from discovery. On top of that the `[labels]`
+//! config section maps ids to names of the user's choosing; those win over
+//! everything and can be changed from clients at runtime.
+
+use crate::hwmon::{Fan, TempSensor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Main lm-sensors config file.
+pub const DEFAULT_SENSORS_CONF: &str = "/etc/sensors3.conf";
+
+/// Directory of extra lm-sensors config files.
+pub const DEFAULT_SENSORS_D: &str = "/etc/sensors.d";
+
+/// lm-sensors import settings (`[lm_sensors]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LmSensorsConfig {
+    /// Apply `label` and `ignore` statements from lm-sensors config files.
+    /// Read on start only.
+    #[serde(default)]
+    pub import: bool,
+
+    /// Files and directories to read, in order. A directory contributes
+    /// every file in it, sorted by name; later statements win.
+    #[serde(default = "default_paths")]
+    pub paths: Vec<String>,
+}
+
+impl Default for LmSensorsConfig {
+    fn default() -> Self {
+        Self {
+            import: false,
+            paths: default_paths(),
+        }
+    }
+}
+
+/// `label` and `ignore` statements parsed from lm-sensors config files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensorsConf {
+    sections: Vec<ChipSection>,
+}
+
+/// The statements under one `chip` line.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ChipSection {
+    /// Chip name patterns, e.g. "nct6775-*" or "k10temp-pci-00c3"
+    patterns: Vec<String>,
+    /// (feature, label) pairs, e.g. ("fan1", "CPU fan")
+    labels: Vec<(String, String)>,
+    /// Features to hide, e.g. "temp3"
+    ignores: HashSet<String>,
+}
+
+impl ChipSection {
+    /// lm-sensors names chips "prefix-bus-address". Only the prefix is
+    /// compared, against the hwmon name, so a pattern covers every chip of
+    /// that type whatever bus or address it names.
+    fn matches(&self, chip: &str) -> bool {
+        self.patterns.iter().any(|p| {
+            let prefix = p.split('-').next().unwrap_or(p);
+            glob_match(prefix, chip)
+        })
+    }
+}
+
+impl SensorsConf {
+    /// Parse the text of one config file and append its statements.
+    /// Statements other than `chip`, `label` and `ignore` are skipped, as
+    /// are `label` and `ignore` before the first `chip`.
+    pub fn parse(&mut self, text: &str) {
+        let mut current: Option<ChipSection> = None;
+        for line in logical_lines(text) {
+            let words = tokenize(&line);
+            let Some((keyword, args)) = words.split_first() else {
+                continue;
+            };
+            match (keyword.as_str(), args) {
+                ("chip", patterns) if !patterns.is_empty() => {
+                    self.sections.extend(current.take());
+                    current = Some(ChipSection {
+                        patterns: patterns.to_vec(),
+                        ..ChipSection::default()
+                    });
+                }
+                ("label", [feature, label, ..]) => {
+                    if let Some(section) = &mut current {
+                        section.labels.push((feature.clone(), label.clone()));
+                    }
+                }
+                ("ignore", [feature, ..]) => {
+                    if let Some(section) = &mut current {
+                        section.ignores.insert(feature.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.sections.extend(current);
+    }
+
+    /// Read every file in `paths` (see [`LmSensorsConfig::paths`]).
+    /// Missing paths are skipped; unreadable ones are logged and skipped.
+    pub fn load(paths: &[String]) -> Self {
+        let mut conf = Self::default();
+        for path in paths {
+            let path = Path::new(path);
+            let files = if path.is_dir() {
+                match config_files(path) {
+                    Ok(files) => files,
+                    Err(e) => {
+                        log::warn!("Could not list {}: {e}", path.display());
+                        continue;
+                    }
+                }
+            } else {
+                vec![path.to_path_buf()]
+            };
+            for file in files {
+                match fs::read_to_string(&file) {
+                    Ok(text) => {
+                        log::info!("Imported lm-sensors config {}", file.display());
+                        conf.parse(&text);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => log::warn!("Could not read {}: {e}", file.display()),
+                }
+            }
+        }
+        conf
+    }
+
+    /// Label for a feature (e.g. "temp1") of a chip, by hwmon name.
+    pub fn label(&self, chip: &str, feature: &str) -> Option<&str> {
+        self.sections
+            .iter()
+            .filter(|s| s.matches(chip))
+            .flat_map(|s| s.labels.iter())
+            .filter(|(f, _)| f == feature)
+            .map(|(_, label)| label.as_str())
+            .next_back()
+    }
+
+    /// Whether a feature of a chip is ignored.
+    pub fn is_ignored(&self, chip: &str, feature: &str) -> bool {
+        self.sections
+            .iter()
+            .any(|s| s.matches(chip) && s.ignores.contains(feature))
+    }
+
+    /// Label discovered fans and sensors and drop ignored channels.
+    ///
+    /// Fans take the label of their `fanN` input. An ignored `fanN` only
+    /// detaches the tachometer, leaving `pwmN` controllable; an ignored
+    /// `tempN` is dropped altogether.
+    pub fn apply(&self, fans: &mut [Fan], sensors: &mut Vec<TempSensor>) {
+        for fan in fans {
+            let Some(feature) = channel(&fan.id)
+                .strip_prefix("pwm")
+                .map(|n| format!("fan{n}"))
+            else {
+                continue;
+            };
+            if let Some(label) = self.label(&fan.hwmon_name, &feature) {
+                fan.label = Some(label.to_string());
+            }
+            if fan.rpm_path.is_some() && self.is_ignored(&fan.hwmon_name, &feature) {
+                log::info!("Ignoring tachometer of {} per lm-sensors config", fan.id);
+                fan.rpm_path = None;
+            }
+        }
+        sensors.retain(|s| {
+            let ignored = self.is_ignored(&s.hwmon_name, channel(&s.id));
+            if ignored {
+                log::info!("Ignoring {} per lm-sensors config", s.id);
+            }
+            !ignored
+        });
+        for sensor in sensors {
+            if let Some(label) = self.label(&sensor.hwmon_name, channel(&sensor.id)) {
+                sensor.label = Some(label.to_string());
+            }
+        }
+    }
+}
+
+/// Match `text` against a shell-style pattern where `*` matches any run of
+/// characters and `?` any single one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it has consumed to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Last path component of an id, e.g. "temp1".
+fn channel(id: &str) -> &str {
+    id.rsplit('/').next().unwrap_or(id)
+}
+
+/// Config files in an lm-sensors config directory, skipping hidden files.
+fn config_files(dir: &Path) -> io::Result<Vec<std::path::PathBuf>> {
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Lines with comments removed and backslash continuations joined.
+fn logical_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pending = String::new();
+    for raw in text.lines() {
+        let line = strip_comment(raw);
+        match line.trim_end().strip_suffix('\\') {
+            Some(head) => {
+                pending.push_str(head);
+                pending.push(' ');
+            }
+            None => {
+                pending.push_str(line);
+                lines.push(std::mem::take(&mut pending));
+            }
+        }
+    }
+    if !pending.is_empty() {
+        lines.push(pending);
+    }
+    lines
+}
+
+/// Drop a `#` comment that isn't inside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Split a line into words, unquoting `"..."` strings.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut word = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => word.extend(chars.next()),
+                    _ => word.push(c),
+                }
+            }
+            words.push(word);
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    words
+}
+
+fn default_paths() -> Vec<String> {
+    vec![
+        DEFAULT_SENSORS_CONF.to_string(),
+        DEFAULT_SENSORS_D.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeSysfs;
+    use crate::hwmon;
+
+    const CONF: &str = r#"
+# Board fans
+chip "nct6775-*" "nct6776-isa-*"
+    label fan1 "CPU fan"    # on CPU_FAN
+    label fan2 "Rear \"exhaust\""
+    label temp1 "SYSTIN"
+    ignore temp3
+    set fan1_min 300
+
+chip "k10temp-pci-00c3"
+    label temp1 \
+        "CPU die"
+
+chip "nct6775-*"
+    label temp1 "Motherboard"
+"#;
+
+    #[test]
+    fn test_parse_sensors_conf() {
+        let mut conf = SensorsConf::default();
+        conf.parse(CONF);
+
+        assert_eq!(conf.label("nct6775", "fan1"), Some("CPU fan"));
+        assert_eq!(conf.label("nct6776", "fan2"), Some("Rear \"exhaust\""));
+        assert_eq!(conf.label("k10temp", "temp1"), Some("CPU die"));
+        // Later statements win
+        assert_eq!(conf.label("nct6775", "temp1"), Some("Motherboard"));
+        assert_eq!(conf.label("nct6776", "temp1"), Some("SYSTIN"));
+        assert_eq!(conf.label("it87", "fan1"), None);
+        assert!(conf.is_ignored("nct6775", "temp3"));
+        assert!(!conf.is_ignored("k10temp", "temp3"));
+    }
+
+    #[test]
+    fn test_apply() {
+        let sysfs = FakeSysfs::new().unwrap();
+        let chip = sysfs.add_chip("nct6775").unwrap();
+        chip.add_fan(1, 100, Some(900)).unwrap();
+        chip.add_fan(2, 100, Some(900)).unwrap();
+        chip.add_temp(1, 40_000, None).unwrap();
+        chip.add_temp(3, -128_000, None).unwrap();
+
+        let mut fans = hwmon::discover_fans(&sysfs.root()).unwrap();
+        let mut sensors = hwmon::discover_temp_sensors(&sysfs.root()).unwrap();
+        let mut conf = SensorsConf::default();
+        conf.parse("chip \"nct6775-*\"\nlabel fan1 \"CPU fan\"\nignore fan2\nignore temp3");
+        conf.apply(&mut fans, &mut sensors);
+
+        assert_eq!(fans[0].label.as_deref(), Some("CPU fan"));
+        assert!(fans[0].rpm_path.is_some());
+        assert!(fans[1].rpm_path.is_none());
+        assert_eq!(sensors.len(), 1);
+        assert!(sensors[0].id.ends_with("/temp1"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("nct*", "nct6775"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("k?0temp", "k10temp"));
+        assert!(glob_match("*temp*", "k10temp"));
+        assert!(!glob_match("nct*", "it87"));
+        assert!(!glob_match("k10temp", "k10temp2"));
+    }
+}
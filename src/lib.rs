@@ -1,7 +1,21 @@
 // Copyright (c) 2026 Pegasus Heavy Industries LLC
 // Licensed under the MIT License
 
+pub mod access;
+pub mod calibration;
 pub mod config;
 pub mod curve;
+pub mod failsafe;
+pub mod fixture;
+pub mod history;
 pub mod hwmon;
+pub mod ignore;
+pub mod labels;
+pub mod metrics;
+pub mod pid;
 pub mod protocol;
+pub mod schedule;
+pub mod smoothing;
+pub mod stall;
+pub mod sysload;
+pub mod trigger;
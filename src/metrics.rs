@@ -0,0 +1,413 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Prometheus metrics.
+//!
+//! With a `[daemon.metrics]` section the daemon answers `GET /metrics` on a
+//! local TCP address or a Unix socket with the current readings in the
+//! Prometheus text exposition format. [`render`] builds the page from a
+//! status snapshot and [`respond`] wraps it in just enough HTTP/1.1 for a
+//! scraper; there's no general-purpose HTTP server behind it.
+
+use crate::curve::InputUnit;
+use crate::failsafe::Failsafe;
+use crate::hwmon::{FanStatus, TempStatus};
+use crate::protocol::FanAssignmentInfo;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default address the exporter listens on.
+pub const DEFAULT_METRICS_LISTEN: &str = "127.0.0.1:9788";
+
+/// Prefix of a `listen` value naming a Unix socket.
+pub const UNIX_LISTEN_PREFIX: &str = "unix:";
+
+/// Path metrics are served on.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Metrics exporter settings (`[daemon.metrics]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricsConfig {
+    /// Where to serve metrics: an IP address and port such as
+    /// "127.0.0.1:9788", or "unix:/path" for a Unix socket. Read on start
+    /// only.
+    #[serde(default = "default_listen")]
+    pub listen: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            listen: DEFAULT_METRICS_LISTEN.to_string(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Parse `listen` into something to bind.
+    pub fn endpoint(&self) -> Result<MetricsEndpoint, String> {
+        if let Some(path) = self.listen.strip_prefix(UNIX_LISTEN_PREFIX) {
+            if path.is_empty() {
+                return Err("Metrics socket path is empty".to_string());
+            }
+            return Ok(MetricsEndpoint::Unix(PathBuf::from(path)));
+        }
+        self.listen.parse().map(MetricsEndpoint::Tcp).map_err(|_| {
+            format!(
+                "Bad metrics address '{}', expected ip:port or unix:/path",
+                self.listen
+            )
+        })
+    }
+}
+
+/// Where the exporter listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsEndpoint {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// Everything a scrape reports.
+pub struct MetricsSnapshot<'a> {
+    pub fans: &'a [FanStatus],
+    pub temps: &'a [TempStatus],
+    pub assignments: &'a [FanAssignmentInfo],
+    pub failsafe: &'a Failsafe,
+    /// How long the last control-loop tick took
+    pub tick: Option<Duration>,
+}
+
+/// Render a snapshot in the Prometheus text exposition format.
+///
+/// Fans are labelled `fan`, sensors `sensor`, both by id, alongside the
+/// hwmon `chip` name and the kernel `label` (empty if there isn't one).
+/// Fans and sensors hidden by ignore rules are left out.
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let mut out = Output::default();
+    let fans: Vec<&FanStatus> = snapshot.fans.iter().filter(|f| !f.hidden).collect();
+    let temps: Vec<&TempStatus> = snapshot.temps.iter().filter(|t| !t.hidden).collect();
+
+    out.family(
+        "fanctl_temperature_celsius",
+        "Temperature sensor reading in degrees Celsius.",
+    );
+    for temp in temps.iter().filter(|t| t.unit == InputUnit::Celsius) {
+        out.sample(&sensor_labels(temp, None), temp.temp_c);
+    }
+    out.family(
+        "fanctl_sensor_value",
+        "Reading of a sensor that isn't a temperature, in its own unit.",
+    );
+    for temp in temps.iter().filter(|t| t.unit != InputUnit::Celsius) {
+        let unit = temp.unit.to_string();
+        out.sample(&sensor_labels(temp, Some(&unit)), temp.temp_c);
+    }
+
+    let fan_gauges: [(&str, &str, FanReading); 5] = [
+        (
+            "fanctl_fan_pwm",
+            "PWM duty read back from the fan, 0-255.",
+            |f| f.pwm.map(f64::from),
+        ),
+        ("fanctl_fan_rpm", "Fan speed in RPM.", |f| {
+            f.rpm.map(f64::from)
+        }),
+        (
+            "fanctl_fan_pwm_enable",
+            "PWM enable mode: 0 off, 1 manual, 2 and up automatic.",
+            |f| f.pwm_enable.map(f64::from),
+        ),
+        (
+            "fanctl_fan_target_pwm",
+            "PWM the control loop is aiming for, 0-255.",
+            |f| f.target_pwm.map(f64::from),
+        ),
+        (
+            "fanctl_fan_applied_pwm",
+            "PWM the control loop last wrote after ramp limiting, 0-255.",
+            |f| f.applied_pwm.map(f64::from),
+        ),
+    ];
+    for (name, help, value) in fan_gauges {
+        out.family(name, help);
+        for fan in &fans {
+            out.sample(&fan_labels(fan, &[]), value(fan));
+        }
+    }
+
+    out.family(
+        "fanctl_fan_mode",
+        "Always 1; the mode label is how the fan is assigned to be controlled.",
+    );
+    for fan in &fans {
+        let mode = snapshot
+            .assignments
+            .iter()
+            .find(|a| a.fan_id == fan.id)
+            .map_or("auto", |a| a.assignment.mode());
+        out.sample(&fan_labels(fan, &[("mode", mode)]), Some(1.0));
+    }
+    out.family(
+        "fanctl_fan_forced",
+        "1 if the failsafe, stall fallback or a calibration is overriding the fan's assignment.",
+    );
+    for fan in &fans {
+        let forced = fan.forced.is_some() || fan.calibrating.is_some();
+        out.sample(&fan_labels(fan, &[]), Some(flag(forced)));
+    }
+    out.family(
+        "fanctl_fan_stalled",
+        "1 if the fan is driven but not spinning.",
+    );
+    for fan in &fans {
+        out.sample(&fan_labels(fan, &[]), Some(flag(fan.stalled)));
+    }
+
+    out.family(
+        "fanctl_failsafe_active",
+        "1 if any sensor has tripped the failsafe.",
+    );
+    out.sample(&[], Some(flag(snapshot.failsafe.is_active())));
+    out.family(
+        "fanctl_failsafe_tripped",
+        "Always 1; one series per sensor holding the failsafe, labelled with why.",
+    );
+    let mut tripped: Vec<_> = snapshot.failsafe.tripped().collect();
+    tripped.sort_by_key(|&(id, _)| id);
+    for (id, reason) in tripped {
+        out.sample(&[("sensor", id), ("reason", reason.kind())], Some(1.0));
+    }
+
+    out.family(
+        "fanctl_engine_tick_seconds",
+        "Time the last control-loop tick took.",
+    );
+    out.sample(&[], snapshot.tick.map(|d| d.as_secs_f64()));
+
+    out.text
+}
+
+/// Build the full HTTP response to a request whose head (request line and
+/// headers) is `head`. `body` is only called for a metrics request.
+pub fn respond(head: &str, body: impl FnOnce() -> String) -> String {
+    let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let path = target.split('?').next().unwrap_or("");
+
+    let (status, content) = match method {
+        "GET" | "HEAD" if path == METRICS_PATH => ("200 OK", body()),
+        "GET" | "HEAD" => ("404 Not Found", "Not found, try /metrics\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "Only GET is supported\n".to_string(),
+        ),
+    };
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        content.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&content);
+    }
+    response
+}
+
+/// Pulls one gauge's value out of a fan's status.
+type FanReading = fn(&FanStatus) -> Option<f64>;
+
+/// Text being rendered, tracking the current metric family.
+#[derive(Default)]
+struct Output {
+    text: String,
+    name: &'static str,
+}
+
+impl Output {
+    /// Start a metric family.
+    fn family(&mut self, name: &'static str, help: &str) {
+        self.name = name;
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} gauge");
+    }
+
+    /// Add a sample to the current family, skipping missing readings.
+    fn sample(&mut self, labels: &[(&str, &str)], value: Option<f64>) {
+        let Some(value) = value else {
+            return;
+        };
+        self.text.push_str(self.name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {value}");
+    }
+}
+
+fn sensor_labels<'a>(temp: &'a TempStatus, unit: Option<&'a str>) -> Vec<(&'a str, &'a str)> {
+    let mut labels = vec![
+        ("sensor", temp.id.as_str()),
+        ("chip", temp.hwmon_name.as_str()),
+        ("label", temp.label.as_deref().unwrap_or("")),
+    ];
+    if let Some(unit) = unit {
+        labels.push(("unit", unit));
+    }
+    labels
+}
+
+fn fan_labels<'a>(fan: &'a FanStatus, extra: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+    let mut labels = vec![
+        ("fan", fan.id.as_str()),
+        ("chip", fan.hwmon_name.as_str()),
+        ("label", fan.label.as_deref().unwrap_or("")),
+    ];
+    labels.extend_from_slice(extra);
+    labels
+}
+
+fn flag(on: bool) -> f64 {
+    if on { 1.0 } else { 0.0 }
+}
+
+/// Escape a label value: backslash, double quote and newline.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn default_listen() -> String {
+    DEFAULT_METRICS_LISTEN.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FanAssignment;
+
+    fn fan(id: &str, label: Option<&str>) -> FanStatus {
+        FanStatus {
+            id: id.to_string(),
+            legacy_id: String::new(),
+            label: label.map(str::to_string),
+            hwmon_name: "nct6775".to_string(),
+            pwm: Some(128),
+            pwm_enable: Some(1),
+            rpm: Some(900),
+            target_pwm: Some(140),
+            applied_pwm: Some(130),
+            active_input: None,
+            pid: None,
+            stalled: false,
+            forced: None,
+            calibrating: None,
+            calibration: None,
+            hidden: false,
+        }
+    }
+
+    fn temp(id: &str, value: f64, unit: InputUnit) -> TempStatus {
+        TempStatus {
+            id: id.to_string(),
+            legacy_id: String::new(),
+            label: Some("CPU \"Tctl\"".to_string()),
+            hwmon_name: "k10temp".to_string(),
+            temp_c: Some(value),
+            filtered_c: None,
+            crit_c: None,
+            max_c: None,
+            unit,
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let fans = [
+            fan("nct6775/pwm1", Some("CPU Fan")),
+            fan("nct6775/pwm2", None),
+        ];
+        let temps = [
+            temp("k10temp/temp1", 52.5, InputUnit::Celsius),
+            temp("load/cpu", 37.0, InputUnit::Percent),
+        ];
+        let assignments = [FanAssignmentInfo {
+            fan_id: "nct6775/pwm1".to_string(),
+            assignment: FanAssignment::Manual { pwm: 128 },
+        }];
+        let failsafe = Failsafe::new();
+        let text = render(&MetricsSnapshot {
+            fans: &fans,
+            temps: &temps,
+            assignments: &assignments,
+            failsafe: &failsafe,
+            tick: Some(Duration::from_millis(3)),
+        });
+
+        for line in [
+            "# TYPE fanctl_fan_pwm gauge",
+            "fanctl_temperature_celsius{sensor=\"k10temp/temp1\",chip=\"k10temp\",label=\"CPU \\\"Tctl\\\"\"} 52.5",
+            "fanctl_sensor_value{sensor=\"load/cpu\",chip=\"k10temp\",label=\"CPU \\\"Tctl\\\"\",unit=\"percent\"} 37",
+            "fanctl_fan_rpm{fan=\"nct6775/pwm1\",chip=\"nct6775\",label=\"CPU Fan\"} 900",
+            "fanctl_fan_target_pwm{fan=\"nct6775/pwm2\",chip=\"nct6775\",label=\"\"} 140",
+            "fanctl_fan_mode{fan=\"nct6775/pwm1\",chip=\"nct6775\",label=\"CPU Fan\",mode=\"manual\"} 1",
+            "fanctl_fan_mode{fan=\"nct6775/pwm2\",chip=\"nct6775\",label=\"\",mode=\"auto\"} 1",
+            "fanctl_failsafe_active 0",
+            "fanctl_engine_tick_seconds 0.003",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {line} in\n{text}");
+        }
+        assert!(!text.contains("fanctl_failsafe_tripped{"));
+    }
+
+    #[test]
+    fn test_respond() {
+        let ok = respond("GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n", || {
+            "a 1\n".to_string()
+        });
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.contains("Content-Length: 4\r\n"));
+        assert!(ok.ends_with("\r\n\r\na 1\n"));
+
+        let head = respond("HEAD /metrics?x=1 HTTP/1.1\r\n\r\n", || "a 1\n".to_string());
+        assert!(head.ends_with("\r\n\r\n"));
+
+        assert!(respond("GET / HTTP/1.1", String::new).starts_with("HTTP/1.1 404"));
+        assert!(respond("POST /metrics HTTP/1.1", String::new).starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let endpoint = |listen: &str| {
+            MetricsConfig {
+                listen: listen.to_string(),
+            }
+            .endpoint()
+        };
+        assert_eq!(
+            endpoint("127.0.0.1:9788"),
+            Ok(MetricsEndpoint::Tcp("127.0.0.1:9788".parse().unwrap()))
+        );
+        assert_eq!(
+            endpoint("unix:/run/fanctl-metrics.sock"),
+            Ok(MetricsEndpoint::Unix(PathBuf::from(
+                "/run/fanctl-metrics.sock"
+            )))
+        );
+        assert!(endpoint("unix:").is_err());
+        assert!(endpoint("localhost").is_err());
+    }
+}
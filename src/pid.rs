@@ -0,0 +1,191 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Closed-loop PID control toward a temperature setpoint.
+//!
+//! Unlike lookup curves, a PID assignment adjusts the PWM until the sensor
+//! settles at the setpoint. The error is `temp - setpoint`, so a positive
+//! error (too hot) drives the PWM up.
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Tuning for a PID assignment.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PidParams {
+    /// Target temperature in degrees Celsius
+    pub setpoint_c: f64,
+    /// Proportional gain (PWM units per °C)
+    pub kp: f64,
+    /// Integral gain (PWM units per °C·s)
+    pub ki: f64,
+    /// Derivative gain (PWM units per °C/s)
+    pub kd: f64,
+    /// Lowest PWM the controller will output
+    pub min_pwm: u8,
+    /// Highest PWM the controller will output
+    pub max_pwm: u8,
+}
+
+impl PidParams {
+    /// Check gains are finite and the output range is non-empty.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, v) in [
+            ("setpoint_c", self.setpoint_c),
+            ("kp", self.kp),
+            ("ki", self.ki),
+            ("kd", self.kd),
+        ] {
+            if !v.is_finite() {
+                return Err(format!("PID {name} must be a finite number"));
+            }
+        }
+        if self.min_pwm > self.max_pwm {
+            return Err("PID min_pwm must not exceed max_pwm".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The individual contributions of the last update, for display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PidTerms {
+    /// Setpoint the terms were computed against
+    pub setpoint_c: f64,
+    /// Error (temp - setpoint) in °C
+    pub error_c: f64,
+    pub p: f64,
+    pub i: f64,
+    pub d: f64,
+    /// Clamped PWM output
+    pub output: u8,
+}
+
+/// Running state of a PID controller.
+#[derive(Debug, Clone, Default)]
+pub struct PidController {
+    /// Accumulated integral term in PWM units. `ki` is applied as the
+    /// error is summed, so retuning it doesn't rescale the history.
+    integral: f64,
+    /// Previous reading and when it was taken
+    last: Option<(f64, Instant)>,
+}
+
+impl PidController {
+    /// A controller with no accumulated error or previous reading.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a reading and compute the next PWM output.
+    ///
+    /// The derivative acts on the measurement rather than the error so
+    /// that retuning the setpoint doesn't cause a kick. Integration is
+    /// paused while the output is saturated in the direction the error
+    /// pushes it (anti-windup).
+    pub fn update(&mut self, params: &PidParams, temp_c: f64, now: Instant) -> PidTerms {
+        let error = temp_c - params.setpoint_c;
+        let (dt, slope) = match self.last {
+            Some((prev, at)) => {
+                let dt = now.saturating_duration_since(at).as_secs_f64();
+                let slope = if dt > 0.0 { (temp_c - prev) / dt } else { 0.0 };
+                (dt, slope)
+            }
+            None => (0.0, 0.0),
+        };
+        self.last = Some((temp_c, now));
+
+        let min = params.min_pwm as f64;
+        let max = params.max_pwm as f64;
+        let p = params.kp * error;
+        let d = params.kd * slope;
+
+        let candidate = self.integral + params.ki * error * dt;
+        let unclamped = p + candidate + d;
+        let winding_up = (unclamped > max && error > 0.0) || (unclamped < min && error < 0.0);
+        if !winding_up {
+            self.integral = candidate;
+        }
+
+        let i = self.integral;
+        let output = (p + i + d).clamp(min, max).round() as u8;
+        PidTerms {
+            setpoint_c: params.setpoint_c,
+            error_c: error,
+            p,
+            i,
+            d,
+            output,
+        }
+    }
+
+    /// Clear the integral and derivative history.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn params() -> PidParams {
+        PidParams {
+            setpoint_c: 40.0,
+            kp: 10.0,
+            ki: 1.0,
+            kd: 0.0,
+            min_pwm: 50,
+            max_pwm: 255,
+        }
+    }
+
+    #[test]
+    fn test_output_clamped_to_range() {
+        let mut pid = PidController::new();
+        let now = Instant::now();
+        assert_eq!(pid.update(&params(), 20.0, now).output, 50);
+        assert_eq!(pid.update(&params(), 90.0, now).output, 255);
+    }
+
+    #[test]
+    fn test_changing_ki_keeps_output() {
+        let mut pid = PidController::new();
+        let start = Instant::now();
+        for s in 0..10 {
+            pid.update(&params(), 45.0, start + Duration::from_secs(s));
+        }
+        let before = pid.update(&params(), 45.0, start + Duration::from_secs(10));
+
+        let retuned = PidParams {
+            ki: 4.0,
+            ..params()
+        };
+        // Same reading straight after: only the new step's integral differs
+        let after = pid.update(&retuned, 45.0, start + Duration::from_secs(10));
+        assert_eq!(after.i, before.i);
+        assert_eq!(after.output, before.output);
+    }
+
+    #[test]
+    fn test_anti_windup() {
+        let mut pid = PidController::new();
+        let start = Instant::now();
+        // Saturated high for a long time: integral must not keep growing
+        for s in 0..100 {
+            pid.update(&params(), 80.0, start + Duration::from_secs(s));
+        }
+        // Just above setpoint, the output should come off the ceiling
+        let terms = pid.update(&params(), 41.0, start + Duration::from_secs(101));
+        assert!(terms.output < 255, "integral wound up: {terms:?}");
+
+        // With the error gone the output falls back to the floor within a
+        // few steps instead of unwinding 100 s worth of integral
+        let recovered = (102..105).any(|s| {
+            let terms = pid.update(&params(), 40.0, start + Duration::from_secs(s));
+            terms.output == params().min_pwm
+        });
+        assert!(recovered, "output stuck after saturation");
+    }
+}
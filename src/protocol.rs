@@ -4,13 +4,73 @@
 //! Client-daemon protocol over Unix domain sockets.
 //!
 //! Messages are newline-delimited JSON. The client sends a [`Request`]
-//! and the daemon replies with a [`Response`].
+//! and the daemon replies with a [`Response`]. After [`Request::Subscribe`],
+//! the daemon also sends unsolicited [`Response::Push`] messages on that
+//! connection: a status snapshot after every control-loop tick and a
+//! [`DaemonEvent`] whenever something notable happens. Pushes are tagged
+//! `"type": "push"` so clients can tell them apart from replies.
+//!
+//! Every message travels in an [`Envelope`]. A client may put an `id` on a
+//! request and the daemon copies it onto the reply, so replies can be
+//! matched up even with pushes interleaved. Clients should open with
+//! [`Request::Hello`] to check they speak the daemon's [`PROTOCOL_VERSION`]
+//! before sending anything else.
 
-use crate::config::FanAssignment;
-use crate::curve::{CurvePoint, FanCurve};
+use crate::calibration::FanCalibration;
+use crate::config::{Combiner, CurveInput, FanAssignment};
+use crate::curve::{CurvePoint, FanCurve, InputUnit};
+use crate::failsafe::{FailsafeAction, FailsafeReason};
+use crate::history::Sample;
 use crate::hwmon::{FanStatus, TempStatus};
+use crate::schedule::ScheduleStatus;
+use crate::smoothing::Smoothing;
+use crate::trigger::TriggerStatus;
 use serde::{Deserialize, Serialize};
 
+/// Version of this protocol. Bumped whenever a change would stop an
+/// existing client from decoding the daemon's messages.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Optional capabilities the daemon reports in [`Response::Hello`], so
+/// clients can hide what an older daemon can't do.
+pub const FEATURES: &[&str] = &[
+    "subscribe",
+    "multi_curve",
+    "pid",
+    "stall",
+    "failsafe",
+    "calibration",
+    "access_control",
+    "profiles",
+    "schedule",
+    "triggers",
+    "load_sensors",
+    "history",
+    "labels",
+    "ignore",
+];
+
+/// Whether a peer speaking `version` can talk to this build.
+pub fn is_compatible(version: u32) -> bool {
+    version == PROTOCOL_VERSION
+}
+
+/// A message plus its optional correlation id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    /// Chosen by the client; echoed on the reply, absent on pushes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(id: Option<u64>, body: T) -> Self {
+        Self { id, body }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Requests (TUI -> Daemon)
 // ---------------------------------------------------------------------------
@@ -18,10 +78,34 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Request {
+    /// Exchange protocol versions. The daemon answers with
+    /// [`Response::Hello`] whatever version the client gives.
+    #[serde(rename = "hello")]
+    Hello {
+        protocol_version: u32,
+        /// Free-form client name and version, for the daemon's log
+        #[serde(default)]
+        client: Option<String>,
+    },
+
     /// Request current status of all fans and temps.
     #[serde(rename = "get_status")]
     GetStatus,
 
+    /// Recorded readings, oldest first.
+    #[serde(rename = "get_history")]
+    GetHistory {
+        /// Sensor and fan ids to include; empty for all
+        #[serde(default)]
+        ids: Vec<String>,
+        /// Only samples at or after this Unix time, in milliseconds
+        #[serde(default)]
+        since: Option<u64>,
+        /// Average samples into buckets this many milliseconds wide
+        #[serde(default)]
+        resolution: Option<u64>,
+    },
+
     /// Set a fan to manual PWM.
     #[serde(rename = "set_manual")]
     SetManual { fan_id: String, pwm: u8 },
@@ -32,12 +116,76 @@ pub enum Request {
         fan_id: String,
         curve_name: String,
         temp_sensor_id: String,
+        /// Optional filter applied to the reading before curve lookup.
+        #[serde(default)]
+        smoothing: Option<Smoothing>,
+    },
+
+    /// Drive a fan from several curves, merging their demands.
+    #[serde(rename = "set_multi_curve")]
+    SetMultiCurve {
+        fan_id: String,
+        inputs: Vec<CurveInput>,
+        #[serde(default)]
+        combiner: Combiner,
+    },
+
+    /// Hold a sensor at a temperature setpoint with a PID controller.
+    #[serde(rename = "set_pid")]
+    SetPid {
+        fan_id: String,
+        temp_sensor_id: String,
+        setpoint_c: f64,
+        kp: f64,
+        ki: f64,
+        kd: f64,
+        min_pwm: u8,
+        max_pwm: u8,
+    },
+
+    /// Adjust a running PID assignment without resetting its state.
+    /// Omitted fields keep their current value.
+    #[serde(rename = "tune_pid")]
+    TunePid {
+        fan_id: String,
+        #[serde(default)]
+        setpoint_c: Option<f64>,
+        #[serde(default)]
+        kp: Option<f64>,
+        #[serde(default)]
+        ki: Option<f64>,
+        #[serde(default)]
+        kd: Option<f64>,
+        #[serde(default)]
+        min_pwm: Option<u8>,
+        #[serde(default)]
+        max_pwm: Option<u8>,
+    },
+
+    /// Measure a fan's start/stop PWM and RPM response. The result is
+    /// stored in the config and announced with an event.
+    #[serde(rename = "calibrate")]
+    Calibrate {
+        fan_id: String,
+        /// Time to settle at each PWM step, in milliseconds
+        #[serde(default)]
+        settle_ms: Option<u64>,
     },
 
     /// Set a fan to automatic (BIOS) control.
     #[serde(rename = "set_auto")]
     SetAuto { fan_id: String },
 
+    /// Give a fan or sensor a friendly name, replacing its kernel or
+    /// lm-sensors label. An empty or missing label clears it.
+    #[serde(rename = "set_label")]
+    SetLabel {
+        /// Fan or sensor id (`id` is taken by the envelope)
+        target: String,
+        #[serde(default)]
+        label: Option<String>,
+    },
+
     /// List all configured curves.
     #[serde(rename = "list_curves")]
     ListCurves,
@@ -47,12 +195,47 @@ pub enum Request {
     UpsertCurve {
         name: String,
         points: Vec<CurvePoint>,
+        /// Hysteresis band in the curve's unit (see [`FanCurve::hysteresis`]).
+        #[serde(default, alias = "hysteresis_c")]
+        hysteresis: f64,
+        /// Minimum hold time in ms (see [`FanCurve::hold_ms`]).
+        #[serde(default)]
+        hold_ms: u64,
+        /// Max PWM increase per second (see [`FanCurve::ramp_up_rate`]).
+        #[serde(default)]
+        ramp_up_rate: f64,
+        /// Max PWM decrease per second (see [`FanCurve::ramp_down_rate`]).
+        #[serde(default)]
+        ramp_down_rate: f64,
+        /// Scale PWM into the fan's usable range (see [`FanCurve::relative`]).
+        #[serde(default)]
+        relative: bool,
+        /// Unit of the x-axis (see [`FanCurve::unit`]).
+        #[serde(default)]
+        unit: InputUnit,
     },
 
     /// Delete a curve by name.
     #[serde(rename = "delete_curve")]
     DeleteCurve { name: String },
 
+    /// List the configured profiles.
+    #[serde(rename = "list_profiles")]
+    ListProfiles,
+
+    /// Switch every fan to a profile's assignments at once.
+    #[serde(rename = "set_profile")]
+    SetProfile { name: String },
+
+    /// Store the current assignments as a profile, replacing any profile
+    /// of that name.
+    #[serde(rename = "save_profile")]
+    SaveProfile { name: String },
+
+    /// Delete a profile by name.
+    #[serde(rename = "delete_profile")]
+    DeleteProfile { name: String },
+
     /// Save current configuration to disk.
     #[serde(rename = "save_config")]
     SaveConfig,
@@ -61,15 +244,33 @@ pub enum Request {
     #[serde(rename = "reload_config")]
     ReloadConfig,
 
-    /// Request the daemon to push periodic status updates.
+    /// Request the daemon to push status updates and events on this
+    /// connection.
     #[serde(rename = "subscribe")]
     Subscribe,
 
-    /// Stop receiving periodic status updates.
+    /// Stop receiving pushes.
     #[serde(rename = "unsubscribe")]
     Unsubscribe,
 }
 
+impl Request {
+    /// Whether the request changes fan control or configuration. Only
+    /// permitted peers may send these (see [`crate::access`]).
+    pub fn is_mutating(&self) -> bool {
+        !matches!(
+            self,
+            Request::Hello { .. }
+                | Request::GetStatus
+                | Request::GetHistory { .. }
+                | Request::ListCurves
+                | Request::ListProfiles
+                | Request::Subscribe
+                | Request::Unsubscribe
+        )
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Responses (Daemon -> TUI)
 // ---------------------------------------------------------------------------
@@ -77,18 +278,43 @@ pub enum Request {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Response {
+    /// Reply to [`Request::Hello`].
+    #[serde(rename = "hello")]
+    Hello {
+        protocol_version: u32,
+        daemon_version: String,
+        features: Vec<String>,
+    },
+
     /// Current system status.
     #[serde(rename = "status")]
     Status {
         fans: Vec<FanStatus>,
         temps: Vec<TempStatus>,
         assignments: Vec<FanAssignmentInfo>,
+        /// Profile the assignments were last switched to
+        #[serde(default)]
+        active_profile: Option<String>,
+        /// Current and next scheduled profile, if a schedule is set up
+        #[serde(default)]
+        schedule: Option<ScheduleStatus>,
+        /// Trigger holding a profile, if one has fired
+        #[serde(default)]
+        trigger: Option<TriggerStatus>,
     },
 
+    /// Reply to [`Request::GetHistory`].
+    #[serde(rename = "history")]
+    History { samples: Vec<Sample> },
+
     /// List of configured curves.
     #[serde(rename = "curves")]
     Curves { curves: Vec<FanCurve> },
 
+    /// List of configured profiles, sorted by name.
+    #[serde(rename = "profiles")]
+    Profiles { profiles: Vec<ProfileInfo> },
+
     /// Operation succeeded.
     #[serde(rename = "ok")]
     Ok { message: String },
@@ -96,6 +322,178 @@ pub enum Response {
     /// Operation failed.
     #[serde(rename = "error")]
     Error { message: String },
+
+    /// The client isn't permitted to send this request.
+    #[serde(rename = "denied")]
+    Denied { message: String },
+
+    /// Unsolicited message sent to subscribed connections.
+    #[serde(rename = "push")]
+    Push { push: Push },
+}
+
+impl Response {
+    /// Whether this is an unsolicited push rather than a reply.
+    pub fn is_push(&self) -> bool {
+        matches!(self, Response::Push { .. })
+    }
+}
+
+/// Messages pushed to subscribed clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Push {
+    /// Status snapshot taken after a control-loop tick.
+    #[serde(rename = "status")]
+    Status {
+        fans: Vec<FanStatus>,
+        temps: Vec<TempStatus>,
+        assignments: Vec<FanAssignmentInfo>,
+        /// Profile the assignments were last switched to
+        #[serde(default)]
+        active_profile: Option<String>,
+        /// Current and next scheduled profile, if a schedule is set up
+        #[serde(default)]
+        schedule: Option<ScheduleStatus>,
+        /// Trigger holding a profile, if one has fired
+        #[serde(default)]
+        trigger: Option<TriggerStatus>,
+    },
+
+    /// Something happened in the daemon.
+    #[serde(rename = "event")]
+    Event { event: DaemonEvent },
+}
+
+/// Notable daemon-side occurrences pushed to subscribed clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum DaemonEvent {
+    /// A driven fan's tachometer has read 0 RPM for the grace period.
+    #[serde(rename = "fan_stalled")]
+    FanStalled { fan_id: String, pwm: u8 },
+
+    /// A stalled fan is spinning again.
+    #[serde(rename = "fan_recovered")]
+    FanRecovered { fan_id: String, rpm: u32 },
+
+    /// A sensor crossed a limit or lost its reading; fans are overridden.
+    #[serde(rename = "failsafe_triggered")]
+    FailsafeTriggered {
+        sensor_id: String,
+        reason: FailsafeReason,
+        action: FailsafeAction,
+    },
+
+    /// A tripped sensor has stayed clear for the configured time.
+    #[serde(rename = "failsafe_cleared")]
+    FailsafeCleared { sensor_id: String },
+
+    /// A fan calibration completed.
+    #[serde(rename = "calibration_finished")]
+    CalibrationFinished {
+        fan_id: String,
+        calibration: FanCalibration,
+    },
+
+    /// A fan calibration failed or was aborted.
+    #[serde(rename = "calibration_failed")]
+    CalibrationFailed { fan_id: String, message: String },
+
+    /// A fan was given a new assignment.
+    #[serde(rename = "assignment_changed")]
+    AssignmentChanged {
+        fan_id: String,
+        assignment: FanAssignment,
+    },
+
+    /// A curve was created, updated or deleted.
+    #[serde(rename = "curves_changed")]
+    CurvesChanged,
+
+    /// The config file was reloaded from disk.
+    #[serde(rename = "config_reloaded")]
+    ConfigReloaded,
+
+    /// Every fan was switched to a profile's assignments.
+    #[serde(rename = "profile_changed")]
+    ProfileChanged { name: String },
+
+    /// A profile was saved or deleted.
+    #[serde(rename = "profiles_changed")]
+    ProfilesChanged,
+
+    /// A trigger fired and switched to its profile.
+    #[serde(rename = "trigger_engaged")]
+    TriggerEngaged { rule: String, profile: String },
+
+    /// A trigger's condition cleared.
+    #[serde(rename = "trigger_released")]
+    TriggerReleased { rule: String },
+
+    /// A fan or sensor was given a label, or had it cleared.
+    #[serde(rename = "label_changed")]
+    LabelChanged { id: String, label: Option<String> },
+}
+
+impl DaemonEvent {
+    /// One-line human-readable description.
+    pub fn describe(&self) -> String {
+        match self {
+            DaemonEvent::FanStalled { fan_id, pwm } => {
+                format!("Fan {fan_id} stalled: 0 RPM at PWM {pwm}")
+            }
+            DaemonEvent::FanRecovered { fan_id, rpm } => {
+                format!("Fan {fan_id} recovered: {rpm} RPM")
+            }
+            DaemonEvent::FailsafeTriggered {
+                sensor_id,
+                reason,
+                action,
+            } => format!(
+                "Failsafe triggered by {sensor_id} ({}): fans to {}",
+                reason.describe(),
+                action.describe()
+            ),
+            DaemonEvent::FailsafeCleared { sensor_id } => {
+                format!("Failsafe cleared for {sensor_id}")
+            }
+            DaemonEvent::CalibrationFinished {
+                fan_id,
+                calibration,
+            } => format!("Calibrated {fan_id}: {}", calibration.describe()),
+            DaemonEvent::CalibrationFailed { fan_id, message } => {
+                format!("Calibration of {fan_id} failed: {message}")
+            }
+            DaemonEvent::AssignmentChanged { fan_id, .. } => {
+                format!("Assignment of {fan_id} changed")
+            }
+            DaemonEvent::CurvesChanged => "Curves changed".to_string(),
+            DaemonEvent::ConfigReloaded => "Config reloaded".to_string(),
+            DaemonEvent::ProfileChanged { name } => format!("Switched to profile '{name}'"),
+            DaemonEvent::ProfilesChanged => "Profiles changed".to_string(),
+            DaemonEvent::TriggerEngaged { rule, profile } => {
+                format!("Trigger '{rule}' switched to profile '{profile}'")
+            }
+            DaemonEvent::TriggerReleased { rule } => format!("Trigger '{rule}' cleared"),
+            DaemonEvent::LabelChanged { id, label } => match label {
+                Some(label) => format!("Labelled {id} \"{label}\""),
+                None => format!("Cleared label of {id}"),
+            },
+        }
+    }
+}
+
+/// A profile as listed by [`Request::ListProfiles`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub assignments: Vec<FanAssignmentInfo>,
+    /// Whether this is the active profile
+    pub active: bool,
+    /// Active, but fans have been reassigned since switching to it
+    #[serde(default)]
+    pub modified: bool,
 }
 
 /// Fan assignment info sent in status messages.
@@ -120,3 +518,66 @@ pub fn encode<T: Serialize>(msg: &T) -> Result<String, serde_json::Error> {
 pub fn decode<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T, serde_json::Error> {
     serde_json::from_str(s.trim())
 }
+
+/// Best-effort correlation id of a message that failed to decode, so the
+/// error reply can still be matched to its request.
+pub fn salvage_id(s: &str) -> Option<u64> {
+    serde_json::from_str::<serde_json::Value>(s.trim())
+        .ok()?
+        .get("id")?
+        .as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_distinguishable_from_reply() {
+        let push = Response::Push {
+            push: Push::Event {
+                event: DaemonEvent::FanStalled {
+                    fan_id: "nct6775/pwm1".to_string(),
+                    pwm: 128,
+                },
+            },
+        };
+        let line = encode(&push).unwrap();
+        assert!(line.starts_with(r#"{"type":"push","push":{"kind":"event""#));
+
+        let decoded: Response = decode(&line).unwrap();
+        assert!(decoded.is_push());
+        let reply: Response = decode(r#"{"type":"ok","message":"done"}"#).unwrap();
+        assert!(!reply.is_push());
+    }
+
+    #[test]
+    fn test_envelope_correlation_id() {
+        // Clients that predate ids still decode
+        let bare: Envelope<Request> = decode(r#"{"type":"get_status"}"#).unwrap();
+        assert_eq!(bare.id, None);
+        assert!(matches!(bare.body, Request::GetStatus));
+
+        let req: Envelope<Request> =
+            decode(r#"{"id":7,"type":"delete_curve","name":"quiet"}"#).unwrap();
+        assert_eq!(req.id, Some(7));
+        assert!(matches!(req.body, Request::DeleteCurve { ref name } if name == "quiet"));
+
+        let reply = encode(&Envelope::new(
+            req.id,
+            Response::Ok {
+                message: "done".to_string(),
+            },
+        ))
+        .unwrap();
+        assert_eq!(reply, "{\"id\":7,\"type\":\"ok\",\"message\":\"done\"}\n");
+
+        let label: Envelope<Request> =
+            decode(r#"{"id":8,"type":"set_label","target":"nct/pwm1","label":"CPU"}"#).unwrap();
+        assert_eq!(label.id, Some(8));
+        assert!(matches!(label.body, Request::SetLabel { ref target, .. } if target == "nct/pwm1"));
+
+        assert_eq!(salvage_id(r#"{"id":9,"type":"from_the_future"}"#), Some(9));
+        assert!(decode::<Envelope<Request>>(r#"{"id":9,"type":"from_the_future"}"#).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Time-of-day profile schedules.
+//!
+//! Rules name a profile, the days and the time range it applies to, and a
+//! priority for when rules overlap. The daemon asks a [`Scheduler`] every
+//! tick which profile should be active and switches when the answer
+//! changes, i.e. at a rule boundary. A manual switch in between sticks
+//! until the next boundary.
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How far ahead [`next_transition`] looks.
+const LOOKAHEAD_DAYS: i64 = 8;
+
+/// Schedule settings (`[schedule]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduleConfig {
+    /// Whether rules are applied at all.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Profile to switch to when no rule applies. If unset, the
+    /// assignments are left as they are between rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+
+    /// Rules, checked in priority order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ScheduleRule>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_profile: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl ScheduleConfig {
+    /// Whether there is anything to schedule.
+    pub fn is_active(&self) -> bool {
+        self.enabled && (!self.rules.is_empty() || self.default_profile.is_some())
+    }
+}
+
+/// Run a profile on some days between two times.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduleRule {
+    /// Name for logs and status; defaults to a description of the times.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Profile to switch to while the rule applies.
+    pub profile: String,
+
+    /// Days the rule starts on. Empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<DaySet>,
+
+    /// Start time, "HH:MM" local time.
+    pub start: TimeOfDay,
+
+    /// End time, "HH:MM". An end at or before the start runs past
+    /// midnight into the next day.
+    pub end: TimeOfDay,
+
+    /// Higher wins where rules overlap; ties go to the earlier rule.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl ScheduleRule {
+    /// Display name, e.g. "night" or "weekdays 08:00-18:00".
+    pub fn describe(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let days = if self.days.is_empty() {
+            "daily".to_string()
+        } else {
+            self.days
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        format!("{days} {}-{}", self.start, self.end)
+    }
+
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.iter().any(|d| d.contains(day))
+    }
+
+    /// Whether the rule covers the instant `at`.
+    pub fn applies_at(&self, at: NaiveDateTime) -> bool {
+        let time = at.time();
+        let (start, end) = (self.start.0, self.end.0);
+        if start < end {
+            self.starts_on(at.weekday()) && time >= start && time < end
+        } else {
+            // Overnight: today's part, or the tail of yesterday's run
+            (self.starts_on(at.weekday()) && time >= start)
+                || (self.starts_on(at.weekday().pred()) && time < end)
+        }
+    }
+}
+
+/// A day or group of days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DaySet {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+    /// Monday to Friday
+    Weekdays,
+    /// Saturday and Sunday
+    Weekends,
+}
+
+impl DaySet {
+    pub fn contains(self, day: Weekday) -> bool {
+        match self {
+            DaySet::Mon => day == Weekday::Mon,
+            DaySet::Tue => day == Weekday::Tue,
+            DaySet::Wed => day == Weekday::Wed,
+            DaySet::Thu => day == Weekday::Thu,
+            DaySet::Fri => day == Weekday::Fri,
+            DaySet::Sat => day == Weekday::Sat,
+            DaySet::Sun => day == Weekday::Sun,
+            DaySet::Weekdays => day.num_days_from_monday() < 5,
+            DaySet::Weekends => day.num_days_from_monday() >= 5,
+        }
+    }
+}
+
+impl fmt::Display for DaySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            DaySet::Mon => "mon",
+            DaySet::Tue => "tue",
+            DaySet::Wed => "wed",
+            DaySet::Thu => "thu",
+            DaySet::Fri => "fri",
+            DaySet::Sat => "sat",
+            DaySet::Sun => "sun",
+            DaySet::Weekdays => "weekdays",
+            DaySet::Weekends => "weekends",
+        };
+        f.write_str(s)
+    }
+}
+
+/// A wall-clock time written as "HH:MM".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(pub NaiveTime);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        NaiveTime::parse_from_str(s.trim(), "%H:%M")
+            .map(TimeOfDay)
+            .map_err(|_| format!("Invalid time '{s}', expected HH:MM"))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(t: TimeOfDay) -> String {
+        t.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0.hour(), self.0.minute())
+    }
+}
+
+/// The rule in force at `at`, if any.
+pub fn active_rule(config: &ScheduleConfig, at: NaiveDateTime) -> Option<&ScheduleRule> {
+    config
+        .rules
+        .iter()
+        .filter(|r| r.applies_at(at))
+        // max_by_key keeps the last maximum; reverse so ties go to the first
+        .rev()
+        .max_by_key(|r| r.priority)
+}
+
+/// The profile the schedule wants at `at`.
+pub fn target_profile(config: &ScheduleConfig, at: NaiveDateTime) -> Option<&str> {
+    active_rule(config, at)
+        .map(|r| r.profile.as_str())
+        .or(config.default_profile.as_deref())
+}
+
+/// When the scheduled profile next changes after `at`, and to what.
+pub fn next_transition(
+    config: &ScheduleConfig,
+    at: NaiveDateTime,
+) -> Option<(NaiveDateTime, Option<String>)> {
+    let current = target_profile(config, at);
+    // The answer can only change where some rule starts or ends
+    let mut boundaries: Vec<NaiveDateTime> = (0..=LOOKAHEAD_DAYS)
+        .flat_map(|day| {
+            let date = at.date() + Duration::days(day);
+            config
+                .rules
+                .iter()
+                .flat_map(move |r| [date.and_time(r.start.0), date.and_time(r.end.0)])
+        })
+        .filter(|&t| t > at)
+        .collect();
+    boundaries.sort();
+    boundaries.dedup();
+    boundaries.into_iter().find_map(|t| {
+        let target = target_profile(config, t);
+        (target != current).then(|| (t, target.map(str::to_string)))
+    })
+}
+
+/// Where the schedule stands, as reported in status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    /// Rule in force, by [`ScheduleRule::describe`]
+    pub rule: Option<String>,
+    /// Profile the schedule wants now
+    pub profile: Option<String>,
+    /// Local time of the next change, "YYYY-MM-DD HH:MM"
+    pub next_at: Option<String>,
+    /// Profile the next change switches to
+    pub next_profile: Option<String>,
+    /// Assignments were changed by hand since the last boundary
+    pub overridden: bool,
+}
+
+impl ScheduleStatus {
+    /// One-line summary, e.g. "render (night), next: quiet at
+    /// 2026-10-17 06:00".
+    pub fn describe(&self) -> String {
+        let mut s = match (&self.profile, &self.rule) {
+            (Some(profile), Some(rule)) => format!("{profile} ({rule})"),
+            (Some(profile), None) => format!("{profile} (default)"),
+            (None, _) => "no profile".to_string(),
+        };
+        if self.overridden {
+            s.push_str(", overridden");
+        }
+        if let Some(at) = &self.next_at {
+            let next = self.next_profile.as_deref().unwrap_or("no profile");
+            s.push_str(&format!(", next: {next} at {at}"));
+        }
+        s
+    }
+}
+
+/// Tracks which scheduled profile was last applied.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    /// Target at the last update; `None` before the first
+    applied: Option<Option<String>>,
+    overridden: bool,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the schedule at `now`. Returns the profile to switch to when
+    /// a boundary has been crossed (or on the first call), which also ends
+    /// any manual override.
+    pub fn update(&mut self, config: &ScheduleConfig, now: NaiveDateTime) -> Option<String> {
+        let target = target_profile(config, now).map(str::to_string);
+        if self.applied.as_ref() == Some(&target) {
+            return None;
+        }
+        self.applied = Some(target.clone());
+        self.overridden = false;
+        target
+    }
+
+    /// Note a manual change; it holds until the next boundary.
+    pub fn override_manual(&mut self) {
+        self.overridden = true;
+    }
+
+    /// Whether a manual change is holding since the last boundary.
+    pub fn is_overridden(&self) -> bool {
+        self.overridden
+    }
+
+    /// Forget what was applied so the next update switches again, e.g.
+    /// after the config is reloaded.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn status(&self, config: &ScheduleConfig, now: NaiveDateTime) -> ScheduleStatus {
+        let next = next_transition(config, now);
+        ScheduleStatus {
+            rule: active_rule(config, now).map(ScheduleRule::describe),
+            profile: target_profile(config, now).map(str::to_string),
+            next_at: next
+                .as_ref()
+                .map(|(t, _)| t.format("%Y-%m-%d %H:%M").to_string()),
+            next_profile: next.and_then(|(_, p)| p),
+            overridden: self.overridden,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn config() -> ScheduleConfig {
+        toml::from_str(
+            r#"
+            default_profile = "quiet"
+
+            [[rules]]
+            profile = "render"
+            days = ["weekdays"]
+            start = "22:00"
+            end = "06:00"
+
+            [[rules]]
+            name = "demo"
+            profile = "gaming"
+            days = ["fri"]
+            start = "23:00"
+            end = "23:30"
+            priority = 10
+            "#,
+        )
+        .unwrap()
+    }
+
+    /// 2026-10-16 is a Friday.
+    fn at(day: u32, h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rules_and_priority() {
+        let cfg = config();
+        assert_eq!(target_profile(&cfg, at(16, 12, 0)), Some("quiet"));
+        assert_eq!(target_profile(&cfg, at(16, 22, 30)), Some("render"));
+        assert_eq!(target_profile(&cfg, at(16, 23, 10)), Some("gaming"));
+        // Friday night's run carries into Saturday morning...
+        assert_eq!(target_profile(&cfg, at(17, 5, 59)), Some("render"));
+        // ...but nothing starts on Saturday night
+        assert_eq!(target_profile(&cfg, at(17, 22, 30)), Some("quiet"));
+        assert_eq!(
+            active_rule(&cfg, at(16, 22, 0)).map(ScheduleRule::describe),
+            Some("weekdays 22:00-06:00".to_string())
+        );
+    }
+
+    #[test]
+    fn test_next_transition_and_override() {
+        let cfg = config();
+        assert_eq!(
+            next_transition(&cfg, at(16, 12, 0)),
+            Some((at(16, 22, 0), Some("render".to_string())))
+        );
+        assert_eq!(
+            next_transition(&cfg, at(16, 23, 10)),
+            Some((at(16, 23, 30), Some("render".to_string())))
+        );
+
+        let mut scheduler = Scheduler::new();
+        assert_eq!(
+            scheduler.update(&cfg, at(16, 12, 0)),
+            Some("quiet".to_string())
+        );
+        scheduler.override_manual();
+        assert_eq!(scheduler.update(&cfg, at(16, 21, 59)), None);
+        assert!(scheduler.status(&cfg, at(16, 21, 59)).overridden);
+        assert_eq!(
+            scheduler.update(&cfg, at(16, 22, 0)),
+            Some("render".to_string())
+        );
+        assert!(!scheduler.status(&cfg, at(16, 22, 0)).overridden);
+    }
+}
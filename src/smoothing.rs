@@ -0,0 +1,132 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Temperature input smoothing.
+//!
+//! Noisy sensors (e.g. k10temp Tctl) spike for a few hundred milliseconds
+//! at a time. A [`TempFilter`] is applied to each reading before curve
+//! lookup so those spikes don't reach the fan.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Smoothing filter configuration for a curve input.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Smoothing {
+    /// Exponential moving average with the given time constant.
+    #[serde(rename = "ema")]
+    Ema { time_constant_ms: u64 },
+
+    /// Mean of the last `window` readings.
+    #[serde(rename = "mean")]
+    Mean { window: usize },
+
+    /// Median of the last `window` readings.
+    #[serde(rename = "median")]
+    Median { window: usize },
+}
+
+impl Smoothing {
+    /// Short human-readable description, e.g. "EMA 5.0s".
+    pub fn describe(&self) -> String {
+        match self {
+            Smoothing::Ema { time_constant_ms } => {
+                format!("EMA {:.1}s", *time_constant_ms as f64 / 1000.0)
+            }
+            Smoothing::Mean { window } => format!("mean of {window}"),
+            Smoothing::Median { window } => format!("median of {window}"),
+        }
+    }
+}
+
+/// Running state for a [`Smoothing`] filter.
+#[derive(Debug, Clone)]
+pub struct TempFilter {
+    spec: Smoothing,
+    ema: Option<(f64, Instant)>,
+    window: VecDeque<f64>,
+}
+
+impl TempFilter {
+    pub fn new(spec: Smoothing) -> Self {
+        Self {
+            spec,
+            ema: None,
+            window: VecDeque::new(),
+        }
+    }
+
+    /// The filter configuration this state was created for.
+    pub fn spec(&self) -> Smoothing {
+        self.spec
+    }
+
+    /// Feed a raw reading and return the filtered value.
+    pub fn update(&mut self, value: f64, now: Instant) -> f64 {
+        match self.spec {
+            Smoothing::Ema { time_constant_ms } => {
+                let filtered = match self.ema {
+                    Some((prev, last)) if time_constant_ms > 0 => {
+                        let dt = now.saturating_duration_since(last).as_secs_f64();
+                        let tau = time_constant_ms as f64 / 1000.0;
+                        let alpha = 1.0 - (-dt / tau).exp();
+                        prev + alpha * (value - prev)
+                    }
+                    _ => value,
+                };
+                self.ema = Some((filtered, now));
+                filtered
+            }
+            Smoothing::Mean { window } => {
+                self.push(value, window);
+                self.window.iter().sum::<f64>() / self.window.len() as f64
+            }
+            Smoothing::Median { window } => {
+                self.push(value, window);
+                let mut sorted: Vec<f64> = self.window.iter().copied().collect();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let mid = sorted.len() / 2;
+                if sorted.len() % 2 == 0 {
+                    (sorted[mid - 1] + sorted[mid]) / 2.0
+                } else {
+                    sorted[mid]
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, value: f64, window: usize) {
+        self.window.push_back(value);
+        while self.window.len() > window.max(1) {
+            self.window.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_median_rejects_spike() {
+        let mut filter = TempFilter::new(Smoothing::Median { window: 3 });
+        let now = Instant::now();
+        filter.update(50.0, now);
+        filter.update(51.0, now);
+        assert_eq!(filter.update(61.0, now), 51.0);
+    }
+
+    #[test]
+    fn test_ema_converges() {
+        let mut filter = TempFilter::new(Smoothing::Ema {
+            time_constant_ms: 1000,
+        });
+        let start = Instant::now();
+        assert_eq!(filter.update(40.0, start), 40.0);
+        let after_one_tau = filter.update(50.0, start + Duration::from_secs(1));
+        assert!((after_one_tau - 46.32).abs() < 0.01);
+    }
+}
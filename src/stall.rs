@@ -0,0 +1,210 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Fan stall detection.
+//!
+//! A fan that is being driven with a meaningful PWM but whose tachometer
+//! keeps reading 0 RPM is dead, unplugged or jammed. A [`StallDetector`]
+//! per fan tracks how long that has been the case and reports the
+//! transitions into and out of the stalled state.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Default time a fan must read 0 RPM before it is reported stalled.
+pub const DEFAULT_GRACE_MS: u64 = 6000;
+
+/// Default PWM at or above which a fan is expected to spin.
+pub const DEFAULT_MIN_PWM: u8 = 64;
+
+/// Stall detection settings (`[stall]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StallConfig {
+    /// Whether tachometers are checked at all.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// How long a fan must read 0 RPM before it counts as stalled, in
+    /// milliseconds. Covers spin-up after a PWM increase.
+    #[serde(default = "default_grace_ms")]
+    pub grace_ms: u64,
+
+    /// PWM at or above which a fan is expected to spin. Many fans stop
+    /// legitimately at low duty cycles, so those are never flagged.
+    #[serde(default = "default_min_pwm")]
+    pub min_pwm: u8,
+
+    /// Shell command run when a fan stalls. `FANCTL_FAN_ID` and
+    /// `FANCTL_FAN_LABEL` are set in its environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// Drive every other fan at full speed while any fan is stalled.
+    #[serde(default)]
+    pub full_speed_on_stall: bool,
+}
+
+impl Default for StallConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grace_ms: DEFAULT_GRACE_MS,
+            min_pwm: DEFAULT_MIN_PWM,
+            command: None,
+            full_speed_on_stall: false,
+        }
+    }
+}
+
+/// A change in a fan's stall state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallChange {
+    /// The fan has read 0 RPM for longer than the grace period.
+    Stalled,
+    /// A previously stalled fan is spinning again, or has been turned
+    /// down below the PWM range that is checked.
+    Recovered,
+}
+
+/// Running stall state for one fan.
+#[derive(Debug, Clone, Default)]
+pub struct StallDetector {
+    /// When the fan was first seen at 0 RPM while driven
+    since: Option<Instant>,
+    stalled: bool,
+}
+
+impl StallDetector {
+    /// A detector for a fan that hasn't been seen at 0 RPM yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the fan is currently considered stalled.
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Feed the current PWM and tachometer reading. Fans without a
+    /// tachometer (`rpm` is `None`) are never flagged.
+    pub fn update(
+        &mut self,
+        pwm: Option<u8>,
+        rpm: Option<u32>,
+        config: &StallConfig,
+        now: Instant,
+    ) -> Option<StallChange> {
+        let driven = pwm.is_some_and(|p| p > 0 && p >= config.min_pwm);
+        if !driven {
+            // 0 RPM is normal down here, so a stall can no longer be told
+            // apart from a fan that stopped on purpose
+            self.since = None;
+            return std::mem::take(&mut self.stalled).then_some(StallChange::Recovered);
+        }
+        match rpm {
+            Some(0) => {
+                let since = *self.since.get_or_insert(now);
+                let grace = Duration::from_millis(config.grace_ms);
+                if !self.stalled && now.saturating_duration_since(since) >= grace {
+                    self.stalled = true;
+                    return Some(StallChange::Stalled);
+                }
+                None
+            }
+            Some(rpm) => {
+                self.since = None;
+                if self.stalled && rpm > 0 {
+                    self.stalled = false;
+                    return Some(StallChange::Recovered);
+                }
+                None
+            }
+            None => {
+                self.since = None;
+                None
+            }
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_grace_ms() -> u64 {
+    DEFAULT_GRACE_MS
+}
+
+fn default_min_pwm() -> u8 {
+    DEFAULT_MIN_PWM
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_after_grace_and_recovery() {
+        let config = StallConfig::default();
+        let mut det = StallDetector::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(det.update(Some(128), Some(0), &config, at(0)), None);
+        assert_eq!(det.update(Some(128), Some(0), &config, at(3000)), None);
+        assert_eq!(
+            det.update(Some(128), Some(0), &config, at(6000)),
+            Some(StallChange::Stalled)
+        );
+        // Only reported once
+        assert_eq!(det.update(Some(128), Some(0), &config, at(8000)), None);
+        assert!(det.is_stalled());
+        assert_eq!(
+            det.update(Some(128), Some(900), &config, at(10000)),
+            Some(StallChange::Recovered)
+        );
+        assert!(!det.is_stalled());
+    }
+
+    #[test]
+    fn test_stall_cleared_below_min_pwm() {
+        let config = StallConfig::default();
+        let mut det = StallDetector::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        det.update(Some(128), Some(0), &config, at(0));
+        det.update(Some(128), Some(0), &config, at(6000));
+        assert!(det.is_stalled());
+        assert_eq!(
+            det.update(Some(30), Some(0), &config, at(7000)),
+            Some(StallChange::Recovered)
+        );
+        assert!(!det.is_stalled());
+        assert_eq!(det.update(Some(30), Some(0), &config, at(8000)), None);
+    }
+
+    #[test]
+    fn test_low_pwm_and_spin_up_not_flagged() {
+        let config = StallConfig::default();
+        let mut det = StallDetector::new();
+        let start = Instant::now();
+
+        // Stopped at low duty is normal
+        for s in 0..20 {
+            let now = start + Duration::from_secs(s);
+            assert_eq!(det.update(Some(30), Some(0), &config, now), None);
+        }
+        // A brief 0 RPM while spinning up resets once the fan turns
+        det.update(Some(200), Some(0), &config, start + Duration::from_secs(20));
+        det.update(
+            Some(200),
+            Some(500),
+            &config,
+            start + Duration::from_secs(22),
+        );
+        let later = start + Duration::from_secs(27);
+        assert_eq!(det.update(Some(200), Some(0), &config, later), None);
+        assert!(!det.is_stalled());
+    }
+}
@@ -0,0 +1,471 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! System load readings from procfs.
+//!
+//! Load averages come from `/proc/loadavg`, CPU utilisation from the
+//! `cpu` lines of `/proc/stat`, CPU pressure from `/proc/pressure/cpu` and
+//! running processes from `/proc/<pid>/comm` and `/proc/<pid>/cmdline`.
+//! Like [`HwmonRoot`], the procfs mount point can be pointed at a fake
+//! tree for tests.
+//!
+//! [`LoadSensors`] turns these into sensors with ids like `load/cpu` that
+//! curves can use as inputs next to temperatures. Load rises as soon as a
+//! compile starts, seconds before the heat reaches a temperature sensor.
+//!
+//! [`HwmonRoot`]: crate::hwmon::HwmonRoot
+
+use crate::curve::InputUnit;
+use crate::hwmon::TempStatus;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default procfs mount point.
+pub const DEFAULT_PROCFS_ROOT: &str = "/proc";
+
+/// Id prefix for load sensors, e.g. "load/cpu".
+pub const LOAD_SENSOR_PREFIX: &str = "load/";
+
+/// The procfs tree load is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcRoot {
+    root: PathBuf,
+}
+
+impl ProcRoot {
+    /// Create a root from a procfs mount point (e.g. "/proc").
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The procfs mount point this root was created from.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// The 1, 5 and 15 minute load averages.
+    pub fn load_average(&self) -> io::Result<[f64; 3]> {
+        let text = fs::read_to_string(self.root.join("loadavg"))?;
+        let mut fields = text.split_whitespace().map(str::parse::<f64>);
+        let mut load = [0.0; 3];
+        for value in &mut load {
+            *value = fields
+                .next()
+                .and_then(Result::ok)
+                .ok_or_else(|| invalid("malformed loadavg"))?;
+        }
+        Ok(load)
+    }
+
+    /// Cumulative CPU time counters across all CPUs.
+    pub fn cpu_times(&self) -> io::Result<CpuTimes> {
+        self.all_cpu_times().map(|(total, _)| total)
+    }
+
+    /// Cumulative CPU time counters across all CPUs and for each CPU.
+    pub fn all_cpu_times(&self) -> io::Result<(CpuTimes, Vec<CpuTimes>)> {
+        let text = fs::read_to_string(self.root.join("stat"))?;
+        let mut total = None;
+        let mut cores = Vec::new();
+        for line in text.lines().filter(|l| l.starts_with("cpu")) {
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default();
+            let times = parse_cpu_times(fields)?;
+            if name == "cpu" {
+                total = Some(times);
+            } else {
+                cores.push(times);
+            }
+        }
+        let total = total.ok_or_else(|| invalid("no cpu line in stat"))?;
+        Ok((total, cores))
+    }
+
+    /// Share of the last 10 seconds in which some runnable task was
+    /// waiting for a CPU, in percent (`some avg10` of PSI).
+    pub fn cpu_pressure(&self) -> io::Result<f64> {
+        let text = fs::read_to_string(self.root.join("pressure/cpu"))?;
+        text.lines()
+            .filter(|l| l.starts_with("some "))
+            .flat_map(str::split_whitespace)
+            .find_map(|field| field.strip_prefix("avg10="))
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| invalid("no avg10 in pressure/cpu"))
+    }
+
+    /// Names of running processes. Each process contributes its `comm`
+    /// (which the kernel truncates to 15 characters) and the file name of
+    /// its executable from the command line, so both short and long names
+    /// can be matched.
+    pub fn process_names(&self) -> io::Result<HashSet<String>> {
+        let mut names = HashSet::new();
+        for entry in fs::read_dir(&self.root)? {
+            let Ok(entry) = entry else { continue };
+            let file_name = entry.file_name();
+            let Some(pid) = file_name.to_str() else {
+                continue;
+            };
+            if !pid.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            // Processes can exit between listing and reading
+            let dir = entry.path();
+            if let Ok(comm) = fs::read_to_string(dir.join("comm")) {
+                names.insert(comm.trim_end().to_string());
+            }
+            if let Ok(cmdline) = fs::read(dir.join("cmdline")) {
+                let argv0 = cmdline.split(|&b| b == 0).next().unwrap_or_default();
+                let argv0 = String::from_utf8_lossy(argv0);
+                if let Some(exe) = argv0.rsplit('/').next().filter(|s| !s.is_empty()) {
+                    names.insert(exe.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+}
+
+impl Default for ProcRoot {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROCFS_ROOT)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parse the counters after the name on a `cpu` line of `/proc/stat`.
+fn parse_cpu_times<'a>(fields: impl Iterator<Item = &'a str>) -> io::Result<CpuTimes> {
+    let values: Vec<u64> = fields
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid("malformed cpu line in stat"))?;
+    if values.len() < 4 {
+        return Err(invalid("short cpu line in stat"));
+    }
+    // user nice system idle iowait irq softirq steal [guest guest_nice];
+    // guest time is already included in user and nice
+    let total: u64 = values.iter().take(8).sum();
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    Ok(CpuTimes { total, idle })
+}
+
+/// Cumulative CPU time in clock ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub total: u64,
+    /// Idle plus iowait
+    pub idle: u64,
+}
+
+/// Turns successive [`CpuTimes`] samples into a utilisation percentage.
+#[derive(Debug, Clone, Default)]
+pub struct CpuUsage {
+    last: Option<CpuTimes>,
+}
+
+impl CpuUsage {
+    /// A tracker waiting for its first sample.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a sample. Returns the busy percentage since the previous
+    /// sample, or `None` for the first one.
+    pub fn update(&mut self, times: CpuTimes) -> Option<f64> {
+        let last = self.last.replace(times)?;
+        let total = times.total.saturating_sub(last.total);
+        if total == 0 {
+            return None;
+        }
+        let idle = times.idle.saturating_sub(last.idle).min(total);
+        Some(100.0 * (total - idle) as f64 / total as f64)
+    }
+}
+
+/// A sensor computed from procfs rather than read from hwmon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadSensor {
+    /// Utilisation of all CPUs together
+    Cpu,
+    /// Utilisation of the busiest CPU, which catches single-threaded
+    /// bursts the average hides
+    CpuMax,
+    /// 1-minute load average
+    LoadAvg,
+    /// CPU pressure over the last 10 seconds
+    CpuPressure,
+}
+
+impl LoadSensor {
+    pub const ALL: [LoadSensor; 4] = [
+        LoadSensor::Cpu,
+        LoadSensor::CpuMax,
+        LoadSensor::LoadAvg,
+        LoadSensor::CpuPressure,
+    ];
+
+    /// The sensor id curves use, e.g. "load/cpu".
+    pub fn id(self) -> String {
+        let name = match self {
+            LoadSensor::Cpu => "cpu",
+            LoadSensor::CpuMax => "cpu_max",
+            LoadSensor::LoadAvg => "loadavg",
+            LoadSensor::CpuPressure => "cpu_pressure",
+        };
+        format!("{LOAD_SENSOR_PREFIX}{name}")
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LoadSensor::Cpu => "CPU utilisation",
+            LoadSensor::CpuMax => "Busiest CPU",
+            LoadSensor::LoadAvg => "Load average",
+            LoadSensor::CpuPressure => "CPU pressure",
+        }
+    }
+
+    pub fn unit(self) -> InputUnit {
+        match self {
+            LoadSensor::LoadAvg => InputUnit::Load,
+            _ => InputUnit::Percent,
+        }
+    }
+
+    /// Look up a sensor by id.
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.id() == id)
+    }
+}
+
+/// The load sensors available on this system, read once per control-loop
+/// tick since utilisation is measured between reads.
+#[derive(Debug, Clone)]
+pub struct LoadSensors {
+    proc: ProcRoot,
+    available: Vec<LoadSensor>,
+    total: CpuUsage,
+    cores: Vec<CpuUsage>,
+    /// Whether utilisation has produced a reading yet
+    cpu_read: bool,
+    readings: HashMap<String, f64>,
+}
+
+impl LoadSensors {
+    /// Probe which sensors `proc` can provide and take the first
+    /// utilisation sample. CPU pressure needs a kernel with PSI enabled.
+    pub fn new(proc: ProcRoot) -> Self {
+        let available = LoadSensor::ALL
+            .into_iter()
+            .filter(|sensor| match sensor {
+                LoadSensor::Cpu | LoadSensor::CpuMax => proc.cpu_times().is_ok(),
+                LoadSensor::LoadAvg => proc.load_average().is_ok(),
+                LoadSensor::CpuPressure => proc.cpu_pressure().is_ok(),
+            })
+            .collect();
+        let mut sensors = Self {
+            proc,
+            available,
+            total: CpuUsage::new(),
+            cores: Vec::new(),
+            cpu_read: false,
+            readings: HashMap::new(),
+        };
+        // Utilisation is measured between samples, so start now for the
+        // first update to have a reading
+        sensors.sample_cpu();
+        sensors
+    }
+
+    /// Where the readings come from.
+    pub fn proc(&self) -> &ProcRoot {
+        &self.proc
+    }
+
+    pub fn available(&self) -> &[LoadSensor] {
+        &self.available
+    }
+
+    /// Whether `id` is a utilisation sensor that hasn't produced its first
+    /// reading yet, e.g. because no clock tick passed between samples.
+    pub fn is_warming_up(&self, id: &str) -> bool {
+        let cpu = matches!(
+            LoadSensor::from_id(id),
+            Some(LoadSensor::Cpu | LoadSensor::CpuMax)
+        );
+        cpu && !self.cpu_read && self.available.contains(&LoadSensor::Cpu)
+    }
+
+    /// Take new readings. Sensors that fail to read are left out.
+    pub fn update(&mut self) {
+        self.readings.clear();
+        self.sample_cpu();
+        let has = |sensor| self.available.contains(&sensor);
+        if has(LoadSensor::LoadAvg) {
+            if let Ok(load) = self.proc.load_average() {
+                self.readings.insert(LoadSensor::LoadAvg.id(), load[0]);
+            }
+        }
+        if has(LoadSensor::CpuPressure) {
+            if let Ok(pressure) = self.proc.cpu_pressure() {
+                self.readings.insert(LoadSensor::CpuPressure.id(), pressure);
+            }
+        }
+    }
+
+    /// Feed the utilisation trackers and record their readings.
+    fn sample_cpu(&mut self) {
+        let has = |sensor| self.available.contains(&sensor);
+        if !has(LoadSensor::Cpu) && !has(LoadSensor::CpuMax) {
+            return;
+        }
+        let Ok((total, cores)) = self.proc.all_cpu_times() else {
+            return;
+        };
+        // CPUs coming online start a fresh tracker
+        self.cores.resize_with(cores.len(), CpuUsage::new);
+        if let Some(pct) = self.total.update(total) {
+            self.readings.insert(LoadSensor::Cpu.id(), pct);
+            self.cpu_read = true;
+        }
+        let busiest = self
+            .cores
+            .iter_mut()
+            .zip(cores)
+            .filter_map(|(usage, times)| usage.update(times))
+            .reduce(f64::max);
+        if let Some(pct) = busiest {
+            self.readings.insert(LoadSensor::CpuMax.id(), pct);
+        }
+    }
+
+    /// Readings from the last [`LoadSensors::update`], keyed by sensor id.
+    pub fn readings(&self) -> &HashMap<String, f64> {
+        &self.readings
+    }
+
+    /// Statuses for the available sensors, listed with the temperatures.
+    pub fn statuses(&self) -> Vec<TempStatus> {
+        self.available
+            .iter()
+            .map(|&sensor| {
+                let id = sensor.id();
+                TempStatus {
+                    temp_c: self.readings.get(&id).copied(),
+                    legacy_id: id.clone(),
+                    id,
+                    label: Some(sensor.label().to_string()),
+                    hwmon_name: "load".to_string(),
+                    filtered_c: None,
+                    crit_c: None,
+                    max_c: None,
+                    unit: sensor.unit(),
+                    hidden: false,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeProcfs;
+
+    #[test]
+    fn test_load_and_cpu() {
+        let procfs = FakeProcfs::new().unwrap();
+        let proc = procfs.root();
+        procfs
+            .write("loadavg", "2.50 1.25 0.75 3/812 41234\n")
+            .unwrap();
+        assert_eq!(proc.load_average().unwrap(), [2.5, 1.25, 0.75]);
+
+        let mut usage = CpuUsage::new();
+        procfs
+            .write("stat", "cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 1 2 3 4\n")
+            .unwrap();
+        assert_eq!(usage.update(proc.cpu_times().unwrap()), None);
+        procfs
+            .write("stat", "cpu  250 0 150 800 100 0 0 0 0 0\n")
+            .unwrap();
+        assert_eq!(usage.update(proc.cpu_times().unwrap()), Some(200.0 / 3.0));
+    }
+
+    #[test]
+    fn test_load_sensors() {
+        let procfs = FakeProcfs::new().unwrap();
+        procfs
+            .write("loadavg", "3.00 2.00 1.00 3/812 41234\n")
+            .unwrap();
+        procfs
+            .write(
+                "stat",
+                "cpu  200 0 0 200 0\ncpu0 100 0 0 100 0\ncpu1 100 0 0 100 0\n",
+            )
+            .unwrap();
+        let mut load = LoadSensors::new(procfs.root());
+        // No PSI in the fake tree
+        assert_eq!(
+            load.available(),
+            &[LoadSensor::Cpu, LoadSensor::CpuMax, LoadSensor::LoadAvg]
+        );
+
+        // No time has passed since the first sample
+        load.update();
+        assert_eq!(load.readings().get("load/cpu"), None);
+        assert!(load.is_warming_up("load/cpu"));
+        assert_eq!(load.readings().get("load/loadavg"), Some(&3.0));
+
+        // cpu0 fully busy, cpu1 idle
+        procfs
+            .write(
+                "stat",
+                "cpu  300 0 0 300 0\ncpu0 200 0 0 100 0\ncpu1 100 0 0 200 0\n",
+            )
+            .unwrap();
+        load.update();
+        assert_eq!(load.readings().get("load/cpu"), Some(&50.0));
+        assert_eq!(load.readings().get("load/cpu_max"), Some(&100.0));
+        assert!(!load.is_warming_up("load/cpu"));
+        let status = load.statuses();
+        assert_eq!(status[0].unit, InputUnit::Percent);
+        assert_eq!(status[2].unit, InputUnit::Load);
+
+        procfs
+            .write(
+                "pressure/cpu",
+                "some avg10=12.50 avg60=3.00 avg300=1.00 total=1234\n",
+            )
+            .unwrap();
+        assert_eq!(procfs.root().cpu_pressure().unwrap(), 12.5);
+        assert_eq!(
+            LoadSensor::from_id("load/cpu_pressure"),
+            Some(LoadSensor::CpuPressure)
+        );
+    }
+
+    #[test]
+    fn test_process_names() {
+        let procfs = FakeProcfs::new().unwrap();
+        procfs
+            .add_process(1, "systemd", &["/sbin/init", "splash"])
+            .unwrap();
+        procfs
+            .add_process(4242, "blender", &["/usr/bin/blender", "scene.blend"])
+            .unwrap();
+        procfs
+            .add_process(4300, "factorio-headle", &["./factorio-headless"])
+            .unwrap();
+        fs::create_dir_all(procfs.path().join("sys")).unwrap();
+
+        let names = procfs.root().process_names().unwrap();
+        assert!(names.contains("blender"));
+        assert!(names.contains("init"));
+        assert!(names.contains("factorio-headless"));
+        assert!(!names.contains("sys"));
+    }
+}
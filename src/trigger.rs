@@ -0,0 +1,411 @@
+// Copyright (c) 2026 Pegasus Heavy Industries LLC
+// Licensed under the MIT License
+
+//! Profile triggers from system activity.
+//!
+//! A trigger switches to a profile while some process is running or the
+//! load average or CPU utilisation is above a threshold, so the fans can
+//! spin up before the temperatures climb. When the condition has been
+//! clear for a while the daemon puts back whatever was active before.
+//! Triggers take precedence over the schedule.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Default time a condition must stay clear before a trigger releases.
+pub const DEFAULT_CLEAR_SECS: u64 = 30;
+
+/// Trigger settings (`[triggers]` in the config file).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriggerConfig {
+    /// Whether rules are evaluated at all.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Rules; where several fire, the highest priority wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<TriggerRule>,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl TriggerConfig {
+    /// Whether there is anything to evaluate.
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.rules.is_empty()
+    }
+
+    /// Whether any rule looks at running processes.
+    pub fn needs_processes(&self) -> bool {
+        self.rules.iter().any(|r| !r.processes.is_empty())
+    }
+}
+
+/// Switch to a profile while a condition holds. A rule with several
+/// conditions fires when any of them holds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriggerRule {
+    /// Name for logs and status; defaults to a description of the
+    /// conditions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Profile to switch to while the rule fires.
+    pub profile: String,
+
+    /// Process names, matched against `comm` and the executable's file
+    /// name, e.g. "blender" or "cargo".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<String>,
+
+    /// 1-minute load average threshold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_above: Option<f64>,
+
+    /// CPU utilisation threshold in percent, across all CPUs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_above: Option<f64>,
+
+    /// Seconds the condition must hold before the rule fires.
+    #[serde(default)]
+    pub delay_secs: u64,
+
+    /// Seconds the condition must stay clear before the rule releases.
+    #[serde(default = "default_clear_secs")]
+    pub clear_secs: u64,
+
+    /// Higher wins where several rules fire; ties go to the earlier rule.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl TriggerRule {
+    /// Display name, e.g. "render" or "blender running or load > 6".
+    pub fn describe(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let mut parts = Vec::new();
+        if !self.processes.is_empty() {
+            parts.push(format!("{} running", self.processes.join("/")));
+        }
+        if let Some(load) = self.load_above {
+            parts.push(format!("load > {load}"));
+        }
+        if let Some(cpu) = self.cpu_above {
+            parts.push(format!("cpu > {cpu}%"));
+        }
+        parts.join(" or ")
+    }
+
+    /// Check that the rule has a condition and sensible thresholds.
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.describe();
+        if self.processes.is_empty() && self.load_above.is_none() && self.cpu_above.is_none() {
+            return Err(format!(
+                "Trigger for profile '{}' has no processes, load_above or cpu_above",
+                self.profile
+            ));
+        }
+        for (field, value) in [
+            ("load_above", self.load_above),
+            ("cpu_above", self.cpu_above),
+        ] {
+            if value.is_some_and(|v| !v.is_finite()) {
+                return Err(format!("Trigger '{name}': {field} must be a finite number"));
+            }
+        }
+        if self.load_above.is_some_and(|l| l < 0.0) {
+            return Err(format!("Trigger '{name}': load_above must not be negative"));
+        }
+        if self.cpu_above.is_some_and(|c| !(0.0..=100.0).contains(&c)) {
+            return Err(format!(
+                "Trigger '{name}': cpu_above must be between 0 and 100"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the condition holds in `sample`.
+    pub fn matches(&self, sample: &SystemSample) -> bool {
+        self.processes.iter().any(|p| sample.processes.contains(p))
+            || self
+                .load_above
+                .zip(sample.load)
+                .is_some_and(|(threshold, load)| load > threshold)
+            || self
+                .cpu_above
+                .zip(sample.cpu_percent)
+                .is_some_and(|(threshold, cpu)| cpu > threshold)
+    }
+}
+
+/// What the system is doing, as far as the rules care. Readings that
+/// aren't needed or couldn't be taken are left empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemSample {
+    pub processes: HashSet<String>,
+    /// 1-minute load average
+    pub load: Option<f64>,
+    /// CPU utilisation in percent
+    pub cpu_percent: Option<f64>,
+}
+
+/// Result of a [`Triggers::update`] that needs acting on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerChange {
+    /// A rule started winning; switch to its profile.
+    Engage { rule: String, profile: String },
+    /// No rule fires any more; put back what was active before.
+    Release {
+        rule: String,
+        /// Assignments were changed by hand while the rule was engaged
+        overridden: bool,
+    },
+}
+
+/// The trigger in force, as reported in status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerStatus {
+    /// Rule that fired, by [`TriggerRule::describe`]
+    pub rule: String,
+    /// Profile it switched to
+    pub profile: String,
+    /// Assignments were changed by hand since it fired
+    pub overridden: bool,
+}
+
+impl TriggerStatus {
+    /// One-line summary, e.g. "render (blender running), overridden".
+    pub fn describe(&self) -> String {
+        let mut s = format!("{} ({})", self.profile, self.rule);
+        if self.overridden {
+            s.push_str(", overridden");
+        }
+        s
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RuleState {
+    met_since: Option<Instant>,
+    clear_since: Option<Instant>,
+    firing: bool,
+}
+
+impl RuleState {
+    fn update(&mut self, rule: &TriggerRule, met: bool, now: Instant) {
+        if met {
+            self.clear_since = None;
+            let since = *self.met_since.get_or_insert(now);
+            if now.saturating_duration_since(since) >= Duration::from_secs(rule.delay_secs) {
+                self.firing = true;
+            }
+        } else {
+            self.met_since = None;
+            if self.firing {
+                let since = *self.clear_since.get_or_insert(now);
+                if now.saturating_duration_since(since) >= Duration::from_secs(rule.clear_secs) {
+                    self.firing = false;
+                    self.clear_since = None;
+                }
+            }
+        }
+    }
+}
+
+/// Tracks which rules fire and which one is engaged.
+#[derive(Debug, Clone, Default)]
+pub struct Triggers {
+    states: Vec<RuleState>,
+    /// Index of the winning rule
+    engaged: Option<usize>,
+    overridden: bool,
+}
+
+impl Triggers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate the rules against `sample`. Returns a change when a
+    /// different rule (or none) wins than on the last call.
+    pub fn update(
+        &mut self,
+        config: &TriggerConfig,
+        sample: &SystemSample,
+        now: Instant,
+    ) -> Option<TriggerChange> {
+        self.states
+            .resize_with(config.rules.len(), RuleState::default);
+        for (rule, state) in config.rules.iter().zip(&mut self.states) {
+            state.update(rule, rule.matches(sample), now);
+        }
+        let winner = config
+            .rules
+            .iter()
+            .zip(&self.states)
+            .enumerate()
+            .filter(|(_, (_, state))| state.firing)
+            // max_by_key keeps the last maximum; reverse so ties go to the first
+            .rev()
+            .max_by_key(|(_, (rule, _))| rule.priority)
+            .map(|(i, _)| i);
+        if winner == self.engaged {
+            return None;
+        }
+
+        let previous = std::mem::replace(&mut self.engaged, winner);
+        let overridden = std::mem::take(&mut self.overridden);
+        match winner {
+            Some(i) => Some(TriggerChange::Engage {
+                rule: config.rules[i].describe(),
+                profile: config.rules[i].profile.clone(),
+            }),
+            None => Some(TriggerChange::Release {
+                rule: previous
+                    .and_then(|i| config.rules.get(i))
+                    .map_or_else(|| "trigger".to_string(), TriggerRule::describe),
+                overridden,
+            }),
+        }
+    }
+
+    /// Whether some rule is engaged.
+    pub fn is_engaged(&self) -> bool {
+        self.engaged.is_some()
+    }
+
+    /// Note a manual change; it is kept when the trigger releases.
+    pub fn override_manual(&mut self) {
+        if self.engaged.is_some() {
+            self.overridden = true;
+        }
+    }
+
+    /// Whether a manual change has been made since a rule engaged.
+    pub fn is_overridden(&self) -> bool {
+        self.overridden
+    }
+
+    /// Forget all rule state, e.g. after the config is reloaded.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn status(&self, config: &TriggerConfig) -> Option<TriggerStatus> {
+        let rule = config.rules.get(self.engaged?)?;
+        Some(TriggerStatus {
+            rule: rule.describe(),
+            profile: rule.profile.clone(),
+            overridden: self.overridden,
+        })
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_clear_secs() -> u64 {
+    DEFAULT_CLEAR_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TriggerConfig {
+        toml::from_str(
+            r#"
+            [[rules]]
+            profile = "render"
+            processes = ["blender", "cargo"]
+            clear_secs = 10
+
+            [[rules]]
+            name = "busy"
+            profile = "loud"
+            cpu_above = 80.0
+            delay_secs = 5
+            clear_secs = 0
+            priority = 5
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn sample(processes: &[&str], cpu: f64) -> SystemSample {
+        SystemSample {
+            processes: processes.iter().map(|p| p.to_string()).collect(),
+            load: None,
+            cpu_percent: Some(cpu),
+        }
+    }
+
+    #[test]
+    fn test_engage_and_release() {
+        let cfg = config();
+        assert!(cfg.rules.iter().all(|r| r.validate().is_ok()));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut triggers = Triggers::new();
+
+        assert_eq!(triggers.update(&cfg, &sample(&["bash"], 10.0), at(0)), None);
+        assert_eq!(
+            triggers.update(&cfg, &sample(&["cargo"], 10.0), at(1)),
+            Some(TriggerChange::Engage {
+                rule: "blender/cargo running".to_string(),
+                profile: "render".to_string(),
+            })
+        );
+        // Busy CPU has to last before the higher-priority rule takes over
+        assert_eq!(
+            triggers.update(&cfg, &sample(&["cargo"], 95.0), at(2)),
+            None
+        );
+        assert!(matches!(
+            triggers.update(&cfg, &sample(&["cargo"], 95.0), at(7)),
+            Some(TriggerChange::Engage { profile, .. }) if profile == "loud"
+        ));
+        assert!(matches!(
+            triggers.update(&cfg, &sample(&[], 10.0), at(8)),
+            Some(TriggerChange::Engage { profile, .. }) if profile == "render"
+        ));
+
+        // Held until the process has been gone for clear_secs
+        triggers.override_manual();
+        assert_eq!(triggers.update(&cfg, &sample(&[], 10.0), at(17)), None);
+        assert!(triggers.status(&cfg).unwrap().overridden);
+        assert_eq!(
+            triggers.update(&cfg, &sample(&[], 10.0), at(18)),
+            Some(TriggerChange::Release {
+                rule: "blender/cargo running".to_string(),
+                overridden: true,
+            })
+        );
+        assert_eq!(triggers.status(&cfg), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let rule = |toml: &str| toml::from_str::<TriggerRule>(toml).unwrap().validate();
+        assert!(rule(r#"profile = "p""#).is_err());
+        assert!(rule("profile = \"p\"\nload_above = -1.0").is_err());
+        assert!(rule("profile = \"p\"\ncpu_above = 150.0").is_err());
+        assert!(rule("profile = \"p\"\ncpu_above = 100.0").is_ok());
+        assert_eq!(
+            rule("profile = \"p\"\nload_above = inf"),
+            Err("Trigger 'load > inf': load_above must be a finite number".to_string())
+        );
+        assert!(rule("profile = \"p\"\nload_above = 4.0").is_ok());
+    }
+}